@@ -2,13 +2,17 @@
 //! another compatible command (f.x. clippy) in a background thread and provide
 //! LSP diagnostics based on the output of the command.
 
-use std::{fmt, io, process::Command, time::Duration};
+mod overlay;
+
+use std::{collections::HashMap, fmt, io, process::Command, time::Duration};
 
 use crossbeam_channel::{never, select, unbounded, Receiver, Sender};
 use paths::AbsPathBuf;
 use serde::Deserialize;
 use stdx::process::streaming_output;
 
+use crate::overlay::Overlay;
+
 pub use cargo_metadata::diagnostic::{
     Applicability, Diagnostic, DiagnosticCode, DiagnosticLevel, DiagnosticSpan,
     DiagnosticSpanMacroExpansion,
@@ -42,14 +46,39 @@ impl fmt::Display for FlycheckConfig {
     }
 }
 
+/// What a check should cover.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CheckScope {
+    /// The whole workspace (`--workspace`).
+    Workspace,
+    /// Just the given packages, as passed to `-p`.
+    Packages(Vec<String>),
+}
+
+impl CheckScope {
+    fn merge(&mut self, other: CheckScope) {
+        match (&mut *self, other) {
+            (CheckScope::Packages(packages), CheckScope::Packages(other)) => {
+                for package in other {
+                    if !packages.contains(&package) {
+                        packages.push(package);
+                    }
+                }
+            }
+            _ => *self = CheckScope::Workspace,
+        }
+    }
+}
+
 /// Flycheck wraps the shared state and communication machinery used for
 /// running `cargo check` (or other compatible command) and providing
 /// diagnostics based on the output.
 /// The spawned thread is shut down when this struct is dropped.
 #[derive(Debug)]
 pub struct FlycheckHandle {
+    id: usize,
     // XXX: drop order is significant
-    sender: Sender<Restart>,
+    sender: Sender<StateChange>,
     _thread: jod_thread::JoinHandle,
 }
 
@@ -61,23 +90,50 @@ impl FlycheckHandle {
         workspace_root: AbsPathBuf,
     ) -> FlycheckHandle {
         let actor = FlycheckActor::new(id, sender, config, workspace_root);
-        let (sender, receiver) = unbounded::<Restart>();
+        let (sender, receiver) = unbounded::<StateChange>();
         let thread = jod_thread::Builder::new()
             .name("Flycheck".to_owned())
             .spawn(move || actor.run(receiver))
             .expect("failed to spawn thread");
-        FlycheckHandle { sender, _thread: thread }
+        FlycheckHandle { id, sender, _thread: thread }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
     }
 
     /// Schedule a re-start of the cargo check worker.
     pub fn update(&self) {
-        self.sender.send(Restart).unwrap();
+        self.restart(CheckScope::Workspace);
+    }
+
+    /// Schedule a re-start of the cargo check worker for `scope`. If any of
+    /// the unsaved files differ from their contents on disk, the check runs
+    /// in a temporary copy of the workspace which has them written out.
+    pub fn restart(&self, scope: CheckScope) {
+        self.sender.send(StateChange::Restart(scope)).unwrap();
+    }
+
+    /// Updates the contents of unsaved editor buffers, `None` meaning that
+    /// the buffer was closed. They are picked up by the next check.
+    pub fn update_unsaved_files(&self, changes: Vec<(AbsPathBuf, Option<Vec<u8>>)>) {
+        self.sender.send(StateChange::UnsavedFiles(changes)).unwrap();
+    }
+
+    /// Stop the check that is currently running, if any.
+    pub fn cancel(&self) {
+        self.sender.send(StateChange::Cancel).unwrap();
     }
 }
 
 pub enum Message {
     /// Request adding a diagnostic with fixes included to a file
-    AddDiagnostic { workspace_root: AbsPathBuf, diagnostic: Diagnostic },
+    AddDiagnostic {
+        workspace_root: AbsPathBuf,
+        /// Id of the package the diagnostic was reported for, if known.
+        package_id: Option<String>,
+        diagnostic: Diagnostic,
+    },
 
     /// Request check progress notification to client
     Progress {
@@ -90,9 +146,10 @@ pub enum Message {
 impl fmt::Debug for Message {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Message::AddDiagnostic { workspace_root, diagnostic } => f
+            Message::AddDiagnostic { workspace_root, package_id, diagnostic } => f
                 .debug_struct("AddDiagnostic")
                 .field("workspace_root", workspace_root)
+                .field("package_id", package_id)
                 .field("diagnostic_code", &diagnostic.code.as_ref().map(|it| &it.code))
                 .finish(),
             Message::Progress { id, progress } => {
@@ -104,13 +161,17 @@ impl fmt::Debug for Message {
 
 #[derive(Debug)]
 pub enum Progress {
-    DidStart,
+    DidStart(CheckScope),
     DidCheckCrate(String),
     DidFinish(io::Result<()>),
     DidCancel,
 }
 
-struct Restart;
+enum StateChange {
+    Restart(CheckScope),
+    UnsavedFiles(Vec<(AbsPathBuf, Option<Vec<u8>>)>),
+    Cancel,
+}
 
 struct FlycheckActor {
     id: usize,
@@ -123,10 +184,16 @@ struct FlycheckActor {
     /// have to wrap sub-processes output handling in a thread and pass messages
     /// back over a channel.
     cargo_handle: Option<CargoHandle>,
+    /// Contents of the unsaved editor buffers.
+    unsaved_files: HashMap<AbsPathBuf, Vec<u8>>,
+    /// The temporary workspace copy with the unsaved files, once there were any.
+    overlay: Option<Overlay>,
+    /// Whether the current check runs in the overlay.
+    in_overlay: bool,
 }
 
 enum Event {
-    Restart(Restart),
+    RequestStateChange(StateChange),
     CheckEvent(Option<CargoMessage>),
 }
 
@@ -137,40 +204,83 @@ impl FlycheckActor {
         config: FlycheckConfig,
         workspace_root: AbsPathBuf,
     ) -> FlycheckActor {
-        FlycheckActor { id, sender, config, workspace_root, cargo_handle: None }
+        FlycheckActor {
+            id,
+            sender,
+            config,
+            workspace_root,
+            cargo_handle: None,
+            unsaved_files: HashMap::new(),
+            overlay: None,
+            in_overlay: false,
+        }
     }
     fn progress(&self, progress: Progress) {
         self.send(Message::Progress { id: self.id, progress });
     }
-    fn next_event(&self, inbox: &Receiver<Restart>) -> Option<Event> {
+    fn next_event(&self, inbox: &Receiver<StateChange>) -> Option<Event> {
         let check_chan = self.cargo_handle.as_ref().map(|cargo| &cargo.receiver);
         select! {
-            recv(inbox) -> msg => msg.ok().map(Event::Restart),
+            recv(inbox) -> msg => msg.ok().map(Event::RequestStateChange),
             recv(check_chan.unwrap_or(&never())) -> msg => Some(Event::CheckEvent(msg.ok())),
         }
     }
-    fn run(mut self, inbox: Receiver<Restart>) {
+    fn run(mut self, inbox: Receiver<StateChange>) {
         while let Some(event) = self.next_event(&inbox) {
             match event {
-                Event::Restart(Restart) => {
-                    while let Ok(Restart) = inbox.recv_timeout(Duration::from_millis(50)) {}
+                Event::RequestStateChange(StateChange::Cancel) => self.cancel_check_process(),
+                Event::RequestStateChange(StateChange::UnsavedFiles(changes)) => {
+                    self.update_unsaved_files(changes)
+                }
+                Event::RequestStateChange(StateChange::Restart(scope)) => {
+                    // Whichever of restarting and cancelling was requested
+                    // last wins.
+                    let mut restart = Some(scope);
+                    while let Ok(next) = inbox.recv_timeout(Duration::from_millis(50)) {
+                        restart = match (restart, next) {
+                            (Some(mut scope), StateChange::Restart(next)) => {
+                                scope.merge(next);
+                                Some(scope)
+                            }
+                            (None, StateChange::Restart(next)) => Some(next),
+                            (restart, StateChange::UnsavedFiles(changes)) => {
+                                self.update_unsaved_files(changes);
+                                restart
+                            }
+                            (_, StateChange::Cancel) => None,
+                        };
+                    }
+                    let scope = match restart {
+                        Some(it) => it,
+                        None => {
+                            self.cancel_check_process();
+                            continue;
+                        }
+                    };
 
                     self.cancel_check_process();
 
-                    let command = self.check_command();
+                    let scope = match self.config {
+                        FlycheckConfig::CargoCommand { .. } => scope,
+                        FlycheckConfig::CustomCommand { .. } => CheckScope::Workspace,
+                    };
+                    self.in_overlay = self.update_overlay();
+                    let command = self.check_command(&scope);
                     tracing::info!("restart flycheck {:?}", command);
                     self.cargo_handle = Some(CargoHandle::spawn(command));
-                    self.progress(Progress::DidStart);
+                    self.progress(Progress::DidStart(scope));
                 }
                 Event::CheckEvent(None) => {
                     // Watcher finished, replace it with a never channel to
                     // avoid busy-waiting.
                     let cargo_handle = self.cargo_handle.take().unwrap();
+                    let command = cargo_handle.command.clone();
                     let res = cargo_handle.join();
-                    if res.is_err() {
+                    if let Err(err) = &res {
                         tracing::error!(
-                            "Flycheck failed to run the following command: {:?}",
-                            self.check_command()
+                            "Flycheck failed to run the following command: {}: {}",
+                            command,
+                            err
                         );
                     }
                     self.progress(Progress::DidFinish(res));
@@ -180,10 +290,14 @@ impl FlycheckActor {
                         self.progress(Progress::DidCheckCrate(msg.target.name));
                     }
 
-                    CargoMessage::Diagnostic(msg) => {
+                    CargoMessage::Diagnostic { package_id, mut diagnostic } => {
+                        if let Some(overlay) = self.active_overlay() {
+                            overlay.remap(&mut diagnostic, &self.workspace_root);
+                        }
                         self.send(Message::AddDiagnostic {
                             workspace_root: self.workspace_root.clone(),
-                            diagnostic: msg,
+                            package_id,
+                            diagnostic,
                         });
                     }
                 },
@@ -197,7 +311,44 @@ impl FlycheckActor {
             self.progress(Progress::DidCancel);
         }
     }
-    fn check_command(&self) -> Command {
+    fn update_unsaved_files(&mut self, changes: Vec<(AbsPathBuf, Option<Vec<u8>>)>) {
+        for (path, contents) in changes {
+            match contents {
+                Some(contents) => self.unsaved_files.insert(path, contents),
+                None => self.unsaved_files.remove(&path),
+            };
+        }
+    }
+    /// Writes the unsaved files to the overlay, returning whether the check
+    /// has to run in it.
+    fn update_overlay(&mut self) -> bool {
+        if self.unsaved_files.is_empty() && self.overlay.is_none() {
+            return false;
+        }
+        let overlay = match &mut self.overlay {
+            Some(it) => it,
+            None => match Overlay::new(&self.workspace_root) {
+                Ok(it) => self.overlay.insert(it),
+                Err(err) => {
+                    tracing::error!("failed to set up flycheck overlay: {}", err);
+                    return false;
+                }
+            },
+        };
+        match overlay.update(&self.unsaved_files) {
+            Ok(it) => it,
+            Err(err) => {
+                tracing::error!("failed to update flycheck overlay: {}", err);
+                // Start over with a fresh overlay next time.
+                self.overlay = None;
+                false
+            }
+        }
+    }
+    fn active_overlay(&self) -> Option<&Overlay> {
+        self.overlay.as_ref().filter(|_| self.in_overlay)
+    }
+    fn check_command(&self, scope: &CheckScope) -> Command {
         let mut cmd = match &self.config {
             FlycheckConfig::CargoCommand {
                 command,
@@ -210,9 +361,25 @@ impl FlycheckActor {
             } => {
                 let mut cmd = Command::new(toolchain::cargo());
                 cmd.arg(command);
-                cmd.current_dir(&self.workspace_root);
-                cmd.args(&["--workspace", "--message-format=json", "--manifest-path"])
-                    .arg(self.workspace_root.join("Cargo.toml").as_os_str());
+                match scope {
+                    CheckScope::Workspace => {
+                        cmd.arg("--workspace");
+                    }
+                    CheckScope::Packages(packages) => {
+                        for package in packages {
+                            cmd.args(&["-p", package]);
+                        }
+                    }
+                }
+                let root = match self.active_overlay() {
+                    Some(overlay) => {
+                        cmd.arg("--target-dir").arg(&overlay.target_dir);
+                        overlay.root.as_path()
+                    }
+                    None => self.workspace_root.as_ref(),
+                };
+                cmd.args(&["--message-format=json", "--manifest-path"])
+                    .arg(root.join("Cargo.toml").as_os_str());
 
                 if let Some(target) = target_triple {
                     cmd.args(&["--target", target.as_str()]);
@@ -240,7 +407,10 @@ impl FlycheckActor {
                 cmd
             }
         };
-        cmd.current_dir(&self.workspace_root);
+        match self.active_overlay() {
+            Some(overlay) => cmd.current_dir(&overlay.root),
+            None => cmd.current_dir(&self.workspace_root),
+        };
         cmd
     }
 
@@ -252,17 +422,20 @@ impl FlycheckActor {
 struct CargoHandle {
     thread: jod_thread::JoinHandle<io::Result<()>>,
     receiver: Receiver<CargoMessage>,
+    /// The command being run, for error messages.
+    command: String,
 }
 
 impl CargoHandle {
     fn spawn(command: Command) -> CargoHandle {
         let (sender, receiver) = unbounded();
         let actor = CargoActor::new(sender);
+        let command_text = format!("{:?}", command);
         let thread = jod_thread::Builder::new()
             .name("CargoHandle".to_owned())
             .spawn(move || actor.run(command))
             .expect("failed to spawn thread");
-        CargoHandle { thread, receiver }
+        CargoHandle { thread, receiver, command: command_text }
     }
 
     fn join(self) -> io::Result<()> {
@@ -309,12 +482,22 @@ impl CargoActor {
                                 self.sender.send(CargoMessage::CompilerArtifact(artifact)).unwrap();
                             }
                             cargo_metadata::Message::CompilerMessage(msg) => {
-                                self.sender.send(CargoMessage::Diagnostic(msg.message)).unwrap();
+                                self.sender
+                                    .send(CargoMessage::Diagnostic {
+                                        package_id: Some(msg.package_id.repr),
+                                        diagnostic: msg.message,
+                                    })
+                                    .unwrap();
                             }
                             _ => (),
                         },
                         JsonMessage::Rustc(message) => {
-                            self.sender.send(CargoMessage::Diagnostic(message)).unwrap();
+                            self.sender
+                                .send(CargoMessage::Diagnostic {
+                                    package_id: None,
+                                    diagnostic: message,
+                                })
+                                .unwrap();
                         }
                     }
                 }
@@ -340,7 +523,7 @@ impl CargoActor {
 
 enum CargoMessage {
     CompilerArtifact(cargo_metadata::Artifact),
-    Diagnostic(Diagnostic),
+    Diagnostic { package_id: Option<String>, diagnostic: Diagnostic },
 }
 
 #[derive(Deserialize)]
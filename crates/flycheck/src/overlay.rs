@@ -0,0 +1,292 @@
+//! A temporary copy of the workspace with unsaved editor buffers written out.
+//!
+//! We can't point `cargo` at in-memory file contents, so we mirror the
+//! workspace in a temporary directory instead. Only the directories leading
+//! to changed files are materialized, everything else is symlinked back to the
+//! original workspace. A separate target directory, kept for as long as the
+//! overlay lives, avoids fighting with the user's own builds over the real one.
+//!
+//! The overlay is updated in place between checks: only files whose contents
+//! changed are rewritten, and files which match the disk again are turned back
+//! into symlinks. The directory belongs to the current process and is removed
+//! when the overlay is dropped.
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeSet, HashMap},
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
+
+use paths::{AbsPath, AbsPathBuf};
+
+use crate::{Diagnostic, DiagnosticSpan};
+
+pub(crate) struct Overlay {
+    workspace_root: AbsPathBuf,
+    /// The directory holding both the mirror and the target directory.
+    base: PathBuf,
+    /// Mirror of the workspace root.
+    pub(crate) root: PathBuf,
+    pub(crate) target_dir: PathBuf,
+    /// Files written to the mirror, relative to the workspace root.
+    written: HashMap<PathBuf, Vec<u8>>,
+    /// Directories of the mirror which are real directories instead of
+    /// symlinks, relative to the workspace root.
+    materialized: BTreeSet<PathBuf>,
+}
+
+impl Overlay {
+    pub(crate) fn new(workspace_root: &AbsPath) -> io::Result<Overlay> {
+        let mut hasher = DefaultHasher::new();
+        workspace_root.hash(&mut hasher);
+        // Several servers may check the same workspace, so the directory is
+        // specific to this process.
+        let base = std::env::temp_dir().join(format!(
+            "rust-analyzer-flycheck-{}-{:x}",
+            std::process::id(),
+            hasher.finish() & 0xffff_ffff
+        ));
+        // Left behind by a process that had the same id.
+        if base.exists() {
+            fs::remove_dir_all(&base)?;
+        }
+        let mut overlay = Overlay {
+            workspace_root: workspace_root.to_path_buf(),
+            root: base.join("workspace"),
+            target_dir: base.join("target"),
+            base,
+            written: HashMap::new(),
+            materialized: BTreeSet::new(),
+        };
+        overlay.materialize(Path::new(""))?;
+        Ok(overlay)
+    }
+
+    /// Brings the mirror up to date with the contents of `files`. Returns
+    /// whether any of them differs from the disk, that is whether a check
+    /// needs to run in the overlay.
+    pub(crate) fn update(&mut self, files: &HashMap<AbsPathBuf, Vec<u8>>) -> io::Result<bool> {
+        let changed = files
+            .iter()
+            .filter(|(path, contents)| fs::read(path).map_or(true, |it| &it != *contents))
+            .filter_map(|(path, contents)| {
+                let rel = path.strip_prefix(&self.workspace_root)?.as_ref().to_path_buf();
+                Some((rel, contents.as_slice()))
+            })
+            .collect::<HashMap<_, _>>();
+
+        // Files which match the disk again point back to the workspace.
+        let stale = self
+            .written
+            .keys()
+            .filter(|rel| !changed.contains_key(*rel))
+            .cloned()
+            .collect::<Vec<_>>();
+        for rel in stale {
+            self.written.remove(&rel);
+            let dst = self.root.join(&rel);
+            remove_file(&dst)?;
+            let src = PathBuf::from(self.workspace_root.join(&rel));
+            if src.exists() {
+                symlink(&src, &dst, false)?;
+            }
+        }
+
+        // Pick up entries created or removed in the workspace since the
+        // directories were materialized.
+        for dir in self.materialized.clone() {
+            self.sync_dir(&dir)?;
+        }
+
+        for (rel, contents) in changed {
+            if self.written.get(&rel).map(Vec::as_slice) == Some(contents) {
+                continue;
+            }
+            if let Some(parent) = rel.parent() {
+                self.materialize(parent)?;
+            }
+            let dst = self.root.join(&rel);
+            remove_file(&dst)?;
+            fs::write(&dst, contents)?;
+            self.written.insert(rel, contents.to_vec());
+        }
+        Ok(!self.written.is_empty())
+    }
+
+    /// Turns `dir` and the directories leading to it into real directories.
+    fn materialize(&mut self, dir: &Path) -> io::Result<()> {
+        let mut rel = PathBuf::new();
+        for component in std::iter::once(Path::new("")).chain(dir.iter().map(Path::new)) {
+            rel.push(component);
+            if self.materialized.contains(&rel) {
+                continue;
+            }
+            let dst = self.root.join(&rel);
+            remove_file(&dst)?;
+            fs::create_dir_all(&dst)?;
+            self.materialized.insert(rel.clone());
+            self.sync_dir(&rel)?;
+        }
+        Ok(())
+    }
+
+    /// Symlinks the entries of the workspace directory `rel` which are missing
+    /// from its mirror, and removes the ones which are gone from the workspace.
+    fn sync_dir(&self, rel: &Path) -> io::Result<()> {
+        let (src, dst) = (PathBuf::from(self.workspace_root.join(rel)), self.root.join(rel));
+        if !src.is_dir() {
+            return Ok(());
+        }
+        for entry in fs::read_dir(&src)? {
+            let entry = entry?;
+            let dst = dst.join(entry.file_name());
+            if fs::symlink_metadata(&dst).is_err() {
+                symlink(&entry.path(), &dst, entry.file_type()?.is_dir())?;
+            }
+        }
+        for entry in fs::read_dir(&dst)? {
+            let name = entry?.file_name();
+            let child = rel.join(&name);
+            let ours = self.written.contains_key(&child) || self.materialized.contains(&child);
+            if !ours && fs::symlink_metadata(src.join(&name)).is_err() {
+                remove_file(&dst.join(&name))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rewrites paths pointing into the overlay to the original workspace.
+    pub(crate) fn remap(&self, diagnostic: &mut Diagnostic, workspace_root: &AbsPath) {
+        for span in &mut diagnostic.spans {
+            self.remap_span(span, workspace_root);
+        }
+        for child in &mut diagnostic.children {
+            self.remap(child, workspace_root);
+        }
+        if let Some(rendered) = &mut diagnostic.rendered {
+            if let (Some(from), Some(to)) = (self.root.to_str(), workspace_root.as_ref().to_str()) {
+                *rendered = rendered.replace(from, to);
+            }
+        }
+    }
+
+    fn remap_span(&self, span: &mut DiagnosticSpan, workspace_root: &AbsPath) {
+        if let Ok(rel) = Path::new(&span.file_name).strip_prefix(&self.root) {
+            span.file_name = workspace_root.as_ref().join(rel).to_string_lossy().into_owned();
+        }
+        if let Some(expansion) = &mut span.expansion {
+            self.remap_span(&mut expansion.span, workspace_root);
+            if let Some(def_site_span) = &mut expansion.def_site_span {
+                self.remap_span(def_site_span, workspace_root);
+            }
+        }
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_dir_all(&self.base) {
+            tracing::warn!("failed to remove flycheck overlay {}: {}", self.base.display(), err);
+        }
+    }
+}
+
+/// Removes the file or symlink at `path`, if there is one.
+fn remove_file(path: &Path) -> io::Result<()> {
+    let res = fs::remove_file(path);
+    // Directory symlinks have to be removed as directories.
+    #[cfg(windows)]
+    let res = res.or_else(|_| fs::remove_dir(path));
+    match res {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+fn symlink(src: &Path, dst: &Path, _is_dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+fn symlink(src: &Path, dst: &Path, is_dir: bool) -> io::Result<()> {
+    if is_dir {
+        std::os::windows::fs::symlink_dir(src, dst)
+    } else {
+        std::os::windows::fs::symlink_file(src, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace(name: &str) -> AbsPathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rust-analyzer-overlay-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src/nested")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]").unwrap();
+        fs::write(dir.join("src/lib.rs"), "mod nested;").unwrap();
+        fs::write(dir.join("src/nested/mod.rs"), "").unwrap();
+        AbsPathBuf::assert(dir)
+    }
+
+    fn files(files: &[(AbsPathBuf, &str)]) -> HashMap<AbsPathBuf, Vec<u8>> {
+        files.iter().map(|(path, text)| (path.clone(), text.as_bytes().to_vec())).collect()
+    }
+
+    fn is_symlink(path: &Path) -> bool {
+        fs::symlink_metadata(path).unwrap().file_type().is_symlink()
+    }
+
+    #[test]
+    fn writes_changed_files_and_links_the_rest() {
+        let ws = workspace("changed");
+        let mut overlay = Overlay::new(&ws).unwrap();
+
+        let unchanged = files(&[(ws.join("src/lib.rs"), "mod nested;")]);
+        assert!(!overlay.update(&unchanged).unwrap());
+
+        let changed = files(&[(ws.join("src/nested/mod.rs"), "fn f() {}")]);
+        assert!(overlay.update(&changed).unwrap());
+        let root = overlay.root.clone();
+        assert_eq!(fs::read(root.join("src/nested/mod.rs")).unwrap(), b"fn f() {}");
+        assert!(!is_symlink(&root.join("src/nested/mod.rs")));
+        assert!(!is_symlink(&root.join("src")));
+        assert!(is_symlink(&root.join("src/lib.rs")));
+        assert!(is_symlink(&root.join("Cargo.toml")));
+
+        // Saved, or reverted, files point back to the workspace.
+        assert!(!overlay.update(&HashMap::new()).unwrap());
+        assert!(is_symlink(&root.join("src/nested/mod.rs")));
+
+        let base = overlay.base.clone();
+        drop(overlay);
+        assert!(!base.exists());
+        fs::remove_dir_all(ws).unwrap();
+    }
+
+    #[test]
+    fn picks_up_new_and_removed_workspace_files() {
+        let ws = workspace("sync");
+        let mut overlay = Overlay::new(&ws).unwrap();
+        let changed = files(&[(ws.join("src/lib.rs"), "mod new;")]);
+        overlay.update(&changed).unwrap();
+
+        fs::write(ws.join("src/new.rs"), "").unwrap();
+        fs::remove_dir_all(ws.join("src/nested")).unwrap();
+        overlay.update(&changed).unwrap();
+        let root = overlay.root.clone();
+        assert!(is_symlink(&root.join("src/new.rs")));
+        assert!(fs::symlink_metadata(root.join("src/nested")).is_err());
+        assert_eq!(fs::read(root.join("src/lib.rs")).unwrap(), b"mod new;");
+
+        drop(overlay);
+        fs::remove_dir_all(ws).unwrap();
+    }
+}
@@ -28,6 +28,7 @@ mod has_source;
 
 pub mod diagnostics;
 pub mod db;
+pub mod term_search;
 
 mod display;
 
@@ -187,6 +188,17 @@ impl Crate {
         db.crate_graph()[self.id].display_name.clone()
     }
 
+    /// The path other crates can import `item` with, relative to this crate's
+    /// root. `None` if the item can't be reached from outside of the crate.
+    pub fn import_path_of(
+        self,
+        db: &dyn HirDatabase,
+        item: impl Into<ItemInNs>,
+    ) -> Option<import_map::ImportPath> {
+        let item: ItemInNs = item.into();
+        db.import_map(self.id).path_of(item.into()).cloned()
+    }
+
     pub fn query_external_importables(
         self,
         db: &dyn DefDatabase,
@@ -1294,6 +1306,36 @@ impl Function {
             .collect()
     }
 
+    /// The parameter types, without `self`, and the return type of the method
+    /// when called on `receiver`. The generic parameters of its impl or trait
+    /// are taken from `receiver`, or the first type it derefs to which has the
+    /// method.
+    pub fn method_signature_on(
+        self,
+        db: &dyn HirDatabase,
+        receiver: &Type,
+    ) -> Option<(Vec<Type>, Type)> {
+        self.self_param(db)?;
+        let substs = receiver.autoderef(db).find_map(|ty| {
+            let self_ty = hir_ty::replace_errors_with_variables(&ty.ty);
+            method_resolution::method_substs(db, receiver.env.clone(), self.id, &self_ty)
+        })?;
+        let sig = db.callable_item_signature(self.id.into()).substitute(&Interner, &substs);
+        let params = sig.params()[1..].iter().map(|ty| receiver.derived(ty.clone())).collect();
+        Some((params, receiver.derived(sig.ret().clone())))
+    }
+
+    /// The parameter types of the function when it returns `ret`, with the
+    /// generic parameters in its return type taken from `ret`. `None` if it
+    /// can't return `ret`.
+    pub fn params_returning(self, db: &dyn HirDatabase, ret: &Type) -> Option<Vec<Type>> {
+        let ret_ty = hir_ty::replace_errors_with_variables(&ret.ty);
+        let substs =
+            method_resolution::substs_for_return_type(db, ret.env.clone(), self.id, &ret_ty)?;
+        let sig = db.callable_item_signature(self.id.into()).substitute(&Interner, &substs);
+        Some(sig.params().iter().map(|ty| ret.derived(ty.clone())).collect())
+    }
+
     pub fn method_params(self, db: &dyn HirDatabase) -> Option<Vec<Param>> {
         if self.self_param(db).is_none() {
             return None;
@@ -1524,6 +1566,18 @@ impl Trait {
     pub fn is_unsafe(&self, db: &dyn HirDatabase) -> bool {
         db.trait_data(self.id).is_unsafe
     }
+
+    /// The type parameters of the trait, without the implicit `Self`.
+    pub fn type_params(self, db: &dyn HirDatabase) -> Vec<TypeParam> {
+        let generics = db.generic_params(self.id.into());
+        let self_param = generics.find_trait_self_param();
+        generics
+            .types
+            .iter()
+            .filter(|(local_id, _)| Some(*local_id) != self_param)
+            .map(|(local_id, _)| TypeParam { id: TypeParamId { parent: self.id.into(), local_id } })
+            .collect()
+    }
 }
 
 impl HasVisibility for Trait {
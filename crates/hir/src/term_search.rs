@@ -0,0 +1,524 @@
+//! Type-directed search for expressions ("terms") of a requested type.
+//!
+//! The search works in two phases. First, it collects the terms reachable
+//! from a scope without any goal in mind: locals, constants and statics, their
+//! fields and the results of their methods that take no arguments, up to a
+//! depth. Then it builds terms of the goal type out of those: the reachable
+//! terms of that type, method calls and function calls returning it, its
+//! constructors and trait functions like `Default::default` and `From::from`.
+//!
+//! Arguments of calls and constructors are always filled with reachable terms,
+//! the search doesn't recurse into nested calls to keep it cheap enough for
+//! completion.
+
+use hir_def::TraitId;
+use hir_expand::name::known;
+use hir_ty::{Cast, Goal, InEnvironment, Interner, Ty, TyBuilder, TyKind};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+
+use crate::{
+    db::HirDatabase, Adt, AssocItem, Const, Crate, DefWithBody, Field, Function, GenericDef,
+    HasVisibility, Local, Module, ModuleDef, ScopeDef, SemanticsScope, Static, Struct, StructKind,
+    Trait, Type, TypeParam, Variant, VariantDef,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TermSearchConfig {
+    /// How many field accesses and method calls may be chained onto a local.
+    pub depth: usize,
+    /// The maximal number of terms to return.
+    pub max_results: usize,
+}
+
+impl Default for TermSearchConfig {
+    fn default() -> Self {
+        TermSearchConfig { depth: 2, max_results: 10 }
+    }
+}
+
+/// An expression found by the term search.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Expr {
+    Local(Local),
+    Const(Const),
+    Static(Static),
+    Reference(Box<Expr>),
+    Field {
+        expr: Box<Expr>,
+        field: Field,
+    },
+    Method {
+        receiver: Box<Expr>,
+        func: Function,
+        args: Vec<Expr>,
+    },
+    Function {
+        func: Function,
+        args: Vec<Expr>,
+    },
+    /// An associated function of a trait, called on the type implementing it.
+    TraitFunction {
+        adt: Adt,
+        func: Function,
+        args: Vec<Expr>,
+    },
+    Struct {
+        strukt: Struct,
+        fields: Vec<(Field, Expr)>,
+    },
+    Variant {
+        variant: Variant,
+        fields: Vec<(Field, Expr)>,
+    },
+}
+
+impl Expr {
+    /// Renders the expression as it can be written in `module`.
+    pub fn gen_source_code(&self, db: &dyn HirDatabase, module: Module) -> String {
+        let path = |def: ModuleDef| match module.find_use_path(db.upcast(), def) {
+            Some(path) => path.to_string(),
+            None => def.name(db).map(|it| it.to_string()).unwrap_or_default(),
+        };
+        let args = |args: &[Expr]| args.iter().map(|it| it.gen_source_code(db, module)).join(", ");
+        let fields = |kind: StructKind, fields: &[(Field, Expr)]| match kind {
+            StructKind::Record => {
+                let fields = fields.iter().map(|(field, expr)| {
+                    let name = field.name(db).to_string();
+                    let expr = expr.gen_source_code(db, module);
+                    if name == expr {
+                        name
+                    } else {
+                        format!("{}: {}", name, expr)
+                    }
+                });
+                format!(" {{ {} }}", fields.format(", "))
+            }
+            StructKind::Tuple => {
+                let fields = fields.iter().map(|(_, expr)| expr.gen_source_code(db, module));
+                format!("({})", fields.format(", "))
+            }
+            StructKind::Unit => String::new(),
+        };
+        match self {
+            Expr::Local(it) => it.name(db).map(|it| it.to_string()).unwrap_or_default(),
+            Expr::Const(it) => path((*it).into()),
+            Expr::Static(it) => path((*it).into()),
+            Expr::Reference(expr) => format!("&{}", expr.gen_source_code(db, module)),
+            Expr::Field { expr, field } => {
+                format!("{}.{}", expr.gen_source_code(db, module), field.name(db))
+            }
+            Expr::Method { receiver, func, args: method_args } => format!(
+                "{}.{}({})",
+                receiver.gen_source_code(db, module),
+                func.name(db),
+                args(method_args)
+            ),
+            Expr::Function { func, args: fn_args } => {
+                format!("{}({})", path((*func).into()), args(fn_args))
+            }
+            Expr::TraitFunction { adt, func, args: fn_args } => {
+                format!("{}::{}({})", path((*adt).into()), func.name(db), args(fn_args))
+            }
+            Expr::Struct { strukt, fields: struct_fields } => {
+                format!("{}{}", path((*strukt).into()), fields(strukt.kind(db), struct_fields))
+            }
+            Expr::Variant { variant, fields: variant_fields } => {
+                format!("{}{}", path((*variant).into()), fields(variant.kind(db), variant_fields))
+            }
+        }
+    }
+
+    /// Whether the expression is just a name, which completion offers anyway.
+    /// This includes unit structs and variants.
+    pub fn is_name(&self, db: &dyn HirDatabase) -> bool {
+        match self {
+            Expr::Local(_) | Expr::Const(_) | Expr::Static(_) => true,
+            Expr::Struct { strukt, .. } => strukt.kind(db) == StructKind::Unit,
+            Expr::Variant { variant, .. } => variant.kind(db) == StructKind::Unit,
+            _ => false,
+        }
+    }
+}
+
+/// Searches for expressions of type `goal` that can be written in `scope`.
+pub fn term_search(scope: &SemanticsScope, goal: &Type, config: TermSearchConfig) -> Vec<Expr> {
+    let _p = profile::span("term_search");
+    let db = scope.db;
+    let (module, krate) = match (scope.module(), scope.krate()) {
+        (Some(module), Some(krate)) => (module, krate),
+        _ => return Vec::new(),
+    };
+    if goal.is_unknown() || goal.is_unit() {
+        return Vec::new();
+    }
+    let ctx = Ctx { db, module, krate, traits_in_scope: scope.traits_in_scope() };
+
+    let body_owner = scope.body_owner();
+    let mut functions = Vec::new();
+    let mut reachable = Vec::new();
+    scope.process_all_names(&mut |_, def| {
+        let (expr, ty) = match def {
+            ScopeDef::Local(it) => (Expr::Local(it), it.ty(db)),
+            ScopeDef::ModuleDef(ModuleDef::Const(it)) => (Expr::Const(it), it.ty(db)),
+            ScopeDef::ModuleDef(ModuleDef::Static(it)) => (Expr::Static(it), it.ty(db)),
+            // Don't suggest recursing into the function being written.
+            ScopeDef::ModuleDef(ModuleDef::Function(it))
+                if body_owner != Some(DefWithBody::Function(it)) =>
+            {
+                functions.push(it);
+                return;
+            }
+            _ => return,
+        };
+        if is_concrete(&ty) {
+            reachable.push(Term { expr, ty });
+        }
+    });
+    // Prefer locals over items.
+    reachable.sort_by_key(|term| !matches!(term.expr, Expr::Local(_)));
+    ctx.extend_reachable(&mut reachable, config.depth);
+
+    let mut res = Vec::new();
+    ctx.reachable_of_type(&mut res, &reachable, goal);
+    ctx.method_calls(&mut res, &reachable, goal);
+    ctx.constructors(&mut res, &reachable, goal);
+    ctx.trait_functions(&mut res, &reachable, goal);
+    ctx.function_calls(&mut res, &reachable, &functions, goal);
+
+    res.into_iter().unique().take(config.max_results).collect()
+}
+
+/// An upper bound for the number of reachable terms, as the number of method
+/// chains grows quickly with the depth.
+const MAX_REACHABLE: usize = 300;
+
+#[derive(Debug, Clone)]
+struct Term {
+    expr: Expr,
+    ty: Type,
+}
+
+struct Ctx<'a> {
+    db: &'a dyn HirDatabase,
+    module: Module,
+    krate: Crate,
+    traits_in_scope: FxHashSet<TraitId>,
+}
+
+impl Ctx<'_> {
+    fn extend_reachable(&self, reachable: &mut Vec<Term>, depth: usize) {
+        let mut frontier = 0..reachable.len();
+        for _ in 0..depth {
+            let start = reachable.len();
+            for idx in frontier {
+                let term = reachable[idx].clone();
+                let mut new_terms = self.fields(&term);
+                new_terms.extend(self.methods(&term).into_iter().filter_map(
+                    |(func, params, ty)| {
+                        params.is_empty().then(|| Term {
+                            expr: Expr::Method {
+                                receiver: Box::new(term.expr.clone()),
+                                func,
+                                args: Vec::new(),
+                            },
+                            ty,
+                        })
+                    },
+                ));
+                for new_term in new_terms {
+                    if reachable.len() >= MAX_REACHABLE {
+                        return;
+                    }
+                    if !reachable.iter().any(|it| it.expr == new_term.expr) {
+                        reachable.push(new_term);
+                    }
+                }
+            }
+            frontier = start..reachable.len();
+        }
+    }
+
+    /// The visible fields of `term`, through references and smart pointers.
+    fn fields(&self, term: &Term) -> Vec<Term> {
+        let fields = term.ty.autoderef(self.db).flat_map(|ty| ty.fields(self.db));
+        fields
+            .filter(|(field, ty)| field.is_visible_from(self.db, self.module) && is_concrete(ty))
+            .map(|(field, ty)| Term {
+                expr: Expr::Field { expr: Box::new(term.expr.clone()), field },
+                ty,
+            })
+            .collect()
+    }
+
+    /// The visible methods callable on `term`, with their parameter and return
+    /// types for the type of `term`.
+    fn methods(&self, term: &Term) -> Vec<(Function, Vec<Type>, Type)> {
+        let mut res = Vec::new();
+        term.ty.iterate_method_candidates(
+            self.db,
+            self.krate,
+            &self.traits_in_scope,
+            None,
+            |receiver_ty, func| {
+                if func.self_param(self.db).is_none()
+                    || func.is_unsafe(self.db)
+                    || !func.is_visible_from(self.db, self.module)
+                {
+                    return None::<()>;
+                }
+                let (params, ret_ty) = func.method_signature_on(self.db, &receiver_ty)?;
+                if is_concrete(&ret_ty) && !ret_ty.is_unit() {
+                    res.push((func, params, ret_ty));
+                }
+                None
+            },
+        );
+        res
+    }
+
+    fn reachable_of_type(&self, acc: &mut Vec<Expr>, reachable: &[Term], goal: &Type) {
+        for term in reachable {
+            if term.ty.could_unify_with(self.db, goal) {
+                acc.push(term.expr.clone());
+            } else if goal.remove_ref().map_or(false, |it| term.ty.could_unify_with(self.db, &it)) {
+                acc.push(Expr::Reference(Box::new(term.expr.clone())));
+            }
+        }
+    }
+
+    fn method_calls(&self, acc: &mut Vec<Expr>, reachable: &[Term], goal: &Type) {
+        for term in reachable {
+            for (func, params, ty) in self.methods(term) {
+                if !ty.could_unify_with(self.db, goal) {
+                    continue;
+                }
+                if let Some(args) = self.fill_args(reachable, params.iter()) {
+                    acc.push(Expr::Method { receiver: Box::new(term.expr.clone()), func, args });
+                }
+            }
+        }
+    }
+
+    fn function_calls(
+        &self,
+        acc: &mut Vec<Expr>,
+        reachable: &[Term],
+        functions: &[Function],
+        goal: &Type,
+    ) {
+        for &func in functions {
+            if func.self_param(self.db).is_some() || func.is_unsafe(self.db) {
+                continue;
+            }
+            // Generic functions are instantiated for the goal, like
+            // `fn wrap<T>(t: T) -> Wrapper<T>` with `T = u32` for `Wrapper<u32>`.
+            let params = match func.params_returning(self.db, goal) {
+                Some(it) => it,
+                None => continue,
+            };
+            if let Some(args) = self.fill_args(reachable, params.iter()) {
+                acc.push(Expr::Function { func, args });
+            }
+        }
+    }
+
+    fn constructors(&self, acc: &mut Vec<Expr>, reachable: &[Term], goal: &Type) {
+        let adt = match goal.as_adt() {
+            Some(it)
+                if self.module.find_use_path(self.db.upcast(), ModuleDef::Adt(it)).is_some() =>
+            {
+                it
+            }
+            _ => return,
+        };
+        match adt {
+            Adt::Struct(strukt) => {
+                if let Some(fields) = self.fill_fields(reachable, goal, strukt.into()) {
+                    acc.push(Expr::Struct { strukt, fields });
+                }
+            }
+            Adt::Enum(enum_) => {
+                for variant in enum_.variants(self.db) {
+                    if let Some(fields) = self.fill_fields(reachable, goal, variant.into()) {
+                        acc.push(Expr::Variant { variant, fields });
+                    }
+                }
+            }
+            Adt::Union(_) => (),
+        }
+    }
+
+    /// `Default::default()`, `From::from(term)` and other trait functions
+    /// returning `Self` with at most one type parameter.
+    fn trait_functions(&self, acc: &mut Vec<Expr>, reachable: &[Term], goal: &Type) {
+        let adt = match goal.as_adt() {
+            Some(it)
+                if self.module.find_use_path(self.db.upcast(), ModuleDef::Adt(it)).is_some() =>
+            {
+                it
+            }
+            _ => return,
+        };
+        for &trait_id in &self.traits_in_scope {
+            let trait_ = Trait::from(trait_id);
+            let trait_params: Vec<TypeParam> = GenericDef::from(trait_)
+                .type_params(self.db)
+                .into_iter()
+                .filter(|it| it.name(self.db) != known::SELF_TYPE)
+                .collect();
+            if trait_params.len() > 1 {
+                continue;
+            }
+            for item in trait_.items(self.db) {
+                let func = match item {
+                    AssocItem::Function(it) if it.self_param(self.db).is_none() => it,
+                    _ => continue,
+                };
+                let returns_self = as_type_param(self.db, &func.ret_type(self.db))
+                    .map_or(false, |it| it.name(self.db) == known::SELF_TYPE);
+                if !returns_self {
+                    continue;
+                }
+                let params = func.assoc_fn_params(self.db);
+                match (trait_params.as_slice(), params.as_slice()) {
+                    ([], []) => {
+                        if goal.impls_trait(self.db, trait_, &[]) {
+                            acc.push(Expr::TraitFunction { adt, func, args: Vec::new() });
+                        }
+                    }
+                    ([trait_param], [param])
+                        if as_type_param(self.db, param.ty()) == Some(*trait_param) =>
+                    {
+                        let arg = reachable.iter().find(|term| {
+                            !term.ty.could_unify_with(self.db, goal)
+                                && goal.impls_trait(self.db, trait_, &[term.ty.clone()])
+                        });
+                        if let Some(arg) = arg {
+                            acc.push(Expr::TraitFunction {
+                                adt,
+                                func,
+                                args: vec![arg.expr.clone()],
+                            });
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    fn fill_fields(
+        &self,
+        reachable: &[Term],
+        goal: &Type,
+        variant: VariantDef,
+    ) -> Option<Vec<(Field, Expr)>> {
+        let substs = match goal.ty.kind(&Interner) {
+            TyKind::Adt(_, substs) => substs,
+            _ => return None,
+        };
+        let field_types = self.db.field_types(variant.into());
+        field_types
+            .iter()
+            .map(|(id, ty)| {
+                let field = Field { parent: variant, id };
+                if !field.is_visible_from(self.db, self.module) {
+                    return None;
+                }
+                let ty = goal.derived(ty.clone().substitute(&Interner, substs));
+                // Prefer a local named like the field, for the shorthand syntax.
+                let name = field.name(self.db);
+                let local = reachable.iter().find(|term| match &term.expr {
+                    Expr::Local(local) => {
+                        local.name(self.db).as_ref() == Some(&name)
+                            && term.ty.could_unify_with(self.db, &ty)
+                    }
+                    _ => false,
+                });
+                let expr = match local {
+                    Some(term) => term.expr.clone(),
+                    None => self.fill_arg(reachable, &ty)?,
+                };
+                Some((field, expr))
+            })
+            .collect()
+    }
+
+    fn fill_args<'a>(
+        &self,
+        reachable: &[Term],
+        params: impl Iterator<Item = &'a Type>,
+    ) -> Option<Vec<Expr>> {
+        params.map(|ty| self.fill_arg(reachable, ty)).collect()
+    }
+
+    /// Finds a reachable term for a parameter of type `ty`. Generic parameters
+    /// take any term satisfying their bounds.
+    fn fill_arg(&self, reachable: &[Term], ty: &Type) -> Option<Expr> {
+        if let Some(param) = as_type_param(self.db, ty) {
+            let bounds = param.trait_bounds(self.db);
+            if bounds.is_empty() {
+                return None;
+            }
+            let term = reachable.iter().find(|term| {
+                bounds.iter().all(|&bound| impls_trait_with_any_args(self.db, &term.ty, bound))
+            })?;
+            return Some(term.expr.clone());
+        }
+        if !is_concrete(ty) {
+            return None;
+        }
+        if let Some(term) = reachable.iter().find(|term| term.ty.could_unify_with(self.db, ty)) {
+            return Some(term.expr.clone());
+        }
+        let inner = ty.remove_ref()?;
+        let term = reachable.iter().find(|term| term.ty.could_unify_with(self.db, &inner))?;
+        Some(Expr::Reference(Box::new(term.expr.clone())))
+    }
+}
+
+/// Whether `ty` implements `trait_` for some arguments of the trait, like
+/// `String: AsRef<_>`.
+fn impls_trait_with_any_args(db: &dyn HirDatabase, ty: &Type, trait_: Trait) -> bool {
+    let trait_ref =
+        TyBuilder::trait_ref(db, trait_.id).push(ty.ty.clone()).fill_with_unknown().build();
+    let goal = hir_ty::replace_errors_with_variables(&InEnvironment::new(
+        &ty.env.env,
+        trait_ref.cast::<Goal>(&Interner),
+    ));
+    db.trait_solve(ty.krate, goal).is_some()
+}
+
+/// Whether `ty` is fully known, without unknown parts or generic parameters.
+fn is_concrete(ty: &Type) -> bool {
+    return !ty.contains_unknown() && !contains_placeholder(&ty.ty);
+
+    fn contains_placeholder(ty: &Ty) -> bool {
+        match ty.kind(&Interner) {
+            TyKind::Placeholder(_) => true,
+            TyKind::Adt(_, substs)
+            | TyKind::AssociatedType(_, substs)
+            | TyKind::Tuple(_, substs)
+            | TyKind::OpaqueType(_, substs)
+            | TyKind::FnDef(_, substs)
+            | TyKind::Closure(_, substs) => {
+                substs.iter(&Interner).filter_map(|a| a.ty(&Interner)).any(contains_placeholder)
+            }
+            TyKind::Array(ty, _)
+            | TyKind::Slice(ty)
+            | TyKind::Raw(_, ty)
+            | TyKind::Ref(_, _, ty) => contains_placeholder(ty),
+            // Trait objects and `impl Trait` can't be named well enough.
+            TyKind::Dyn(_) | TyKind::Alias(_) => true,
+            _ => false,
+        }
+    }
+}
+
+fn as_type_param(db: &dyn HirDatabase, ty: &Type) -> Option<TypeParam> {
+    match ty.ty.kind(&Interner) {
+        TyKind::Placeholder(idx) => Some(TypeParam { id: hir_ty::from_placeholder_idx(db, *idx) }),
+        _ => None,
+    }
+}
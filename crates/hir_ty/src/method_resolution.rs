@@ -940,6 +940,87 @@ fn transform_receiver_ty(
     Some(sig.map(|s| s.params()[0].clone()).substitute(&Interner, &substs))
 }
 
+/// The substitution for calling `function_id` as a method on `self_ty`, or
+/// `None` if `self_ty` isn't the type of its impl or doesn't implement its
+/// trait. The parameters of its impl are inferred from `self_ty`, a trait's
+/// `Self` is `self_ty` and its other parameters are unknown. The parameters of
+/// the method itself stay placeholders.
+pub fn method_substs(
+    db: &dyn HirDatabase,
+    env: Arc<TraitEnvironment>,
+    function_id: FunctionId,
+    self_ty: &Canonical<Ty>,
+) -> Option<Substitution> {
+    let parent_substs = match function_id.lookup(db.upcast()).container {
+        AssocContainerId::TraitId(trait_id) => {
+            if !implements_trait(self_ty, db, env.clone(), env.krate, trait_id) {
+                return None;
+            }
+            TyBuilder::subst_for_def(db, trait_id)
+                .push(self_ty.value.clone())
+                .fill_with_unknown()
+                .build()
+        }
+        AssocContainerId::ImplId(impl_id) => inherent_impl_substs(db, env, impl_id, self_ty)?,
+        AssocContainerId::ModuleId(_) => return None,
+    };
+    let placeholders = TyBuilder::type_params_subst(db, function_id);
+    Some(
+        TyBuilder::subst_for_def(db, function_id)
+            .use_parent_substs(&parent_substs)
+            .fill(placeholders.iter(&Interner).skip(parent_substs.len(&Interner)).cloned())
+            .build(),
+    )
+}
+
+/// The substitution for a call of `function_id` returning `ret_ty`, or `None`
+/// if it can't return that type. Parameters that don't appear in the return
+/// type stay placeholders.
+pub fn substs_for_return_type(
+    db: &dyn HirDatabase,
+    env: Arc<TraitEnvironment>,
+    function_id: FunctionId,
+    ret_ty: &Canonical<Ty>,
+) -> Option<Substitution> {
+    // Like in `inherent_impl_substs`, there's a var for each type parameter
+    // of the function after the ones of `ret_ty`.
+    let ret_ty_vars = ret_ty.binders.len(&Interner);
+    let vars = TyBuilder::subst_for_def(db, function_id)
+        .fill_with_bound_vars(DebruijnIndex::INNERMOST, ret_ty_vars)
+        .build();
+    let sig = db.callable_item_signature(function_id.into());
+    let sig_ret_ty = sig.map(|s| s.ret().clone()).substitute(&Interner, &vars);
+    let mut kinds = ret_ty.binders.interned().to_vec();
+    kinds.extend(
+        iter::repeat(chalk_ir::WithKind::new(
+            chalk_ir::VariableKind::Ty(chalk_ir::TyVariableKind::General),
+            UniverseIndex::ROOT,
+        ))
+        .take(vars.len(&Interner)),
+    );
+    let tys = Canonical {
+        binders: CanonicalVarKinds::from_iter(&Interner, kinds),
+        value: (sig_ret_ty, ret_ty.value.clone()),
+    };
+    let substs = super::infer::unify(db, env, &tys)?;
+    // Unlike there, vars of `ret_ty` become unknown as well, as they are
+    // unknown parts of the return type.
+    let substs = fallback_bound_vars(
+        Substitution::from_iter(&Interner, substs.iter(&Interner).cloned().skip(ret_ty_vars)),
+        0,
+    );
+    let placeholders = TyBuilder::type_params_subst(db, function_id);
+    Some(Substitution::from_iter(
+        &Interner,
+        substs.iter(&Interner).zip(placeholders.iter(&Interner)).map(
+            |(arg, placeholder)| match arg.ty(&Interner) {
+                Some(ty) if ty.is_unknown() => placeholder.clone(),
+                _ => arg.clone(),
+            },
+        ),
+    ))
+}
+
 pub fn implements_trait(
     ty: &Canonical<Ty>,
     db: &dyn HirDatabase,
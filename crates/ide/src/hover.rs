@@ -332,7 +332,7 @@ fn goto_type_action_for_def(db: &RootDatabase, def: Definition) -> Option<HoverA
     Some(HoverAction::goto_type_from_targets(db, targets))
 }
 
-fn walk_and_push_ty(
+pub(crate) fn walk_and_push_ty(
     db: &RootDatabase,
     ty: &hir::Type,
     push_new_def: &mut dyn FnMut(hir::ModuleDef),
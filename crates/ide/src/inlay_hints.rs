@@ -1,15 +1,17 @@
+use std::fmt;
+
 use either::Either;
-use hir::{known, Callable, HasVisibility, HirDisplay, Semantics, TypeInfo};
+use hir::{known, Callable, HasSource, HasVisibility, HirDisplay, InFile, Semantics, TypeInfo};
 use ide_db::RootDatabase;
 use ide_db::{base_db::FileRange, helpers::FamousDefs};
 use itertools::Itertools;
 use stdx::to_lower_snake_case;
 use syntax::{
     ast::{self, AstNode, HasArgList, HasName},
-    match_ast, Direction, NodeOrToken, SmolStr, SyntaxKind, TextRange, T,
+    match_ast, Direction, NodeOrToken, SyntaxKind, TextRange, TextSize, T,
 };
 
-use crate::FileId;
+use crate::{display::TryToNav, hover::walk_and_push_ty, FileId};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct InlayHintsConfig {
@@ -30,7 +32,81 @@ pub enum InlayKind {
 pub struct InlayHint {
     pub range: TextRange,
     pub kind: InlayKind,
-    pub label: SmolStr,
+    pub label: InlayHintLabel,
+    pub tooltip: Option<InlayTooltip>,
+}
+
+/// What to show when hovering over a hint, computed lazily by the client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InlayTooltip {
+    /// The hover of the expression or pattern in the given range.
+    HoverRanged(FileId, TextRange),
+    /// The hover of the definition at the given offset.
+    HoverOffset(FileId, TextSize),
+}
+
+/// The text of a hint, split into parts that may link to a definition.
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct InlayHintLabel {
+    pub parts: Vec<InlayHintLabelPart>,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct InlayHintLabelPart {
+    pub text: String,
+    /// The definition this part refers to, e.g. the struct for its name in a type.
+    pub linked_location: Option<FileRange>,
+}
+
+impl InlayHintLabel {
+    fn push_str(&mut self, text: &str, linked_location: Option<FileRange>) {
+        match self.parts.last_mut() {
+            Some(last) if linked_location.is_none() && last.linked_location.is_none() => {
+                last.text.push_str(text)
+            }
+            _ => self.parts.push(InlayHintLabelPart { text: text.to_string(), linked_location }),
+        }
+    }
+}
+
+impl From<String> for InlayHintLabel {
+    fn from(text: String) -> Self {
+        InlayHintLabel { parts: vec![InlayHintLabelPart { text, linked_location: None }] }
+    }
+}
+
+impl From<&str> for InlayHintLabel {
+    fn from(text: &str) -> Self {
+        text.to_string().into()
+    }
+}
+
+impl fmt::Display for InlayHintLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.parts.iter().try_for_each(|part| f.write_str(&part.text))
+    }
+}
+
+impl fmt::Debug for InlayHintLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.parts.iter().all(|part| part.linked_location.is_none()) {
+            return fmt::Debug::fmt(&self.to_string(), f);
+        }
+        f.debug_list().entries(&self.parts).finish()
+    }
+}
+
+impl fmt::Debug for InlayHintLabelPart {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.linked_location {
+            Some(linked_location) => f
+                .debug_struct("InlayHintLabelPart")
+                .field("text", &self.text)
+                .field("linked_location", &linked_location)
+                .finish(),
+            None => fmt::Debug::fmt(&self.text, f),
+        }
+    }
 }
 
 // Feature: Inlay Hints
@@ -44,6 +120,9 @@ pub struct InlayHint {
 // * names of function arguments
 // * types of chained expressions
 //
+// Clients using the standard `textDocument/inlayHint` request get the type names and parameter names
+// in hints linked to their definitions, and the hover of the hinted expression as the tooltip.
+//
 // **Note:** VS Code does not have native support for inlay hints https://github.com/microsoft/vscode/issues/16221[yet] and the hints are implemented using decorations.
 // This approach has limitations, the caret movement and bracket highlighting near the edges of the hint may be weird:
 // https://github.com/rust-analyzer/rust-analyzer/issues/1623[1], https://github.com/rust-analyzer/rust-analyzer/issues/3453[2].
@@ -69,7 +148,7 @@ pub(crate) fn inlay_hints(
 
     for node in file.descendants() {
         if let Some(expr) = ast::Expr::cast(node.clone()) {
-            get_chaining_hints(&mut res, &sema, config, &expr);
+            get_chaining_hints(&mut res, &sema, config, file_id, &expr);
             match expr {
                 ast::Expr::CallExpr(it) => {
                     get_param_name_hints(&mut res, &sema, config, ast::Expr::from(it));
@@ -80,7 +159,7 @@ pub(crate) fn inlay_hints(
                 _ => (),
             }
         } else if let Some(it) = ast::IdentPat::cast(node.clone()) {
-            get_bind_pat_hints(&mut res, &sema, config, &it);
+            get_bind_pat_hints(&mut res, &sema, config, file_id, &it);
         }
     }
     res
@@ -90,6 +169,7 @@ fn get_chaining_hints(
     acc: &mut Vec<InlayHint>,
     sema: &Semantics<RootDatabase>,
     config: &InlayHintsConfig,
+    file_id: FileId,
     expr: &ast::Expr,
 ) -> Option<()> {
     if !config.chaining_hints {
@@ -135,12 +215,12 @@ fn get_chaining_hints(
                     }
                 }
             }
+            let (text, defs) = type_hint_text(sema, &famous_defs, config, &ty);
             acc.push(InlayHint {
                 range: expr.syntax().text_range(),
                 kind: InlayKind::ChainingHint,
-                label: hint_iterator(sema, &famous_defs, config, &ty).unwrap_or_else(|| {
-                    ty.display_truncated(sema.db, config.max_length).to_string().into()
-                }),
+                label: linked_label(sema.db, &text, &defs),
+                tooltip: Some(InlayTooltip::HoverRanged(file_id, expr.syntax().text_range())),
             });
         }
     }
@@ -158,6 +238,11 @@ fn get_param_name_hints(
     }
 
     let (callable, arg_list) = get_callable(sema, &expr)?;
+    // The parameters come from the source of the callee, which lives in this file.
+    let callee_file = match callable.kind() {
+        hir::CallableKind::Function(it) => it.source(sema.db).map(|src| src.file_id),
+        _ => None,
+    };
     let hints = callable
         .params(sema.db)
         .into_iter()
@@ -165,22 +250,32 @@ fn get_param_name_hints(
         .filter_map(|((param, _ty), arg)| {
             // Only annotate hints for expressions that exist in the original file
             let range = sema.original_range_opt(arg.syntax())?;
-            let param_name = match param? {
-                Either::Left(_) => "self".to_string(),
+            let (param_name, param_syntax) = match param? {
+                Either::Left(self_param) => ("self".to_string(), self_param.syntax().clone()),
                 Either::Right(pat) => match pat {
-                    ast::Pat::IdentPat(it) => it.name()?.to_string(),
+                    ast::Pat::IdentPat(it) => {
+                        let name = it.name()?;
+                        (name.to_string(), name.syntax().clone())
+                    }
                     _ => return None,
                 },
             };
-            Some((param_name, arg, range))
+            let linked_location = callee_file.and_then(|file_id| {
+                InFile::new(file_id, &param_syntax).original_file_range_opt(sema.db)
+            });
+            Some((param_name, linked_location, arg, range))
         })
-        .filter(|(param_name, arg, _)| {
+        .filter(|(param_name, _, arg, _)| {
             !should_hide_param_name_hint(sema, &callable, param_name, arg)
         })
-        .map(|(param_name, _, FileRange { range, .. })| InlayHint {
+        .map(|(param_name, linked_location, _, FileRange { range, .. })| InlayHint {
             range,
             kind: InlayKind::ParameterHint,
-            label: param_name.into(),
+            label: InlayHintLabel {
+                parts: vec![InlayHintLabelPart { text: param_name, linked_location }],
+            },
+            tooltip: linked_location
+                .map(|it| InlayTooltip::HoverOffset(it.file_id, it.range.start())),
         });
 
     acc.extend(hints);
@@ -191,6 +286,7 @@ fn get_bind_pat_hints(
     acc: &mut Vec<InlayHint>,
     sema: &Semantics<RootDatabase>,
     config: &InlayHintsConfig,
+    file_id: FileId,
     pat: &ast::IdentPat,
 ) -> Option<()> {
     if !config.type_hints {
@@ -207,16 +303,16 @@ fn get_bind_pat_hints(
 
     let krate = sema.scope(desc_pat.syntax()).module().map(|it| it.krate());
     let famous_defs = FamousDefs(sema, krate);
-    let label = hint_iterator(sema, &famous_defs, config, &ty);
-
-    let label = match label {
-        Some(label) => label,
+    let (text, defs) = match hint_iterator(sema, &famous_defs, config, &ty) {
+        Some(it) => it,
         None => {
             let ty_name = ty.display_truncated(sema.db, config.max_length).to_string();
             if is_named_constructor(sema, pat, &ty_name).is_some() {
                 return None;
             }
-            ty_name.into()
+            let mut defs = Vec::new();
+            walk_and_push_ty(sema.db, &ty, &mut |def| defs.push(def));
+            (ty_name, defs)
         }
     };
 
@@ -226,7 +322,8 @@ fn get_bind_pat_hints(
             None => pat.syntax().text_range(),
         },
         kind: InlayKind::TypeHint,
-        label,
+        label: linked_label(sema.db, &text, &defs),
+        tooltip: Some(InlayTooltip::HoverRanged(file_id, pat.syntax().text_range())),
     });
 
     Some(())
@@ -288,13 +385,55 @@ fn is_named_constructor(
     (ctor_name == ty_name).then(|| ())
 }
 
+/// Renders the type for a hint, along with the definitions mentioned in it.
+fn type_hint_text(
+    sema: &Semantics<RootDatabase>,
+    famous_defs: &FamousDefs,
+    config: &InlayHintsConfig,
+    ty: &hir::Type,
+) -> (String, Vec<hir::ModuleDef>) {
+    hint_iterator(sema, famous_defs, config, ty).unwrap_or_else(|| {
+        let mut defs = Vec::new();
+        walk_and_push_ty(sema.db, ty, &mut |def| defs.push(def));
+        (ty.display_truncated(sema.db, config.max_length).to_string(), defs)
+    })
+}
+
+/// Splits `text` into label parts, linking the names of `defs` to their definitions.
+fn linked_label(db: &RootDatabase, text: &str, defs: &[hir::ModuleDef]) -> InlayHintLabel {
+    let targets: Vec<_> = defs
+        .iter()
+        .filter_map(|def| {
+            let name = def.name(db)?.to_string();
+            let nav = def.try_to_nav(db)?;
+            Some((name, FileRange { file_id: nav.file_id, range: nav.focus_or_full_range() }))
+        })
+        .collect();
+
+    let mut label = InlayHintLabel::default();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let len = match rest.find(|c| !is_ident(c)) {
+            Some(0) => rest.chars().next().map_or(1, char::len_utf8),
+            Some(len) => len,
+            None => rest.len(),
+        };
+        let (word, tail) = rest.split_at(len);
+        let target = targets.iter().find(|(name, _)| name == word).map(|&(_, range)| range);
+        label.push_str(word, target);
+        rest = tail;
+    }
+    label
+}
+
 /// Checks if the type is an Iterator from std::iter and replaces its hint with an `impl Iterator<Item = Ty>`.
 fn hint_iterator(
     sema: &Semantics<RootDatabase>,
     famous_defs: &FamousDefs,
     config: &InlayHintsConfig,
     ty: &hir::Type,
-) -> Option<SmolStr> {
+) -> Option<(String, Vec<hir::ModuleDef>)> {
     let db = sema.db;
     let strukt = ty.strip_references().as_adt()?;
     let krate = strukt.module(db).krate();
@@ -320,18 +459,20 @@ fn hint_iterator(
             const LABEL_START: &str = "impl Iterator<Item = ";
             const LABEL_END: &str = ">";
 
-            let ty_display = hint_iterator(sema, famous_defs, config, &ty)
-                .map(|assoc_type_impl| assoc_type_impl.to_string())
+            let (ty_display, mut defs) = hint_iterator(sema, famous_defs, config, &ty)
                 .unwrap_or_else(|| {
-                    ty.display_truncated(
+                    let mut defs = Vec::new();
+                    walk_and_push_ty(db, &ty, &mut |def| defs.push(def));
+                    let ty_display = ty.display_truncated(
                         db,
                         config
                             .max_length
                             .map(|len| len.saturating_sub(LABEL_START.len() + LABEL_END.len())),
-                    )
-                    .to_string()
+                    );
+                    (ty_display.to_string(), defs)
                 });
-            return Some(format!("{}{}{}", LABEL_START, ty_display, LABEL_END).into());
+            defs.push(iter_trait.into());
+            return Some((format!("{}{}{}", LABEL_START, ty_display, LABEL_END), defs));
         }
     }
 
@@ -1427,12 +1568,48 @@ fn main() {
                     InlayHint {
                         range: 147..172,
                         kind: ChainingHint,
-                        label: "B",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "B",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 63..64,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                147..172,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 147..154,
                         kind: ChainingHint,
-                        label: "A",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "A",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 7..8,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                147..154,
+                            ),
+                        ),
                     },
                 ]
             "#]],
@@ -1487,12 +1664,48 @@ fn main() {
                     InlayHint {
                         range: 143..190,
                         kind: ChainingHint,
-                        label: "C",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "C",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 51..52,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                143..190,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 143..179,
                         kind: ChainingHint,
-                        label: "B",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "B",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 29..30,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                143..179,
+                            ),
+                        ),
                     },
                 ]
             "#]],
@@ -1532,12 +1745,70 @@ fn main() {
                     InlayHint {
                         range: 246..283,
                         kind: ChainingHint,
-                        label: "B<X<i32, bool>>",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "B",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 23..24,
+                                },
+                            },
+                            "<",
+                            InlayHintLabelPart {
+                                text: "X",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 55..56,
+                                },
+                            },
+                            "<i32, bool>>",
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                246..283,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 246..265,
                         kind: ChainingHint,
-                        label: "A<X<i32, bool>>",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "A",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 7..8,
+                                },
+                            },
+                            "<",
+                            InlayHintLabelPart {
+                                text: "X",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 55..56,
+                                },
+                            },
+                            "<i32, bool>>",
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                246..265,
+                            ),
+                        ),
                     },
                 ]
             "#]],
@@ -1579,22 +1850,101 @@ fn main() {
                     InlayHint {
                         range: 174..241,
                         kind: ChainingHint,
-                        label: "impl Iterator<Item = ()>",
+                        label: [
+                            "impl ",
+                            InlayHintLabelPart {
+                                text: "Iterator",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    range: 2231..2239,
+                                },
+                            },
+                            "<Item = ()>",
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                174..241,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 174..224,
                         kind: ChainingHint,
-                        label: "impl Iterator<Item = ()>",
+                        label: [
+                            "impl ",
+                            InlayHintLabelPart {
+                                text: "Iterator",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    range: 2231..2239,
+                                },
+                            },
+                            "<Item = ()>",
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                174..224,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 174..206,
                         kind: ChainingHint,
-                        label: "impl Iterator<Item = ()>",
+                        label: [
+                            "impl ",
+                            InlayHintLabelPart {
+                                text: "Iterator",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        1,
+                                    ),
+                                    range: 2231..2239,
+                                },
+                            },
+                            "<Item = ()>",
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                174..206,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 174..189,
                         kind: ChainingHint,
-                        label: "&mut MyIter",
+                        label: [
+                            "&mut ",
+                            InlayHintLabelPart {
+                                text: "MyIter",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 24..30,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                174..189,
+                            ),
+                        ),
                     },
                 ]
             "#]],
@@ -1628,22 +1978,94 @@ fn main() {
                     InlayHint {
                         range: 124..130,
                         kind: TypeHint,
-                        label: "Struct",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "Struct",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 7..13,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                124..130,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 145..185,
                         kind: ChainingHint,
-                        label: "Struct",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "Struct",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 7..13,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                145..185,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 145..168,
                         kind: ChainingHint,
-                        label: "Struct",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "Struct",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 7..13,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverRanged(
+                                FileId(
+                                    0,
+                                ),
+                                145..168,
+                            ),
+                        ),
                     },
                     InlayHint {
                         range: 222..228,
                         kind: ParameterHint,
-                        label: "self",
+                        label: [
+                            InlayHintLabelPart {
+                                text: "self",
+                                linked_location: FileRange {
+                                    file_id: FileId(
+                                        0,
+                                    ),
+                                    range: 42..46,
+                                },
+                            },
+                        ],
+                        tooltip: Some(
+                            HoverOffset(
+                                FileId(
+                                    0,
+                                ),
+                                42,
+                            ),
+                        ),
                     },
                 ]
             "#]],
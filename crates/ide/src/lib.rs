@@ -55,7 +55,7 @@ mod view_crate_graph;
 mod view_hir;
 mod view_item_tree;
 
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 use cfg::CfgOptions;
 use ide_db::{
@@ -79,7 +79,9 @@ pub use crate::{
     folding_ranges::{Fold, FoldKind},
     highlight_related::{HighlightRelatedConfig, HighlightedRange},
     hover::{HoverAction, HoverConfig, HoverDocFormat, HoverGotoTypeData, HoverResult},
-    inlay_hints::{InlayHint, InlayHintsConfig, InlayKind},
+    inlay_hints::{
+        InlayHint, InlayHintLabel, InlayHintLabelPart, InlayHintsConfig, InlayKind, InlayTooltip,
+    },
     join_lines::JoinLinesConfig,
     markup::Markup,
     move_item::Direction,
@@ -111,7 +113,8 @@ pub use ide_db::{
     line_index::{LineCol, LineColUtf16, LineIndex},
     search::{ReferenceCategory, SearchScope},
     source_change::{FileSystemEdit, SourceChange},
-    symbol_index::Query,
+    symbol_index::{CachedLibrarySymbols, Query},
+    unused_items::UnusedItem,
     RootDatabase, SymbolKind,
 };
 pub use ide_diagnostics::{Diagnostic, DiagnosticsConfig, Severity};
@@ -242,6 +245,22 @@ impl Analysis {
         self.with_db(move |db| prime_caches::prime_caches(db, &cb))
     }
 
+    /// Reads the symbols of library roots from the on-disk cache, see
+    /// [`symbol_index::load_library_symbols`].
+    pub fn load_library_symbols(
+        &self,
+        dir: &Path,
+        version: &str,
+    ) -> Cancellable<Option<CachedLibrarySymbols>> {
+        self.with_db(|db| symbol_index::load_library_symbols(db, dir, version))
+    }
+
+    /// Writes the symbols of library roots to the on-disk cache, see
+    /// [`symbol_index::store_library_symbols`].
+    pub fn store_library_symbols(&self, dir: &Path, version: &str) -> Cancellable<()> {
+        self.with_db(|db| symbol_index::store_library_symbols(db, dir, version))
+    }
+
     /// Gets the text of the source file.
     pub fn file_text(&self, file_id: FileId) -> Cancellable<Arc<String>> {
         self.with_db(|db| db.file_text(file_id))
@@ -409,6 +428,11 @@ impl Analysis {
         self.with_db(|db| references::find_all_refs(&Semantics::new(db), position, search_scope))
     }
 
+    /// Finds the public items of the file that nothing in the workspace uses.
+    pub fn unused_public_items(&self, file_id: FileId) -> Cancellable<Vec<UnusedItem>> {
+        self.with_db(|db| ide_db::unused_items::unused_public_items(&Semantics::new(db), file_id))
+    }
+
     /// Finds all methods and free functions for the file. Does not return tests!
     pub fn find_all_methods(&self, file_id: FileId) -> Cancellable<Vec<FileRange>> {
         self.with_db(|db| fn_references::find_all_methods(db, file_id))
@@ -0,0 +1,329 @@
+use itertools::Itertools;
+use stdx::format_to;
+use syntax::{
+    ast::{
+        self,
+        edit::{AstNodeEdit, IndentLevel},
+        HasName,
+    },
+    AstNode, SyntaxKind,
+};
+
+use crate::{AssistContext, AssistId, AssistKind, Assists};
+
+// Assist: convert_let_else_to_match
+//
+// Converts a `let ... else` statement into a `let` with a `match` initializer.
+//
+// ```
+// # //- minicore: option
+// fn main(opt: Option<i32>) {
+//     let Some(mut x) = opt else$0 { return };
+// }
+// ```
+// ->
+// ```
+// fn main(opt: Option<i32>) {
+//     let mut x = match opt {
+//         Some(x) => x,
+//         _ => { return }
+//     };
+// }
+// ```
+pub(crate) fn convert_let_else_to_match(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let let_stmt: ast::LetStmt = ctx.find_node_at_offset()?;
+    let let_else = let_stmt.let_else()?;
+    let cursor_in_range = let_stmt.let_token()?.text_range().contains_inclusive(ctx.offset())
+        || let_else.else_token()?.text_range().contains_inclusive(ctx.offset());
+    if !cursor_in_range {
+        return None;
+    }
+    // The type annotation of a `let ... else` describes the scrutinee, which we can't spell out
+    // in the `match` form without an extra binding.
+    if let_stmt.ty().is_some() {
+        return None;
+    }
+
+    let pat = let_stmt.pat()?;
+    let initializer = let_stmt.initializer()?;
+    let else_block = let_else.block_expr()?;
+
+    let binders = pat
+        .syntax()
+        .descendants()
+        .filter_map(ast::IdentPat::cast)
+        .filter(|it| ctx.sema.resolve_bind_pat_to_const(it).is_none())
+        .collect::<Vec<_>>();
+    if binders.iter().any(|it| it.pat().is_some()) {
+        // `name @ pat` bindings would need to be duplicated, leave them alone.
+        return None;
+    }
+
+    let target = let_stmt.syntax().text_range();
+    acc.add(
+        AssistId("convert_let_else_to_match", AssistKind::RefactorRewrite),
+        "Convert let-else to let and match",
+        target,
+        |edit| {
+            let indent = IndentLevel::from_node(let_stmt.syntax());
+            let arm_indent = indent + 1;
+
+            // Mutability of by-value bindings moves to the outer binding, the match arm only needs
+            // to move the value. `ref mut` bindings stay as they are, they borrow mutably.
+            let mut_tokens = binders
+                .iter()
+                .filter(|it| it.ref_token().is_none())
+                .filter_map(|it| it.mut_token())
+                .collect::<Vec<_>>();
+            let mut after_mut = false;
+            let arm_pat = pat
+                .syntax()
+                .descendants_with_tokens()
+                .filter_map(|it| it.into_token())
+                .filter(|token| {
+                    let skip = mut_tokens.contains(token)
+                        || (after_mut && token.kind() == SyntaxKind::WHITESPACE);
+                    after_mut = mut_tokens.contains(token);
+                    !skip
+                })
+                .join("");
+
+            let (outer_pat, arm_expr) = match binders.as_slice() {
+                [] => ("()".to_string(), "()".to_string()),
+                [binder] => (binding_with_mut(binder), binding_name(binder)),
+                binders => (
+                    format!("({})", binders.iter().map(binding_with_mut).join(", ")),
+                    format!("({})", binders.iter().map(binding_name).join(", ")),
+                ),
+            };
+
+            let else_block = else_block.reset_indent().indent(arm_indent);
+            let mut replacement = String::new();
+            format_to!(replacement, "let {} = match {} {{\n", outer_pat, initializer);
+            format_to!(replacement, "{}{} => {},\n", arm_indent, arm_pat, arm_expr);
+            format_to!(replacement, "{}_ => {}\n", arm_indent, else_block);
+            format_to!(replacement, "{}}};", indent);
+            edit.replace(target, replacement);
+        },
+    )
+}
+
+fn binding_name(binder: &ast::IdentPat) -> String {
+    binder.name().map(|it| it.to_string()).unwrap_or_default()
+}
+
+fn binding_with_mut(binder: &ast::IdentPat) -> String {
+    let by_value_mut = binder.mut_token().is_some() && binder.ref_token().is_none();
+    let mut_ = if by_value_mut { "mut " } else { "" };
+    format!("{}{}", mut_, binding_name(binder))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn not_applicable_without_else() {
+        check_assist_not_applicable(
+            convert_let_else_to_match,
+            r#"
+fn main() {
+    let$0 x = 92;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_with_type_annotation() {
+        check_assist_not_applicable(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 Some(x): Option<i32> = opt else { return };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_outside_keywords() {
+        check_assist_not_applicable(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let Some(x) = o$0pt else { return };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_single_binding() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 Some(x) = opt else { return };
+}
+"#,
+            r#"
+fn main(opt: Option<i32>) {
+    let x = match opt {
+        Some(x) => x,
+        _ => { return }
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_mut_binding() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let Some(mut x) = opt else$0 { return };
+}
+"#,
+            r#"
+fn main(opt: Option<i32>) {
+    let mut x = match opt {
+        Some(x) => x,
+        _ => { return }
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_ref_mut_binding() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+fn main(opt: &mut Option<i32>) {
+    let Some(ref mut x) = *opt else$0 { return };
+}
+"#,
+            r#"
+fn main(opt: &mut Option<i32>) {
+    let x = match *opt {
+        Some(ref mut x) => x,
+        _ => { return }
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_multiple_bindings() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+enum E { A(i32, i32), B }
+fn main(e: E) {
+    let$0 E::A(a, mut b) = e else {
+        return;
+    };
+}
+"#,
+            r#"
+enum E { A(i32, i32), B }
+fn main(e: E) {
+    let (a, mut b) = match e {
+        E::A(a, b) => (a, b),
+        _ => {
+            return;
+        }
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_no_bindings() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+enum E { A, B }
+fn main(e: E) {
+    let$0 E::A = e else { return };
+}
+"#,
+            r#"
+enum E { A, B }
+fn main(e: E) {
+    let () = match e {
+        E::A => (),
+        _ => { return }
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_ignores_unit_variants_in_pattern() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+enum E { A, B }
+use E::A;
+fn main(opt: Option<(E, i32)>) {
+    let$0 Some((A, x)) = opt else { return };
+}
+"#,
+            r#"
+enum E { A, B }
+use E::A;
+fn main(opt: Option<(E, i32)>) {
+    let x = match opt {
+        Some((A, x)) => x,
+        _ => { return }
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_nested_indentation() {
+        check_assist(
+            convert_let_else_to_match,
+            r#"
+//- minicore: option
+fn main(opts: &[Option<i32>]) {
+    for opt in opts {
+        let$0 Some(x) = opt else {
+            continue;
+        };
+    }
+}
+"#,
+            r#"
+fn main(opts: &[Option<i32>]) {
+    for opt in opts {
+        let x = match opt {
+            Some(x) => x,
+            _ => {
+                continue;
+            }
+        };
+    }
+}
+"#,
+        );
+    }
+}
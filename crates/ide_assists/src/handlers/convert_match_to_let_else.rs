@@ -0,0 +1,395 @@
+use hir::PathResolution;
+use syntax::{
+    ast::{
+        self,
+        edit::{AstNodeEdit, IndentLevel},
+        HasName,
+    },
+    AstNode,
+};
+
+use crate::{AssistContext, AssistId, AssistKind, Assists};
+
+// Assist: convert_match_to_let_else
+//
+// Converts a `let` statement whose initializer is a `match` or `if let` that either produces the
+// bound value or diverges into a `let ... else` statement.
+//
+// ```
+// # //- minicore: option
+// fn main(opt: Option<i32>) {
+//     let$0 x = match opt {
+//         Some(it) => it,
+//         None => return,
+//     };
+// }
+// ```
+// ->
+// ```
+// fn main(opt: Option<i32>) {
+//     let Some(x) = opt else { return };
+// }
+// ```
+pub(crate) fn convert_match_to_let_else(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let let_stmt: ast::LetStmt = ctx.find_node_at_offset()?;
+    let binding = match let_stmt.pat()? {
+        ast::Pat::IdentPat(it) => it,
+        _ => return None,
+    };
+    let cursor_in_range = let_stmt.let_token()?.text_range().contains_inclusive(ctx.offset())
+        || binding.syntax().text_range().contains_inclusive(ctx.offset());
+    if !cursor_in_range {
+        return None;
+    }
+    if let_stmt.let_else().is_some() || let_stmt.ty().is_some() {
+        return None;
+    }
+    if binding.ref_token().is_some() || binding.pat().is_some() {
+        return None;
+    }
+
+    let (scrutinee, happy_pat, binder, diverging) = match let_stmt.initializer()? {
+        ast::Expr::MatchExpr(match_expr) => from_match(ctx, &match_expr)?,
+        ast::Expr::IfExpr(if_expr) => from_if_let(ctx, &if_expr)?,
+        _ => return None,
+    };
+
+    let target = let_stmt.syntax().text_range();
+    acc.add(
+        AssistId("convert_match_to_let_else", AssistKind::RefactorRewrite),
+        "Convert to let-else",
+        target,
+        |edit| {
+            let indent = IndentLevel::from_node(let_stmt.syntax());
+            let pat = rename_binder(&happy_pat, &binder, &binding);
+            let else_block = match diverging {
+                ast::Expr::BlockExpr(block) if block.modifier().is_none() => {
+                    block.reset_indent().indent(indent).to_string()
+                }
+                expr => format!("{{ {} }}", expr),
+            };
+            let replacement = format!("let {} = {} else {};", pat, scrutinee, else_block);
+            edit.replace(target, replacement);
+        },
+    )
+}
+
+/// Handles `match scrutinee { Happy(it) => it, _ => diverge }`, in any arm order.
+fn from_match(
+    ctx: &AssistContext,
+    match_expr: &ast::MatchExpr,
+) -> Option<(ast::Expr, ast::Pat, ast::IdentPat, ast::Expr)> {
+    let scrutinee = match_expr.expr()?;
+    let arms = match_expr.match_arm_list()?.arms().collect::<Vec<_>>();
+    let (first, second) = match arms.as_slice() {
+        [first, second] => (first, second),
+        _ => return None,
+    };
+    if first.guard().is_some() || second.guard().is_some() {
+        return None;
+    }
+
+    let happy_arm = |arm: &ast::MatchArm| {
+        let pat = arm.pat()?;
+        let binder = moved_binder(ctx, &pat, &arm.expr()?)?;
+        Some((pat, binder))
+    };
+    let ((happy_pat, binder), diverging) = match (happy_arm(first), happy_arm(second)) {
+        (Some(happy), None) => (happy, second),
+        (None, Some(happy)) => (happy, first),
+        _ => return None,
+    };
+    if !diverges(ctx, &diverging.expr()?) {
+        return None;
+    }
+    Some((scrutinee, happy_pat, binder, diverging.expr()?))
+}
+
+/// Handles `if let Happy(it) = scrutinee { it } else { diverge }`.
+fn from_if_let(
+    ctx: &AssistContext,
+    if_expr: &ast::IfExpr,
+) -> Option<(ast::Expr, ast::Pat, ast::IdentPat, ast::Expr)> {
+    let cond = if_expr.condition()?;
+    let happy_pat = cond.pat()?;
+    let scrutinee = cond.expr()?;
+    let else_block = match if_expr.else_branch()? {
+        ast::ElseBranch::Block(it) => it,
+        ast::ElseBranch::IfExpr(_) => return None,
+    };
+    if !diverges(ctx, &else_block.clone().into()) {
+        return None;
+    }
+    let then_branch = if_expr.then_branch()?;
+    let binder = moved_binder(ctx, &happy_pat, &then_branch.into())?;
+    Some((scrutinee, happy_pat, binder, else_block.into()))
+}
+
+fn diverges(ctx: &AssistContext, expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::ReturnExpr(_) | ast::Expr::BreakExpr(_) | ast::Expr::ContinueExpr(_) => true,
+        // The type of a block ending in a diverging statement gets unified with the other
+        // branch, so look at its last statement instead.
+        ast::Expr::BlockExpr(block) if block.modifier().is_none() => {
+            let stmt_list = match block.stmt_list() {
+                Some(it) => it,
+                None => return false,
+            };
+            match stmt_list.tail_expr() {
+                Some(tail) => diverges(ctx, &tail),
+                None => match stmt_list.statements().last() {
+                    Some(ast::Stmt::ExprStmt(stmt)) => {
+                        stmt.expr().map_or(false, |it| diverges(ctx, &it))
+                    }
+                    _ => false,
+                },
+            }
+        }
+        _ => ctx.sema.type_of_expr(expr).map_or(false, |ty| ty.original.is_never()),
+    }
+}
+
+/// Returns the only binding of `pat` if `expr` is exactly that binding, possibly wrapped into a
+/// block.
+fn moved_binder(ctx: &AssistContext, pat: &ast::Pat, expr: &ast::Expr) -> Option<ast::IdentPat> {
+    let mut binders = pat
+        .syntax()
+        .descendants()
+        .filter_map(ast::IdentPat::cast)
+        .filter_map(|it| Some((ctx.sema.to_def(&it)?, it)));
+    let (local, binder) = binders.next()?;
+    if binders.next().is_some() || binder.pat().is_some() {
+        return None;
+    }
+
+    let mut expr = expr.clone();
+    while let ast::Expr::BlockExpr(block) = &expr {
+        if block.modifier().is_some() {
+            return None;
+        }
+        let stmt_list = block.stmt_list()?;
+        if stmt_list.statements().next().is_some() {
+            return None;
+        }
+        expr = stmt_list.tail_expr()?;
+    }
+    let path = match expr {
+        ast::Expr::PathExpr(it) => it.path()?,
+        _ => return None,
+    };
+    match ctx.sema.resolve_path(&path)? {
+        PathResolution::Local(it) if it == local => Some(binder),
+        _ => None,
+    }
+}
+
+/// Renders `pat` with `binder` replaced by the name and mutability of the `let` binding.
+fn rename_binder(pat: &ast::Pat, binder: &ast::IdentPat, binding: &ast::IdentPat) -> String {
+    let mut new_binder = String::new();
+    if let Some(field) = binder.syntax().parent().and_then(ast::RecordPatField::cast) {
+        if field.name_ref().is_none() {
+            if let Some(name) = binder.name() {
+                new_binder.push_str(&format!("{}: ", name));
+            }
+        }
+    }
+    if binder.ref_token().is_some() {
+        new_binder.push_str("ref ");
+    }
+    if binding.mut_token().is_some() {
+        new_binder.push_str("mut ");
+    }
+    if let Some(name) = binding.name() {
+        new_binder.push_str(&name.to_string());
+    }
+
+    let mut text = pat.syntax().to_string();
+    let range = binder.syntax().text_range() - pat.syntax().text_range().start();
+    text.replace_range(usize::from(range.start())..usize::from(range.end()), &new_binder);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn not_applicable_without_diverging_arm() {
+        check_assist_not_applicable(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 x = match opt {
+        Some(it) => it,
+        None => 0,
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_when_arm_transforms_binding() {
+        check_assist_not_applicable(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 x = match opt {
+        Some(it) => it + 1,
+        None => return,
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_with_guard() {
+        check_assist_not_applicable(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 x = match opt {
+        Some(it) if it > 0 => it,
+        _ => return,
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_on_initializer() {
+        check_assist_not_applicable(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let x = match o$0pt {
+        Some(it) => it,
+        None => return,
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_match() {
+        check_assist(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let x$0 = match opt {
+        Some(it) => it,
+        None => return,
+    };
+}
+"#,
+            r#"
+fn main(opt: Option<i32>) {
+    let Some(x) = opt else { return };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_match_diverging_arm_first() {
+        check_assist(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opts: &[Option<i32>]) {
+    for opt in opts {
+        let$0 mut x = match *opt {
+            None => {
+                continue;
+            }
+            Some(it) => { it }
+        };
+    }
+}
+"#,
+            r#"
+fn main(opts: &[Option<i32>]) {
+    for opt in opts {
+        let Some(mut x) = *opt else {
+            continue;
+        };
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_match_record_shorthand() {
+        check_assist(
+            convert_match_to_let_else,
+            r#"
+enum E { A { field: i32 }, B }
+fn main(e: E) -> i32 {
+    let$0 x = match e {
+        E::A { field } => field,
+        _ => return 0,
+    };
+    x
+}
+"#,
+            r#"
+enum E { A { field: i32 }, B }
+fn main(e: E) -> i32 {
+    let E::A { field: x } = e else { return 0 };
+    x
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_if_let() {
+        check_assist(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 x = if let Some(it) = opt { it } else {
+        return;
+    };
+}
+"#,
+            r#"
+fn main(opt: Option<i32>) {
+    let Some(x) = opt else {
+        return;
+    };
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_if_let_keeps_ref() {
+        check_assist(
+            convert_match_to_let_else,
+            r#"
+//- minicore: option
+fn main(opt: Option<String>) {
+    let$0 x = if let Some(ref it) = opt { it } else { return };
+}
+"#,
+            r#"
+fn main(opt: Option<String>) {
+    let Some(ref x) = opt else { return };
+}
+"#,
+        );
+    }
+}
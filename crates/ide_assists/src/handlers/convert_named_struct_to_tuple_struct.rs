@@ -0,0 +1,578 @@
+use either::Either;
+use ide_db::{base_db::FileId, defs::Definition, search::FileReference};
+use itertools::Itertools;
+use rustc_hash::FxHashMap;
+use syntax::{
+    ast::{self, AstNode, HasAttrs, HasGenericParams, HasName, HasVisibility},
+    match_ast, SyntaxKind, TextRange,
+};
+
+use crate::{assist_context::AssistBuilder, AssistContext, AssistId, AssistKind, Assists};
+
+// Assist: convert_named_struct_to_tuple_struct
+//
+// Converts struct with named fields to tuple struct, and analogously for enum variants with named
+// fields.
+//
+// ```
+// struct Point$0 { x: f32, y: f32 }
+//
+// impl Point {
+//     pub fn new(x: f32, y: f32) -> Self {
+//         Point { x, y }
+//     }
+//
+//     pub fn x(&self) -> f32 {
+//         self.x
+//     }
+//
+//     pub fn y(&self) -> f32 {
+//         self.y
+//     }
+// }
+// ```
+// ->
+// ```
+// struct Point(f32, f32);
+//
+// impl Point {
+//     pub fn new(x: f32, y: f32) -> Self {
+//         Point(x, y)
+//     }
+//
+//     pub fn x(&self) -> f32 {
+//         self.0
+//     }
+//
+//     pub fn y(&self) -> f32 {
+//         self.1
+//     }
+// }
+// ```
+pub(crate) fn convert_named_struct_to_tuple_struct(
+    acc: &mut Assists,
+    ctx: &AssistContext,
+) -> Option<()> {
+    let strukt = ctx
+        .find_node_at_offset::<ast::Struct>()
+        .map(Either::Left)
+        .or_else(|| ctx.find_node_at_offset::<ast::Variant>().map(Either::Right))?;
+    let field_list = strukt.as_ref().either(|s| s.field_list(), |v| v.field_list())?;
+    let record_fields = match field_list {
+        ast::FieldList::RecordFieldList(it) => it,
+        ast::FieldList::TupleFieldList(_) => return None,
+    };
+    // Inside the fields, field-specific assists are what the user is after.
+    if record_fields.syntax().text_range().contains(ctx.offset()) {
+        return None;
+    }
+    let strukt_def = match &strukt {
+        Either::Left(s) => Either::Left(ctx.sema.to_def(s)?),
+        Either::Right(v) => Either::Right(ctx.sema.to_def(v)?),
+    };
+    let target = strukt.as_ref().either(|s| s.syntax(), |v| v.syntax()).text_range();
+
+    acc.add(
+        AssistId("convert_named_struct_to_tuple_struct", AssistKind::RefactorRewrite),
+        "Convert to tuple struct",
+        target,
+        |edit| {
+            let names = record_fields
+                .fields()
+                .map(|it| it.name().map(|name| name.to_string()).unwrap_or_default())
+                .collect::<Vec<_>>();
+            let rewritten = edit_struct_references(ctx, edit, strukt_def, &names);
+            edit_field_references(ctx, edit, record_fields.fields(), &rewritten);
+            edit_struct_def(ctx, edit, &strukt, record_fields);
+        },
+    )
+}
+
+fn edit_struct_def(
+    ctx: &AssistContext,
+    edit: &mut AssistBuilder,
+    strukt: &Either<ast::Struct, ast::Variant>,
+    record_fields: ast::RecordFieldList,
+) {
+    let tuple_fields = record_fields
+        .fields()
+        .filter_map(|f| {
+            let mut buf = f.attrs().map(|attr| format!("{} ", attr)).collect::<String>();
+            if let Some(visibility) = f.visibility() {
+                buf.push_str(&format!("{} ", visibility));
+            }
+            buf.push_str(&f.ty()?.to_string());
+            Some(buf)
+        })
+        .join(", ");
+
+    edit.edit_file(ctx.file_id());
+
+    let mut range = record_fields.syntax().text_range();
+    let mut replacement = format!("({})", tuple_fields);
+    if let Either::Left(strukt) = strukt {
+        // The `where` clause of a tuple struct comes after its fields.
+        if let Some(w) = strukt.where_clause() {
+            range = TextRange::new(w.syntax().text_range().start(), range.end());
+            let where_clause = w.syntax().text().to_string();
+            replacement.push('\n');
+            replacement.push_str(where_clause.trim_end().trim_end_matches(','));
+        }
+        replacement.push(';');
+    }
+    let preceding_ws = strukt
+        .as_ref()
+        .either(|s| s.syntax().clone(), |v| v.syntax().clone())
+        .children_with_tokens()
+        .filter_map(|it| it.into_token())
+        .find(|it| it.kind() == SyntaxKind::WHITESPACE && it.text_range().end() == range.start());
+    if let Some(ws) = preceding_ws {
+        range = TextRange::new(ws.text_range().start(), range.end());
+    }
+
+    edit.replace(range, replacement);
+}
+
+/// Rewrites record literals and patterns of the struct into their tuple forms. Returns the
+/// rewritten ranges, so that field references within them are left alone.
+fn edit_struct_references(
+    ctx: &AssistContext,
+    edit: &mut AssistBuilder,
+    strukt: Either<hir::Struct, hir::Variant>,
+    names: &[String],
+) -> FxHashMap<FileId, Vec<TextRange>> {
+    let strukt_def = match strukt {
+        Either::Left(s) => Definition::ModuleDef(hir::ModuleDef::Adt(hir::Adt::Struct(s))),
+        Either::Right(v) => Definition::ModuleDef(hir::ModuleDef::Variant(v)),
+    };
+    let usages = strukt_def.usages(&ctx.sema).include_self_refs().all();
+    let field_index = |name: &str| names.iter().position(|it| it == name);
+
+    let edit_node = |edit: &mut AssistBuilder, r: &FileReference| -> Option<TextRange> {
+        let name_ref = r.name.as_name_ref()?;
+        let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+        let path = std::iter::successors(Some(path), |it| it.parent_path()).last()?;
+        let parent = path.syntax().parent()?;
+        match_ast! {
+            match parent {
+                ast::RecordExpr(record_expr) => {
+                    let field_list = record_expr.record_expr_field_list()?;
+                    // `..base` has to keep the record form, field references take care of it.
+                    if field_list.spread().is_some() {
+                        return None;
+                    }
+                    let mut args = vec![None; names.len()];
+                    let mut in_order = true;
+                    let mut all_pure = true;
+                    let mut prev_idx = None;
+                    for field in field_list.fields() {
+                        let idx = field_index(&field.field_name()?.to_string())?;
+                        let expr = field.expr()?;
+                        in_order &= prev_idx.map_or(true, |prev| prev < idx);
+                        all_pure &= matches!(expr, ast::Expr::Literal(_) | ast::Expr::PathExpr(_));
+                        prev_idx = Some(idx);
+                        args[idx] = Some(expr.to_string());
+                    }
+                    // Reordering the initializers would change their evaluation order, keep the
+                    // record form with indices instead, field references take care of it.
+                    if !in_order && !all_pure {
+                        return None;
+                    }
+                    let args = args.into_iter().collect::<Option<Vec<_>>>()?;
+                    let range = record_expr.syntax().text_range();
+                    edit.replace(range, format!("{}({})", path, args.join(", ")));
+                    Some(range)
+                },
+                ast::RecordPat(record_pat) => {
+                    let field_list = record_pat.record_pat_field_list()?;
+                    let mut pats = vec![None; names.len()];
+                    for field in field_list.fields() {
+                        let idx = field_index(&field.field_name()?.to_string())?;
+                        pats[idx] = Some(field.pat()?.to_string());
+                    }
+                    let has_rest = field_list.rest_pat().is_some();
+                    let len = match pats.iter().rposition(Option::is_some) {
+                        Some(last) if has_rest => last + 1,
+                        None if has_rest => 0,
+                        _ => names.len(),
+                    };
+                    let mut pats = pats
+                        .into_iter()
+                        .take(len)
+                        .map(|it| it.unwrap_or_else(|| "_".to_string()))
+                        .collect::<Vec<_>>();
+                    if has_rest && len < names.len() {
+                        pats.push("..".to_string());
+                    }
+                    let range = record_pat.syntax().text_range();
+                    edit.replace(range, format!("{}({})", path, pats.join(", ")));
+                    Some(range)
+                },
+                _ => None,
+            }
+        }
+    };
+
+    let mut rewritten: FxHashMap<FileId, Vec<TextRange>> = FxHashMap::default();
+    for (file_id, refs) in usages {
+        edit.edit_file(file_id);
+        for r in refs {
+            if let Some(range) = edit_node(edit, &r) {
+                rewritten.entry(file_id).or_default().push(range);
+            }
+        }
+    }
+    rewritten
+}
+
+fn edit_field_references(
+    ctx: &AssistContext,
+    edit: &mut AssistBuilder,
+    fields: impl Iterator<Item = ast::RecordField>,
+    rewritten: &FxHashMap<FileId, Vec<TextRange>>,
+) {
+    for (idx, field) in fields.enumerate() {
+        let field = match ctx.sema.to_def(&field) {
+            Some(it) => it,
+            None => continue,
+        };
+        let def = Definition::Field(field);
+        let usages = def.usages(&ctx.sema).all();
+        for (file_id, refs) in usages {
+            edit.edit_file(file_id);
+            let skipped = rewritten.get(&file_id).map(Vec::as_slice).unwrap_or_default();
+            for r in refs {
+                if skipped.iter().any(|it| it.contains_range(r.range)) {
+                    continue;
+                }
+                // Shorthands like `S { field }` need to spell out the index.
+                let shorthand = r.name.syntax().ancestors().find_map(|node| {
+                    match_ast! {
+                        match node {
+                            ast::RecordExprField(it) => Some(
+                                it.name_ref()
+                                    .is_none()
+                                    .then(|| Some((it.syntax().text_range(), it.expr()?.to_string())))
+                                    .flatten(),
+                            ),
+                            ast::RecordPatField(it) => Some(
+                                it.name_ref()
+                                    .is_none()
+                                    .then(|| Some((it.syntax().text_range(), it.pat()?.to_string())))
+                                    .flatten(),
+                            ),
+                            _ => None,
+                        }
+                    }
+                });
+                match shorthand.flatten() {
+                    Some((range, value)) => edit.replace(range, format!("{}: {}", idx, value)),
+                    None => edit.replace(r.range, idx.to_string()),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn not_applicable_other_than_record_struct() {
+        check_assist_not_applicable(convert_named_struct_to_tuple_struct, r#"struct Foo$0(u32)"#);
+        check_assist_not_applicable(convert_named_struct_to_tuple_struct, r#"struct Foo$0;"#);
+    }
+
+    #[test]
+    fn not_applicable_other_than_record_variant() {
+        check_assist_not_applicable(
+            convert_named_struct_to_tuple_struct,
+            r#"enum Enum { Variant$0(usize) };"#,
+        );
+        check_assist_not_applicable(
+            convert_named_struct_to_tuple_struct,
+            r#"enum Enum { Variant$0 }"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_inside_fields() {
+        check_assist_not_applicable(
+            convert_named_struct_to_tuple_struct,
+            r#"struct Foo { $0bar: u32 }"#,
+        );
+    }
+
+    #[test]
+    fn convert_simple_struct() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct Inner;
+struct A$0 { inner: Inner }
+
+impl A {
+    fn new(inner: Inner) -> A {
+        A { inner }
+    }
+
+    fn new_with_default() -> A {
+        A::new(Inner)
+    }
+
+    fn into_inner(self) -> Inner {
+        self.inner
+    }
+}"#,
+            r#"
+struct Inner;
+struct A(Inner);
+
+impl A {
+    fn new(inner: Inner) -> A {
+        A(inner)
+    }
+
+    fn new_with_default() -> A {
+        A::new(Inner)
+    }
+
+    fn into_inner(self) -> Inner {
+        self.0
+    }
+}"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_referenced_via_self_kw() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct Inner;
+struct A$0 { inner: Inner }
+
+impl A {
+    fn new(inner: Inner) -> Self {
+        Self { inner }
+    }
+
+    fn into_inner(self) -> Inner {
+        let Self { inner } = self;
+        inner
+    }
+}"#,
+            r#"
+struct Inner;
+struct A(Inner);
+
+impl A {
+    fn new(inner: Inner) -> Self {
+        Self(inner)
+    }
+
+    fn into_inner(self) -> Inner {
+        let Self(inner) = self;
+        inner
+    }
+}"#,
+        );
+    }
+
+    #[test]
+    fn convert_destructured_struct_in_field_order() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct A$0 { x: u8, y: u16, z: u32 }
+
+fn f(a: A) -> A {
+    let A { z: third, x, .. } = a;
+    let A { y, .. } = a;
+    let A { .. } = a;
+    let A { z, y: _, x: first } = a;
+    A { z: 3, y: 2, x: 1 }
+}"#,
+            r#"
+struct A(u8, u16, u32);
+
+fn f(a: A) -> A {
+    let A(x, _, third) = a;
+    let A(_, y, ..) = a;
+    let A(..) = a;
+    let A(first, _, z) = a;
+    A(1, 2, 3)
+}"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_with_spread_and_alias() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct A$0 { x: u8, y: u16 }
+type B = A;
+
+fn f(a: A) -> B {
+    let B { y, .. } = a;
+    B { x: 1, ..a }
+}
+
+fn g(x: u8, a: A) -> A {
+    A { x, ..a }
+}"#,
+            r#"
+struct A(u8, u16);
+type B = A;
+
+fn f(a: A) -> B {
+    let B { 1: y, .. } = a;
+    B { 0: 1, ..a }
+}
+
+fn g(x: u8, a: A) -> A {
+    A { 0: x, ..a }
+}"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_keeps_evaluation_order() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct A$0 { x: u8, y: u16 }
+
+fn f() -> u8 { 0 }
+fn g() -> u16 { 0 }
+
+fn h() -> A {
+    let a = A { y: g(), x: f() };
+    let b = A { x: f() };
+    A { y: 2, x: 1 }
+}"#,
+            r#"
+struct A(u8, u16);
+
+fn f() -> u8 { 0 }
+fn g() -> u16 { 0 }
+
+fn h() -> A {
+    let a = A { 1: g(), 0: f() };
+    let b = A { 0: f() };
+    A(1, 2)
+}"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_with_visibility_attrs_and_where_clause() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct Wrap$0<T>
+where
+    T: Display,
+{ #[cfg(test)] pub field1: T, pub(crate) field2: u32 }
+"#,
+            r#"
+struct Wrap<T>(#[cfg(test)] pub T, pub(crate) u32)
+where
+    T: Display;
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_variant() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+enum E {
+    V$0 { x: u32, y: bool },
+}
+
+fn f(e: E) -> u32 {
+    match e {
+        E::V { x, .. } => x,
+    }
+}
+
+fn g() -> E {
+    E::V { y: true, x: 0 }
+}"#,
+            r#"
+enum E {
+    V(u32, bool),
+}
+
+fn f(e: E) -> u32 {
+    match e {
+        E::V(x, ..) => x,
+    }
+}
+
+fn g() -> E {
+    E::V(0, true)
+}"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_with_references_in_other_crate() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+//- /lib.rs crate:dep
+pub struct A$0 { pub x: u32, pub y: u32 }
+pub enum E { V { a: u32 } }
+//- /main.rs crate:main deps:dep
+fn f(a: dep::A) -> u32 {
+    let dep::A { x, .. } = a;
+    let b = dep::A { x: 1, y: 2 };
+    a.y + x
+}
+"#,
+            r#"
+//- /lib.rs
+pub struct A(pub u32, pub u32);
+pub enum E { V { a: u32 } }
+//- /main.rs
+fn f(a: dep::A) -> u32 {
+    let dep::A(x, ..) = a;
+    let b = dep::A(1, 2);
+    a.1 + x
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_variant_with_references_in_other_crate() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+//- /lib.rs crate:dep
+pub enum E { V$0 { a: u32, b: bool } }
+//- /main.rs crate:main deps:dep
+fn f(e: dep::E) -> u32 {
+    let dep::E::V { a, b: _ } = e;
+    a
+}
+"#,
+            r#"
+//- /lib.rs
+pub enum E { V(u32, bool) }
+//- /main.rs
+fn f(e: dep::E) -> u32 {
+    let dep::E::V(a, _) = e;
+    a
+}
+"#,
+        );
+    }
+}
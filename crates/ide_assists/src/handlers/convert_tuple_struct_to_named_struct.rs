@@ -136,20 +136,34 @@ fn edit_struct_references(
         match_ast! {
             match node {
                 ast::TupleStructPat(tuple_struct_pat) => {
+                    let pats = tuple_struct_pat.fields().collect::<Vec<_>>();
+                    // `..` can stand for any number of fields, so the patterns after it
+                    // correspond to the last fields.
+                    let rest_idx = pats.iter().position(|it| matches!(it, ast::Pat::RestPat(_)));
+                    let named_pats = match rest_idx {
+                        Some(idx) => {
+                            let after = &pats[idx + 1..];
+                            let after_names = &names[names.len().saturating_sub(after.len())..];
+                            names.iter().zip(&pats[..idx]).chain(after_names.iter().zip(after)).collect::<Vec<_>>()
+                        }
+                        None => names.iter().zip(&pats).collect(),
+                    };
+                    let mut fields = named_pats
+                        .into_iter()
+                        .map(|(name, pat)| {
+                            ast::make::record_pat_field(
+                                ast::make::name_ref(&name.to_string()),
+                                pat.clone(),
+                            )
+                            .to_string()
+                        })
+                        .collect::<Vec<_>>();
+                    if rest_idx.is_some() {
+                        fields.push("..".to_string());
+                    }
                     edit.replace(
                         tuple_struct_pat.syntax().text_range(),
-                        ast::make::record_pat_with_fields(
-                            tuple_struct_pat.path()?,
-                            ast::make::record_pat_field_list(tuple_struct_pat.fields().zip(names).map(
-                                |(pat, name)| {
-                                    ast::make::record_pat_field(
-                                        ast::make::name_ref(&name.to_string()),
-                                        pat,
-                                    )
-                                },
-                            )),
-                        )
-                        .to_string(),
+                        format!("{} {{ {} }}", tuple_struct_pat.path()?, fields.join(", ")),
                     );
                 },
                 // for tuple struct creations like Foo(42)
@@ -834,6 +848,93 @@ use crate::{A::Variant, Inner};
 fn f() {
     let a = Variant { field1: Inner };
 }
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_with_rest_pattern() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            r#"
+struct A$0(u8, u16, u32);
+fn f(a: A) -> u32 {
+    let A(x, ..) = a;
+    let A(.., z) = a;
+    let A(_, y, ..) = a;
+    let A(..) = a;
+    z
+}
+"#,
+            r#"
+struct A { field1: u8, field2: u16, field3: u32 }
+fn f(a: A) -> u32 {
+    let A { field1: x, .. } = a;
+    let A { field3: z, .. } = a;
+    let A { field1: _, field2: y, .. } = a;
+    let A { .. } = a;
+    z
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_struct_with_references_in_other_crate() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            r#"
+//- /lib.rs crate:dep
+pub struct A$0(pub u32, pub u32);
+//- /main.rs crate:main deps:dep
+fn f(a: dep::A) -> u32 {
+    let dep::A(x, ..) = a;
+    let b = dep::A(1, 2);
+    a.1 + x
+}
+"#,
+            r#"
+//- /lib.rs
+pub struct A { pub field1: u32, pub field2: u32 }
+//- /main.rs
+fn f(a: dep::A) -> u32 {
+    let dep::A { field1: x, .. } = a;
+    let b = dep::A { field1: 1, field2: 2 };
+    a.field2 + x
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_variant_with_references_in_other_crate() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            r#"
+//- /lib.rs crate:dep
+pub enum E { V$0(u32, bool) }
+//- /main.rs crate:main deps:dep
+fn f(e: dep::E) -> u32 {
+    match e {
+        dep::E::V(x, ..) => x,
+    }
+}
+fn g() -> dep::E {
+    dep::E::V(0, true)
+}
+"#,
+            r#"
+//- /lib.rs
+pub enum E { V { field1: u32, field2: bool } }
+//- /main.rs
+fn f(e: dep::E) -> u32 {
+    match e {
+        dep::E::V { field1: x, .. } => x,
+    }
+}
+fn g() -> dep::E {
+    dep::E::V { field1: 0, field2: true }
+}
 "#,
         );
     }
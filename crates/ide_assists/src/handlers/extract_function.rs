@@ -15,13 +15,13 @@ use ide_db::{
     RootDatabase,
 };
 use itertools::Itertools;
-use rustc_hash::FxHasher;
+use rustc_hash::{FxHashSet, FxHasher};
 use stdx::format_to;
 use syntax::{
     ast::{
         self,
         edit::{AstNodeEdit, IndentLevel},
-        AstNode,
+        AstNode, HasGenericParams, HasName,
     },
     match_ast, ted, SyntaxElement,
     SyntaxKind::{self, COMMENT},
@@ -82,6 +82,7 @@ pub(crate) fn extract_function(acc: &mut Assists, ctx: &AssistContext) -> Option
     let (locals_used, self_param) = body.analyze(&ctx.sema);
 
     let anchor = if self_param.is_some() { Anchor::Method } else { Anchor::Freestanding };
+    let (generic_param_lists, where_clauses) = generic_parents(&node, &anchor);
     let insert_after = node_to_insert_after(&body, anchor)?;
     let module = ctx.sema.scope(&insert_after).module()?;
 
@@ -116,12 +117,14 @@ pub(crate) fn extract_function(acc: &mut Assists, ctx: &AssistContext) -> Option
                 body,
                 outliving_locals,
                 mods: container_info,
+                generic_param_lists,
+                where_clauses,
             };
 
             let new_indent = IndentLevel::from_node(&insert_after);
             let old_indent = fun.body.indent_level();
 
-            builder.replace(target_range, make_call(ctx, &fun, old_indent));
+            builder.replace(target_range, make_call(ctx, module, &fun, old_indent));
 
             let fn_def = format_function(ctx, module, &fun, old_indent, new_indent);
             let insert_offset = insert_after.text_range().end();
@@ -157,6 +160,137 @@ pub(crate) fn extract_function(acc: &mut Assists, ctx: &AssistContext) -> Option
     )
 }
 
+// Assist: extract_closure
+//
+// Extracts selected expression or statements into a local closure capturing the variables it
+// uses.
+//
+// ```
+// fn main() {
+//     let n = 1;
+//     let m = $0n * 2$0 + 1;
+// }
+// ```
+// ->
+// ```
+// fn main() {
+//     let n = 1;
+//     let $0fun_name = || -> i32 {
+//         n * 2
+//     };
+//     let m = fun_name() + 1;
+// }
+// ```
+pub(crate) fn extract_closure(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let range = ctx.selection_trimmed();
+    if range.is_empty() {
+        return None;
+    }
+
+    let node = match ctx.covering_element() {
+        syntax::NodeOrToken::Node(n) => n,
+        syntax::NodeOrToken::Token(t) if t.kind() == COMMENT => return None,
+        syntax::NodeOrToken::Token(t) => t.parent()?,
+    };
+    let body = extraction_target(&node, range)?;
+    let container_info = body.analyze_container(&ctx.sema)?;
+    if container_info.is_const {
+        return None;
+    }
+    let control_flow = body.external_control_flow(ctx, &container_info)?;
+    if control_flow.kind.is_some() || control_flow.is_async {
+        // `return`, `break`, `?` and `.await` would mean something else inside of a closure.
+        return None;
+    }
+
+    // An extracted expression is preceded by the closure, so it must not use locals bound by the
+    // statement it is part of.
+    let anchor = match &body {
+        FunctionBody::Expr(expr) => Some(expr.syntax().ancestors().find(|it| {
+            it.parent().map_or(false, |parent| ast::StmtList::can_cast(parent.kind()))
+        })?),
+        FunctionBody::Span { .. } => None,
+    };
+    let (locals_used, _) = body.analyze(&ctx.sema);
+    let captured = locals_used
+        .iter()
+        .copied()
+        .filter(|local| is_defined_outside_of_body(ctx, &body, &local.source(ctx.db())))
+        .collect::<Vec<_>>();
+    if let Some(anchor) = &anchor {
+        let bound_by_anchor = captured.iter().any(|local| {
+            let src = local.source(ctx.db());
+            anchor.text_range().contains_range(either_syntax(&src.value).text_range())
+        });
+        if bound_by_anchor {
+            return None;
+        }
+    }
+    let requires_mut = captured.iter().any(|&local| {
+        has_exclusive_usages(ctx, &LocalUsages::find_local_usages(ctx, local), &body)
+    });
+
+    let module = ctx.sema.scope(&node).module()?;
+    let ret_ty = body.return_ty(ctx)?;
+    let ret_values = body.ret_values(ctx, node.parent().as_ref().unwrap_or(&node));
+
+    let target_range = body.text_range();
+    acc.add(
+        AssistId("extract_closure", crate::AssistKind::RefactorExtract),
+        "Extract into closure",
+        target_range,
+        move |builder| {
+            let outliving_locals: Vec<_> = ret_values.collect();
+            if stdx::never!(!outliving_locals.is_empty() && !ret_ty.is_unit()) {
+                return;
+            }
+
+            let fun = Function {
+                name: make::name_ref("fun_name"),
+                self_param: None,
+                params: Vec::new(),
+                control_flow,
+                ret_ty,
+                body,
+                outliving_locals,
+                mods: container_info,
+                generic_param_lists: Vec::new(),
+                where_clauses: Vec::new(),
+            };
+
+            let old_indent = fun.body.indent_level();
+            let indent = anchor.as_ref().map_or(old_indent, IndentLevel::from_node);
+            let closure_body = make_body(ctx, old_indent, indent, &fun);
+            let ret_ty = match fun.return_type(ctx) {
+                FunType::Unit => String::new(),
+                ty => format!(" -> {}", ty.make_ty(ctx, module)),
+            };
+            let mut_kw = if requires_mut { "mut " } else { "" };
+            let name = match ctx.config.snippet_cap {
+                Some(_) => format!("$0{}", fun.name),
+                None => fun.name.to_string(),
+            };
+            let closure =
+                format!("let {}{} = ||{} {};\n{}", mut_kw, name, ret_ty, closure_body, indent);
+
+            let (insert_offset, call) = match &anchor {
+                Some(anchor) => (anchor.text_range().start(), format!("{}()", fun.name)),
+                None => (target_range.start(), make_call(ctx, module, &fun, indent)),
+            };
+            let (range, text) = if insert_offset == target_range.start() {
+                (target_range, format!("{}{}", closure, call))
+            } else {
+                builder.replace(target_range, call);
+                (TextRange::empty(insert_offset), closure)
+            };
+            match ctx.config.snippet_cap {
+                Some(cap) => builder.replace_snippet(cap, range, text),
+                None => builder.replace(range, text),
+            }
+        },
+    )
+}
+
 /// Try to guess what user wants to extract
 ///
 /// We have basically have two cases:
@@ -216,6 +350,9 @@ struct Function {
     body: FunctionBody,
     outliving_locals: Vec<OutlivedLocal>,
     mods: ContainerInfo,
+    /// Generic parameters in scope of the body that are not in scope of the new function.
+    generic_param_lists: Vec<ast::GenericParamList>,
+    where_clauses: Vec<ast::WhereClause>,
 }
 
 #[derive(Debug)]
@@ -1081,6 +1218,40 @@ fn either_syntax(value: &Either<ast::IdentPat, ast::SelfParam>) -> &SyntaxNode {
     }
 }
 
+/// Collects the generics of the function containing `node`, and of its impl or trait when the
+/// extracted function goes outside of it.
+fn generic_parents(
+    node: &SyntaxNode,
+    anchor: &Anchor,
+) -> (Vec<ast::GenericParamList>, Vec<ast::WhereClause>) {
+    let fn_ = match node.ancestors().find_map(ast::Fn::cast) {
+        Some(it) => it,
+        None => return (Vec::new(), Vec::new()),
+    };
+    let mut parents: Vec<Box<dyn HasGenericParams>> = Vec::new();
+    if let Anchor::Freestanding = anchor {
+        let container = fn_
+            .syntax()
+            .parent()
+            .filter(|it| it.kind() == SyntaxKind::ASSOC_ITEM_LIST)
+            .and_then(|it| it.parent());
+        if let Some(container) = container {
+            match_ast! {
+                match container {
+                    ast::Impl(it) => parents.push(Box::new(it)),
+                    ast::Trait(it) => parents.push(Box::new(it)),
+                    _ => (),
+                }
+            }
+        }
+    }
+    parents.push(Box::new(fn_));
+
+    let generic_param_lists = parents.iter().filter_map(|it| it.generic_param_list()).collect();
+    let where_clauses = parents.iter().filter_map(|it| it.where_clause()).collect();
+    (generic_param_lists, where_clauses)
+}
+
 /// find where to put extracted function definition
 ///
 /// Function should be put right after returned node
@@ -1115,17 +1286,34 @@ fn node_to_insert_after(body: &FunctionBody, anchor: Anchor) -> Option<SyntaxNod
     last_ancestor
 }
 
-fn make_call(ctx: &AssistContext, fun: &Function, indent: IndentLevel) -> String {
+fn make_call(
+    ctx: &AssistContext,
+    module: hir::Module,
+    fun: &Function,
+    indent: IndentLevel,
+) -> String {
     let ret_ty = fun.return_type(ctx);
 
     let args = make::arg_list(fun.params.iter().map(|param| param.to_arg(ctx)));
     let name = fun.name.clone();
+    let generic_args = fun.make_generic_args(ctx, module);
     let mut call_expr = if fun.self_param.is_some() {
         let self_arg = make::expr_path(make::ext::ident_path("self"));
-        make::expr_method_call(self_arg, name, args)
+        let call_expr = make::expr_method_call(self_arg, name, args).clone_for_update();
+        if let (ast::Expr::MethodCallExpr(call), Some(generic_args)) = (&call_expr, generic_args) {
+            if let Some(name_ref) = call.name_ref() {
+                let generic_args = generic_args.clone_for_update();
+                ted::insert_raw(ted::Position::after(name_ref.syntax()), generic_args.syntax());
+            }
+        }
+        call_expr
     } else {
-        let func = make::expr_path(make::path_unqualified(make::path_segment(name)));
-        make::expr_call(func, args)
+        let segment = make::path_segment(name);
+        let segment = match generic_args {
+            Some(generic_args) => make::path_from_text(&format!("{}{}", segment, generic_args)),
+            None => make::path_unqualified(segment),
+        };
+        make::expr_call(make::expr_path(segment), args)
     };
 
     let handler = FlowHandler::from_ret_ty(fun, &ret_ty);
@@ -1302,34 +1490,46 @@ fn format_function(
     let params = fun.make_param_list(ctx, module);
     let ret_ty = fun.make_ret_ty(ctx, module);
     let body = make_body(ctx, old_indent, new_indent, fun);
+    let (generic_params, where_preds) = fun.generics(ctx, module);
+    let generic_params = if generic_params.is_empty() {
+        String::new()
+    } else {
+        make::generic_param_list(generic_params).to_string()
+    };
+    let where_clause = (!where_preds.is_empty()).then(|| make::where_clause(where_preds));
     let const_kw = if fun.mods.is_const { "const " } else { "" };
     let async_kw = if fun.control_flow.is_async { "async " } else { "" };
     let unsafe_kw = if fun.control_flow.is_unsafe { "unsafe " } else { "" };
     match ctx.config.snippet_cap {
         Some(_) => format_to!(
             fn_def,
-            "\n\n{}{}{}{}fn $0{}{}",
+            "\n\n{}{}{}{}fn $0{}{}{}",
             new_indent,
             const_kw,
             async_kw,
             unsafe_kw,
             fun.name,
+            generic_params,
             params
         ),
         None => format_to!(
             fn_def,
-            "\n\n{}{}{}{}fn {}{}",
+            "\n\n{}{}{}{}fn {}{}{}",
             new_indent,
             const_kw,
             async_kw,
             unsafe_kw,
             fun.name,
+            generic_params,
             params
         ),
     }
     if let Some(ret_ty) = ret_ty {
         format_to!(fn_def, " {}", ret_ty);
     }
+    if let Some(where_clause) = where_clause {
+        format_to!(fn_def, " {}", where_clause);
+    }
     format_to!(fn_def, " {}", body);
 
     fn_def
@@ -1342,6 +1542,85 @@ impl Function {
         make::param_list(self_param, params)
     }
 
+    /// Picks the generic parameters and where predicates the new function needs. Types are
+    /// rendered with their parameter names, so it's enough to look at the identifiers of its
+    /// signature and body.
+    fn generics(
+        &self,
+        ctx: &AssistContext,
+        module: hir::Module,
+    ) -> (Vec<ast::GenericParam>, Vec<ast::WherePred>) {
+        let params =
+            self.generic_param_lists.iter().flat_map(|it| it.generic_params()).collect::<Vec<_>>();
+        let preds = self.where_clauses.iter().flat_map(|it| it.predicates()).collect::<Vec<_>>();
+        if params.is_empty() {
+            return (Vec::new(), Vec::new());
+        }
+
+        let mut used = FxHashSet::default();
+        used.extend(idents(self.make_param_list(ctx, module).syntax()));
+        if let Some(ret_ty) = self.make_ret_ty(ctx, module) {
+            used.extend(idents(ret_ty.syntax()));
+        }
+        used.extend(idents(make_body(ctx, IndentLevel(0), IndentLevel(0), self).syntax()));
+        let mut needed_params = vec![false; params.len()];
+        let mut needed_preds = vec![false; preds.len()];
+        let mut needed_names = FxHashSet::default();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (param, needed) in params.iter().zip(&mut needed_params) {
+                let name = match generic_param_name(param) {
+                    Some(it) if !*needed && used.contains(&it) => it,
+                    _ => continue,
+                };
+                // Bounds of a parameter can mention further parameters.
+                used.extend(idents(param.syntax()));
+                needed_names.insert(name);
+                *needed = true;
+                changed = true;
+            }
+            for (pred, needed) in preds.iter().zip(&mut needed_preds) {
+                let subject = pred
+                    .ty()
+                    .map(|it| it.syntax().clone())
+                    .or_else(|| pred.lifetime().map(|it| it.syntax().clone()));
+                let applies =
+                    subject.map_or(false, |it| idents(&it).any(|it| needed_names.contains(&it)));
+                if !*needed && applies {
+                    used.extend(idents(pred.syntax()));
+                    *needed = true;
+                    changed = true;
+                }
+            }
+        }
+
+        let params = params.into_iter().zip(needed_params).filter(|(_, needed)| *needed);
+        let preds = preds.into_iter().zip(needed_preds).filter(|(_, needed)| *needed);
+        (params.map(|(param, _)| param).collect(), preds.map(|(pred, _)| pred).collect())
+    }
+
+    /// Explicit generic arguments for the call, needed when some type or const parameter can't
+    /// be inferred from the arguments.
+    fn make_generic_args(
+        &self,
+        ctx: &AssistContext,
+        module: hir::Module,
+    ) -> Option<ast::GenericArgList> {
+        let (params, _) = self.generics(ctx, module);
+        let args = params
+            .iter()
+            .filter(|it| !matches!(it, ast::GenericParam::LifetimeParam(_)))
+            .filter_map(generic_param_name)
+            .collect::<Vec<_>>();
+        let inferable =
+            idents(self.make_param_list(ctx, module).syntax()).collect::<FxHashSet<_>>();
+        if args.iter().all(|it| inferable.contains(it)) {
+            return None;
+        }
+        make::path_from_text(&format!("f::<{}>", args.join(", "))).segment()?.generic_arg_list()
+    }
+
     fn make_ret_ty(&self, ctx: &AssistContext, module: hir::Module) -> Option<ast::RetType> {
         let fun_ty = self.return_type(ctx);
         let handler = if self.mods.is_in_tail {
@@ -1388,6 +1667,22 @@ impl Function {
     }
 }
 
+fn generic_param_name(param: &ast::GenericParam) -> Option<String> {
+    match param {
+        ast::GenericParam::ConstParam(it) => it.name().map(|it| it.to_string()),
+        ast::GenericParam::LifetimeParam(it) => it.lifetime().map(|it| it.to_string()),
+        ast::GenericParam::TypeParam(it) => it.name().map(|it| it.to_string()),
+    }
+}
+
+/// Identifiers and lifetimes occurring in `node`.
+fn idents(node: &SyntaxNode) -> impl Iterator<Item = String> {
+    node.descendants_with_tokens()
+        .filter_map(SyntaxElement::into_token)
+        .filter(|it| matches!(it.kind(), SyntaxKind::IDENT | SyntaxKind::LIFETIME_IDENT))
+        .map(|it| it.text().to_string())
+}
+
 impl FunType {
     fn make_ty(&self, ctx: &AssistContext, module: hir::Module) -> ast::Type {
         match self {
@@ -4335,6 +4630,304 @@ fn foo() {
 fn $0fun_name(a: _) -> _ {
     a
 }
+"#,
+        );
+    }
+
+    #[test]
+    fn generic_fn_param() {
+        check_assist(
+            extract_function,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+fn func<T: Trait, U>(t: T, u: U) -> i32 {
+    $0t.f() + 1$0
+}
+"#,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+fn func<T: Trait, U>(t: T, u: U) -> i32 {
+    fun_name(t)
+}
+
+fn $0fun_name<T: Trait>(t: T) -> i32 {
+    t.f() + 1
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn generic_param_mentioned_in_bound() {
+        check_assist(
+            extract_function,
+            r#"
+trait Trait<U> { fn f(&self, u: U); }
+fn func<'a, T: Trait<U>, U, V>(t: &'a T, u: U, v: V) {
+    $0t.f(u);$0
+}
+"#,
+            r#"
+trait Trait<U> { fn f(&self, u: U); }
+fn func<'a, T: Trait<U>, U, V>(t: &'a T, u: U, v: V) {
+    fun_name(t, u);
+}
+
+fn $0fun_name<T: Trait<U>, U>(t: &T, u: U) {
+    t.f(u);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn generic_param_used_in_body_of_method() {
+        check_assist(
+            extract_function,
+            r#"
+trait Default { fn default() -> Self; }
+struct S;
+impl S {
+    fn func<T: Default>(&self) {
+        $0self.g();
+        let _t = T::default();$0
+    }
+    fn g(&self) {}
+}
+"#,
+            r#"
+trait Default { fn default() -> Self; }
+struct S;
+impl S {
+    fn func<T: Default>(&self) {
+        self.fun_name::<T>();
+    }
+
+    fn $0fun_name<T: Default>(&self) {
+        self.g();
+        let _t = T::default();
+    }
+    fn g(&self) {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn generic_param_used_in_body_only() {
+        check_assist(
+            extract_function,
+            r#"
+trait Default { fn default() -> Self; }
+fn func<T: Default>() {
+    $0let _t = T::default();$0
+}
+"#,
+            r#"
+trait Default { fn default() -> Self; }
+fn func<T: Default>() {
+    fun_name::<T>();
+}
+
+fn $0fun_name<T: Default>() {
+    let _t = T::default();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn where_clause_from_fn_and_impl() {
+        check_assist(
+            extract_function,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+struct Struct<T>(T);
+impl<T> Struct<T>
+where
+    T: Trait,
+{
+    fn func<V, W>(&self, t: &T, v: V, w: W) -> i32
+    where
+        V: Trait,
+        W: Trait,
+    {
+        $0t.f() + v.f()$0
+    }
+}
+"#,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+struct Struct<T>(T);
+impl<T> Struct<T>
+where
+    T: Trait,
+{
+    fn func<V, W>(&self, t: &T, v: V, w: W) -> i32
+    where
+        V: Trait,
+        W: Trait,
+    {
+        fun_name(t, v)
+    }
+}
+
+fn $0fun_name<T, V>(t: &T, v: V) -> i32 where T: Trait, V: Trait {
+    t.f() + v.f()
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn method_keeps_impl_generics_in_scope() {
+        check_assist(
+            extract_function,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+struct Struct<T>(T);
+impl<T: Trait> Struct<T> {
+    fn func<V: Trait>(&self, v: V) -> i32 {
+        $0self.0.f() + v.f()$0
+    }
+}
+"#,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+struct Struct<T>(T);
+impl<T: Trait> Struct<T> {
+    fn func<V: Trait>(&self, v: V) -> i32 {
+        self.fun_name(v)
+    }
+
+    fn $0fun_name<V: Trait>(&self, v: V) -> i32 {
+        self.0.f() + v.f()
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn closure_from_expr() {
+        check_assist(
+            extract_closure,
+            r#"
+fn foo() {
+    let n = 1;
+    let m = $0n * 2$0 + 1;
+}
+"#,
+            r#"
+fn foo() {
+    let n = 1;
+    let $0fun_name = || -> i32 {
+        n * 2
+    };
+    let m = fun_name() + 1;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn closure_from_statements_with_outliving_local() {
+        check_assist(
+            extract_closure,
+            r#"
+fn foo() {
+    let n = 1;
+    $0let m = n + 2;
+    let k = m + n;$0
+    let g = k + 3;
+}
+"#,
+            r#"
+fn foo() {
+    let n = 1;
+    let $0fun_name = || -> i32 {
+        let m = n + 2;
+        let k = m + n;
+        k
+    };
+    let k = fun_name();
+    let g = k + 3;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn closure_mutating_capture() {
+        check_assist(
+            extract_closure,
+            r#"
+fn foo() {
+    let mut n = 1;
+    $0n += 1;$0
+    let m = n;
+}
+"#,
+            r#"
+fn foo() {
+    let mut n = 1;
+    let mut $0fun_name = || {
+        n += 1;
+    };
+    fun_name();
+    let m = n;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn closure_in_generic_fn() {
+        check_assist(
+            extract_closure,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+fn foo<T: Trait>(t: T) -> i32 {
+    $0t.f() + 1$0
+}
+"#,
+            r#"
+trait Trait { fn f(&self) -> i32; }
+fn foo<T: Trait>(t: T) -> i32 {
+    let $0fun_name = || -> i32 {
+        t.f() + 1
+    };
+    fun_name()
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn closure_not_applicable_with_control_flow() {
+        check_assist_not_applicable(
+            extract_closure,
+            r#"
+fn foo() {
+    loop {
+        $0if true { break; }$0
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn closure_not_applicable_with_local_of_enclosing_statement() {
+        check_assist_not_applicable(
+            extract_closure,
+            r#"
+fn foo(opt: Option<i32>) {
+    match opt {
+        Some(x) => $0x + 1$0,
+        None => 0,
+    };
+}
 "#,
         );
     }
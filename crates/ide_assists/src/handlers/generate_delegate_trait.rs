@@ -0,0 +1,407 @@
+use hir::{self, HasSource, HasVisibility};
+use ide_db::{helpers::mod_path_to_ast, path_transform::PathTransform};
+use itertools::Itertools;
+use syntax::{
+    ast::{
+        self,
+        edit::{AstNodeEdit, IndentLevel},
+        edit_in_place::AttrsOwnerEdit,
+        make, AstNode, HasGenericParams, HasName,
+    },
+    ted, SyntaxKind, SyntaxNode,
+};
+
+use crate::{
+    utils::{convert_param_list_to_arg_list, generate_trait_impl_text},
+    AssistContext, AssistId, AssistKind, Assists, GroupLabel,
+};
+
+// Assist: generate_delegate_trait
+//
+// Generate an implementation of a trait the field implements, forwarding every item to the field.
+//
+// ```
+// trait Len {
+//     const EMPTY: bool;
+//     fn len(&self) -> usize;
+// }
+// struct Bytes;
+// impl Len for Bytes {
+//     const EMPTY: bool = false;
+//     fn len(&self) -> usize { 0 }
+// }
+//
+// struct Wrapper {
+//     inn$0er: Bytes,
+// }
+// ```
+// ->
+// ```
+// trait Len {
+//     const EMPTY: bool;
+//     fn len(&self) -> usize;
+// }
+// struct Bytes;
+// impl Len for Bytes {
+//     const EMPTY: bool = false;
+//     fn len(&self) -> usize { 0 }
+// }
+//
+// struct Wrapper {
+//     inner: Bytes,
+// }
+//
+// impl Len for Wrapper {
+//     const EMPTY: bool = <Bytes as Len>::EMPTY;
+//
+//     fn len(&self) -> usize {
+//         <Bytes as Len>::len(&self.inner)
+//     }
+// }
+// ```
+pub(crate) fn generate_delegate_trait(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let strukt = ctx.find_node_at_offset::<ast::Struct>()?;
+    let (field_name, field_ty) = match ctx.find_node_at_offset::<ast::RecordField>() {
+        Some(field) => (field.name()?.to_string(), field.ty()?),
+        None => {
+            let field = ctx.find_node_at_offset::<ast::TupleField>()?;
+            let field_list = ctx.find_node_at_offset::<ast::TupleFieldList>()?;
+            let field_list_index = field_list.fields().position(|it| it == field)?;
+            (field_list_index.to_string(), field.ty()?)
+        }
+    };
+
+    let db = ctx.db();
+    let strukt_def = ctx.sema.to_def(&strukt)?;
+    let current_module = strukt_def.module(db);
+    let strukt_ty = strukt_def.ty(db);
+    let sema_field_ty = ctx.sema.resolve_type(&field_ty)?;
+
+    let mut traits: Vec<hir::Trait> = Vec::new();
+    for imp in hir::Impl::all_for_type(db, sema_field_ty) {
+        let trait_ = match imp.trait_(db) {
+            Some(it) if !imp.is_negative(db) => it,
+            _ => continue,
+        };
+        // Traits with parameters other than `Self` would need the substitution of the field's
+        // impl, which we don't have.
+        if traits.contains(&trait_)
+            || hir::GenericDef::from(trait_).type_params(db).len() > 1
+            || trait_.is_auto(db)
+            || !trait_.is_visible_from(db, current_module)
+            || strukt_ty.impls_trait(db, trait_, &[])
+        {
+            continue;
+        }
+        traits.push(trait_);
+    }
+
+    let target = field_ty.syntax().text_range();
+    let adt = ast::Adt::Struct(strukt.clone());
+    for trait_ in traits {
+        let trait_path = match current_module.find_use_path(db, hir::ModuleDef::Trait(trait_)) {
+            Some(it) => mod_path_to_ast(&it),
+            None => continue,
+        };
+        let items = match delegate_items(ctx, &strukt, trait_, &trait_path, &field_ty, &field_name)
+        {
+            Some(it) if !it.is_empty() => it,
+            _ => continue,
+        };
+        let unsafe_ = if trait_.is_unsafe(db) { "unsafe " } else { "" };
+        acc.add_group(
+            &GroupLabel("Generate delegate trait impls…".to_owned()),
+            AssistId("generate_delegate_trait", AssistKind::Generate),
+            format!("Generate delegate impl of `{}` for `{}`", trait_path, field_name),
+            target,
+            |builder| {
+                let code = items.iter().join("\n\n");
+                let mut impl_text = generate_trait_impl_text(&adt, &trait_path.to_string(), &code);
+                if !unsafe_.is_empty() {
+                    impl_text = impl_text.replacen("impl", "unsafe impl", 1);
+                }
+                builder.insert(strukt.syntax().text_range().end(), impl_text);
+            },
+        );
+    }
+    Some(())
+}
+
+/// Renders the forwarding version of every item of `trait_`, or `None` if a required item can't
+/// be forwarded. Provided items that can't be forwarded keep their default implementation.
+fn delegate_items(
+    ctx: &AssistContext,
+    strukt: &ast::Struct,
+    trait_: hir::Trait,
+    trait_path: &ast::Path,
+    field_ty: &ast::Type,
+    field_name: &str,
+) -> Option<Vec<String>> {
+    let db = ctx.db();
+    let target_scope = ctx.sema.scope(strukt.syntax());
+    let source_scope = ctx.sema.scope_for_def(trait_);
+    let impl_ = make::impl_trait(trait_path.clone(), make::ext::ident_path("Self"));
+    let transform = PathTransform::trait_impl(&target_scope, &source_scope, trait_, impl_);
+    let qualified = |name: &str| format!("<{} as {}>::{}", field_ty, trait_path, name);
+
+    let mut items = Vec::new();
+    for item in trait_.items(db) {
+        match item {
+            hir::AssocItem::Function(func) => {
+                let fn_ = func.source(db)?.value.clone_for_update();
+                transform.apply(fn_.syntax());
+                fn_.remove_attrs_and_docs();
+                let has_default = fn_.body().is_some();
+                let call = match delegate_call(&fn_, field_name, &qualified) {
+                    Some(it) => it,
+                    None if has_default => continue,
+                    None => return None,
+                };
+                let fn_ = fn_.reset_indent().clone_for_update();
+                let body = make::block_expr(None, Some(call)).clone_for_update();
+                ted::replace(fn_.get_or_create_body().syntax(), body.syntax());
+                items.push(format!("{}{}", IndentLevel(1), fn_.indent(IndentLevel(1))));
+            }
+            hir::AssocItem::TypeAlias(alias) => {
+                let name = alias.name(db).to_string();
+                let text = format!("type {} = {};", name, qualified(&name));
+                items.push(format!("{}{}", IndentLevel(1), text));
+            }
+            hir::AssocItem::Const(konst) => {
+                let source = konst.source(db)?.value.clone_for_update();
+                transform.apply(source.syntax());
+                let has_default = source.body().is_some();
+                let (name, ty) = match (source.name(), source.ty()) {
+                    (Some(name), Some(ty)) if !mentions_self(ty.syntax()) => (name, ty),
+                    _ if has_default => continue,
+                    _ => return None,
+                };
+                let expr = make::expr_path(make::path_from_text(&qualified(&name.to_string())));
+                let konst = make::item_const(None, name, ty, expr);
+                items.push(format!("{}{}", IndentLevel(1), konst));
+            }
+        }
+    }
+    Some(items)
+}
+
+/// Builds `<Field as Trait>::method(&self.field, args)` for `fn_`, unless its signature mentions
+/// `Self` in a position we can't forward.
+fn delegate_call(
+    fn_: &ast::Fn,
+    field_name: &str,
+    qualified: &dyn Fn(&str) -> String,
+) -> Option<ast::Expr> {
+    let name = fn_.name()?.to_string();
+    let param_list = fn_.param_list()?;
+    let signature_mentions_self = param_list.params().any(|it| mentions_self(it.syntax()))
+        || fn_.ret_type().map_or(false, |it| mentions_self(it.syntax()))
+        || fn_.generic_param_list().map_or(false, |it| mentions_self(it.syntax()))
+        || fn_.where_clause().map_or(false, |it| mentions_self(it.syntax()));
+    if signature_mentions_self {
+        return None;
+    }
+
+    let mut args = Vec::new();
+    if let Some(self_param) = param_list.self_param() {
+        if self_param.ty().is_some() {
+            return None;
+        }
+        let field = make::expr_field(make::expr_path(make::ext::ident_path("self")), field_name);
+        let receiver = match self_param.amp_token() {
+            Some(_) => make::expr_ref(field, self_param.mut_token().is_some()),
+            None => field,
+        };
+        args.push(receiver);
+    }
+    args.extend(convert_param_list_to_arg_list(param_list).args());
+
+    let callee = make::expr_path(make::path_from_text(&qualified(&name)));
+    Some(make::expr_call(callee, make::arg_list(args)))
+}
+
+/// Checks for uses of `Self` other than as the qualifier of an associated item like `Self::Item`.
+fn mentions_self(node: &SyntaxNode) -> bool {
+    node.descendants_with_tokens().filter_map(|it| it.into_token()).any(|token| {
+        if token.kind() != SyntaxKind::IDENT || token.text() != "Self" {
+            return false;
+        }
+        let path = match token.ancestors().find_map(ast::Path::cast) {
+            Some(it) => it,
+            None => return true,
+        };
+        path.parent_path().map_or(true, |parent| parent.qualifier() != Some(path))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn not_applicable_without_trait_impls() {
+        check_assist_not_applicable(
+            generate_delegate_trait,
+            r#"
+struct Inner;
+impl Inner { fn len(&self) -> usize { 0 } }
+struct Wrapper { inn$0er: Inner }
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_when_already_implemented() {
+        check_assist_not_applicable(
+            generate_delegate_trait,
+            r#"
+trait Len { fn len(&self) -> usize; }
+struct Inner;
+impl Len for Inner { fn len(&self) -> usize { 0 } }
+struct Wrapper { inn$0er: Inner }
+impl Len for Wrapper { fn len(&self) -> usize { 1 } }
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_when_required_method_returns_self() {
+        check_assist_not_applicable(
+            generate_delegate_trait,
+            r#"
+trait Dup { fn dup(&self) -> Self; }
+struct Inner;
+impl Dup for Inner { fn dup(&self) -> Self { Inner } }
+struct Wrapper { inn$0er: Inner }
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_for_generic_trait() {
+        check_assist_not_applicable(
+            generate_delegate_trait,
+            r#"
+trait Convert<T> { fn convert(&self) -> T; }
+struct Inner;
+impl Convert<u8> for Inner { fn convert(&self) -> u8 { 0 } }
+struct Wrapper { inn$0er: Inner }
+"#,
+        );
+    }
+
+    #[test]
+    fn delegate_tuple_field_with_assoc_type() {
+        check_assist(
+            generate_delegate_trait,
+            r#"
+trait Source {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+    fn consume(self, limit: usize) -> usize;
+    fn provided(&self) -> Self where Self: Sized { loop {} }
+}
+struct Inner;
+impl Source for Inner {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> { None }
+    fn consume(self, limit: usize) -> usize { limit }
+}
+struct Wrapper(i32, In$0ner);
+"#,
+            r#"
+trait Source {
+    type Item;
+    fn next(&mut self) -> Option<Self::Item>;
+    fn consume(self, limit: usize) -> usize;
+    fn provided(&self) -> Self where Self: Sized { loop {} }
+}
+struct Inner;
+impl Source for Inner {
+    type Item = u8;
+    fn next(&mut self) -> Option<u8> { None }
+    fn consume(self, limit: usize) -> usize { limit }
+}
+struct Wrapper(i32, Inner);
+
+impl Source for Wrapper {
+    type Item = <Inner as Source>::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        <Inner as Source>::next(&mut self.1)
+    }
+
+    fn consume(self, limit: usize) -> usize {
+        <Inner as Source>::consume(self.1, limit)
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn delegate_generic_struct_and_qualified_paths() {
+        check_assist(
+            generate_delegate_trait,
+            r#"
+mod fmt {
+    pub struct Formatter;
+    pub trait Show {
+        fn show(&self, f: &mut Formatter) -> bool;
+    }
+    pub struct Plain;
+    impl Show for Plain {
+        fn show(&self, f: &mut Formatter) -> bool { true }
+    }
+}
+struct Wrapper<T> { inn$0er: fmt::Plain, extra: T }
+"#,
+            r#"
+mod fmt {
+    pub struct Formatter;
+    pub trait Show {
+        fn show(&self, f: &mut Formatter) -> bool;
+    }
+    pub struct Plain;
+    impl Show for Plain {
+        fn show(&self, f: &mut Formatter) -> bool { true }
+    }
+}
+struct Wrapper<T> { inner: fmt::Plain, extra: T }
+
+impl<T> fmt::Show for Wrapper<T> {
+    fn show(&self, f: &mut fmt::Formatter) -> bool {
+        <fmt::Plain as fmt::Show>::show(&self.inner, f)
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn delegate_unsafe_trait() {
+        check_assist(
+            generate_delegate_trait,
+            r#"
+unsafe trait Raw { unsafe fn ptr(&self) -> *const u8; }
+struct Inner;
+unsafe impl Raw for Inner { unsafe fn ptr(&self) -> *const u8 { loop {} } }
+struct Wrapper { inn$0er: Inner }
+"#,
+            r#"
+unsafe trait Raw { unsafe fn ptr(&self) -> *const u8; }
+struct Inner;
+unsafe impl Raw for Inner { unsafe fn ptr(&self) -> *const u8 { loop {} } }
+struct Wrapper { inner: Inner }
+
+unsafe impl Raw for Wrapper {
+    unsafe fn ptr(&self) -> *const u8 {
+        <Inner as Raw>::ptr(&self.inner)
+    }
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,434 @@
+use hir::{AsAssocItem, AssocItemContainer, HasVisibility, Visibility};
+use ide_db::ty_filter::TryEnum;
+use itertools::Itertools;
+use stdx::{format_to, to_lower_snake_case};
+use syntax::{
+    ast::{self, edit::IndentLevel, HasDocComments, HasName, HasVisibility as _},
+    AstNode,
+};
+
+use crate::{AssistContext, AssistId, AssistKind, Assists};
+
+// Assist: generate_documentation_template
+//
+// Adds a documentation template above a public function definition, including the `# Errors`,
+// `# Panics` and `# Safety` sections that apply to it and a doc-test skeleton.
+//
+// ```
+// pub fn add$0(a: i32, b: i32) -> i32 { a + b }
+// ```
+// ->
+// ```
+// /// .
+// ///
+// /// # Examples
+// ///
+// /// ```
+// /// use test::add;
+// ///
+// /// assert_eq!(add(a, b), );
+// /// ```
+// pub fn add(a: i32, b: i32) -> i32 { a + b }
+// ```
+pub(crate) fn generate_documentation_template(
+    acc: &mut Assists,
+    ctx: &AssistContext,
+) -> Option<()> {
+    let name = ctx.find_node_at_offset::<ast::Name>()?;
+    let ast_func = name.syntax().parent().and_then(ast::Fn::cast)?;
+    if ast_func.visibility().is_none() || ast_func.doc_comments().next().is_some() {
+        return None;
+    }
+    let func = ctx.sema.to_def(&ast_func)?;
+    if func.visibility(ctx.db()) != Visibility::Public {
+        return None;
+    }
+    let container = match func.as_assoc_item(ctx.db()).map(|it| it.container(ctx.db())) {
+        Some(AssocItemContainer::Impl(imp)) if imp.trait_(ctx.db()).is_some() => return None,
+        Some(AssocItemContainer::Impl(imp)) => Some(imp),
+        Some(AssocItemContainer::Trait(_)) => return None,
+        None => None,
+    };
+
+    let target = ast_func.syntax().text_range();
+    acc.add(
+        AssistId("generate_documentation_template", AssistKind::Generate),
+        "Generate a documentation template",
+        target,
+        |builder| {
+            let self_ty_name = container
+                .and_then(|imp| imp.self_ty(ctx.db()).as_adt())
+                .map(|adt| adt.name(ctx.db()).to_string());
+
+            let mut lines = vec![summary(&ast_func, self_ty_name.as_deref())];
+            let ret_ty = func.ret_type(ctx.db());
+            if let Some(TryEnum::Result) = TryEnum::from_ty(&ctx.sema, &ret_ty) {
+                lines.extend(section("Errors", "This function will return an error if ."));
+            }
+            if can_panic(&ast_func) {
+                lines.extend(section("Panics", "Panics if ."));
+            }
+            if ast_func.unsafe_token().is_some() {
+                lines.extend(section("Safety", "."));
+            }
+            if let Some(example) = example(ctx, &ast_func, func, self_ty_name.as_deref()) {
+                lines.extend(section("Examples", "```"));
+                lines.extend(example);
+                lines.push("```".to_string());
+            }
+
+            let indent = IndentLevel::from_node(ast_func.syntax());
+            let mut buf = String::new();
+            for line in lines {
+                if line.is_empty() {
+                    format_to!(buf, "///\n{}", indent);
+                } else {
+                    format_to!(buf, "/// {}\n{}", line, indent);
+                }
+            }
+            builder.insert(ast_func.syntax().text_range().start(), buf);
+        },
+    )
+}
+
+/// Produces a first sentence for well-known function shapes, and a placeholder otherwise.
+fn summary(ast_func: &ast::Fn, self_ty_name: Option<&str>) -> String {
+    let name = ast_func.name().map(|it| it.to_string()).unwrap_or_default();
+    match self_ty_name {
+        Some(self_ty) if name == "new" => format!("Creates a new [`{}`].", self_ty),
+        Some(self_ty) => match name.strip_prefix("set_") {
+            Some(field) => format!("Sets the {} of this [`{}`].", field.replace('_', " "), self_ty),
+            None => ".".to_string(),
+        },
+        None => ".".to_string(),
+    }
+}
+
+fn section(title: &str, body: &str) -> Vec<String> {
+    vec![String::new(), format!("# {}", title), String::new(), body.to_string()]
+}
+
+/// Looks for the usual suspects of panicking in the function body.
+fn can_panic(ast_func: &ast::Fn) -> bool {
+    const PANICKING_MACROS: &[&str] =
+        &["panic", "unreachable", "todo", "unimplemented", "assert", "assert_eq", "assert_ne"];
+    const PANICKING_METHODS: &[&str] = &["unwrap", "expect"];
+
+    let body = match ast_func.body() {
+        Some(it) => it,
+        None => return false,
+    };
+    body.syntax().descendants().any(|node| {
+        if let Some(call) = ast::MacroCall::cast(node.clone()) {
+            return call
+                .path()
+                .and_then(|path| path.segment())
+                .and_then(|segment| segment.name_ref())
+                .map_or(false, |name| PANICKING_MACROS.contains(&name.text().as_str()));
+        }
+        if let Some(call) = ast::MethodCallExpr::cast(node) {
+            return call
+                .name_ref()
+                .map_or(false, |name| PANICKING_METHODS.contains(&name.text().as_str()));
+        }
+        false
+    })
+}
+
+/// Builds the body of a doc-test calling the function with its parameter names as placeholders.
+fn example(
+    ctx: &AssistContext,
+    ast_func: &ast::Fn,
+    func: hir::Function,
+    self_ty_name: Option<&str>,
+) -> Option<Vec<String>> {
+    let db = ctx.db();
+    let krate = func.module(db).krate();
+    let fn_name = func.name(db);
+
+    // Doc-tests are compiled as a separate crate, so the item needs a path
+    // that's visible from outside, re-exports included.
+    let item = match func.as_assoc_item(db).map(|it| it.container(db)) {
+        Some(AssocItemContainer::Impl(imp)) => hir::ModuleDef::Adt(imp.self_ty(db).as_adt()?),
+        _ => hir::ModuleDef::Function(func),
+    };
+    let import_path = krate.import_path_of(db, item)?;
+    let use_path = format!("{}::{}", krate.display_name(db)?, import_path);
+
+    let args = ast_func
+        .param_list()
+        .into_iter()
+        .flat_map(|it| it.params())
+        .map(|param| match param.pat() {
+            Some(ast::Pat::IdentPat(pat)) => {
+                pat.name().map_or_else(|| "_".to_string(), |it| it.to_string())
+            }
+            _ => "_".to_string(),
+        })
+        .join(", ");
+
+    let mut lines = vec![format!("use {};", use_path), String::new()];
+    let call = match (self_ty_name, func.self_param(db)) {
+        (Some(self_ty), Some(self_param)) => {
+            let receiver = to_lower_snake_case(self_ty);
+            let mut_ = match self_param.access(db) {
+                hir::Access::Exclusive => "mut ",
+                hir::Access::Shared | hir::Access::Owned => "",
+            };
+            lines.push(format!("let {}{} = ;", mut_, receiver));
+            format!("{}.{}({})", receiver, fn_name, args)
+        }
+        (Some(self_ty), None) => format!("{}::{}({})", self_ty, fn_name, args),
+        (None, _) => format!("{}({})", fn_name, args),
+    };
+    let call =
+        if ast_func.unsafe_token().is_some() { format!("unsafe {{ {} }}", call) } else { call };
+    if func.ret_type(db).is_unit() {
+        lines.push(format!("{};", call));
+    } else {
+        lines.push(format!("assert_eq!({}, );", call));
+    }
+    Some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn not_applicable_on_private_fn() {
+        check_assist_not_applicable(
+            generate_documentation_template,
+            r#"
+fn priv$0ate() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_on_restricted_fn() {
+        check_assist_not_applicable(
+            generate_documentation_template,
+            r#"
+pub(crate) fn restr$0icted() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_on_documented_fn() {
+        check_assist_not_applicable(
+            generate_documentation_template,
+            r#"
+/// Does things.
+pub fn docu$0mented() {}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_in_trait_impl() {
+        check_assist_not_applicable(
+            generate_documentation_template,
+            r#"
+pub trait Trait { fn method(&self); }
+pub struct S;
+impl Trait for S {
+    fn meth$0od(&self) {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn unit_fn_in_module() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- /lib.rs crate:my_crate
+pub mod module {
+    pub fn no$0op() {}
+}
+"#,
+            r#"
+pub mod module {
+    /// .
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_crate::module::noop;
+    ///
+    /// noop();
+    /// ```
+    pub fn noop() {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fn_in_private_module_uses_reexport() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- /lib.rs crate:my_crate
+mod imp {
+    pub fn no$0op() {}
+}
+pub use imp::noop;
+"#,
+            r#"
+mod imp {
+    /// .
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_crate::noop;
+    ///
+    /// noop();
+    /// ```
+    pub fn noop() {}
+}
+pub use imp::noop;
+"#,
+        );
+    }
+
+    #[test]
+    fn no_example_for_unreachable_fn() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- /lib.rs crate:my_crate
+mod imp {
+    pub fn no$0op() {}
+}
+"#,
+            r#"
+mod imp {
+    /// .
+    pub fn noop() {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn errors_and_panics() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- minicore: result, option
+//- /lib.rs crate:my_crate
+pub fn par$0se(s: &str) -> Result<i32, ()> {
+    let first = s.chars().next().unwrap();
+    Ok(0)
+}
+"#,
+            r#"
+/// .
+///
+/// # Errors
+///
+/// This function will return an error if .
+///
+/// # Panics
+///
+/// Panics if .
+///
+/// # Examples
+///
+/// ```
+/// use my_crate::parse;
+///
+/// assert_eq!(parse(s), );
+/// ```
+pub fn parse(s: &str) -> Result<i32, ()> {
+    let first = s.chars().next().unwrap();
+    Ok(0)
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn unsafe_method() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- /lib.rs crate:my_crate
+pub struct MyVec;
+impl MyVec {
+    pub unsafe fn set_len$0(&mut self, len: usize) {}
+}
+"#,
+            r#"
+pub struct MyVec;
+impl MyVec {
+    /// Sets the len of this [`MyVec`].
+    ///
+    /// # Safety
+    ///
+    /// .
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_crate::MyVec;
+    ///
+    /// let mut my_vec = ;
+    /// unsafe { my_vec.set_len(len) };
+    /// ```
+    pub unsafe fn set_len(&mut self, len: usize) {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn constructor() {
+        check_assist(
+            generate_documentation_template,
+            r#"
+//- /lib.rs crate:my_crate
+pub struct S { x: i32 }
+impl S {
+    pub fn n$0ew(x: i32) -> S {
+        if x < 0 {
+            panic!("negative");
+        }
+        S { x }
+    }
+}
+"#,
+            r#"
+pub struct S { x: i32 }
+impl S {
+    /// Creates a new [`S`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if .
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use my_crate::S;
+    ///
+    /// assert_eq!(S::new(x), );
+    /// ```
+    pub fn new(x: i32) -> S {
+        if x < 0 {
+            panic!("negative");
+        }
+        S { x }
+    }
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,234 @@
+use hir::term_search::{self, TermSearchConfig};
+use syntax::ast::{self, AstNode};
+
+use crate::{AssistContext, AssistId, AssistKind, Assists, GroupLabel};
+
+// Assist: term_search
+//
+// Replaces a `todo!()` with an expression of the expected type, built from
+// the locals in scope.
+//
+// ```
+// //- minicore: todo
+// struct Config { port: u16 }
+// fn port(cfg: &Config) -> u16 {
+//     todo$0!()
+// }
+// ```
+// ->
+// ```
+// struct Config { port: u16 }
+// fn port(cfg: &Config) -> u16 {
+//     cfg.port
+// }
+// ```
+pub(crate) fn term_search(acc: &mut Assists, ctx: &AssistContext) -> Option<()> {
+    let macro_call = ctx.find_node_at_offset::<ast::MacroCall>()?;
+    if macro_call.path()?.segment()?.name_ref()?.text() != "todo"
+        || macro_call.excl_token().is_none()
+    {
+        return None;
+    }
+    let expr = ast::Expr::MacroCall(macro_call.clone());
+    // `todo!()` is `!`, the coercion gives the type it has to fill in.
+    let goal = ctx.sema.type_of_expr(&expr)?.adjusted();
+    if goal.is_never() {
+        return None;
+    }
+    let scope = ctx.sema.scope(macro_call.syntax());
+    let module = scope.module()?;
+
+    let terms = term_search::term_search(&scope, &goal, TermSearchConfig::default());
+    let group = GroupLabel("Fill `todo!()`".into());
+    let range = macro_call.syntax().text_range();
+    for term in terms {
+        let text = term.gen_source_code(ctx.db(), module);
+        acc.add_group(
+            &group,
+            AssistId("term_search", AssistKind::Generate),
+            format!("Replace `todo!()` with `{}`", text),
+            range,
+            |builder| builder.replace(range, text),
+        );
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::{check_assist, check_assist_by_label, check_assist_not_applicable};
+
+    use super::*;
+
+    #[test]
+    fn fills_with_method_call() {
+        check_assist_by_label(
+            term_search,
+            r#"
+//- minicore: todo
+mod path {
+    pub struct Path;
+    pub struct PathBuf;
+    impl Path {
+        pub fn join(&self, other: &str) -> PathBuf { PathBuf }
+    }
+}
+use path::{Path, PathBuf};
+
+struct Config { root: &'static Path }
+
+fn f(cfg: Config, name: &str) -> PathBuf {
+    $0todo!()
+}
+"#,
+            r#"
+mod path {
+    pub struct Path;
+    pub struct PathBuf;
+    impl Path {
+        pub fn join(&self, other: &str) -> PathBuf { PathBuf }
+    }
+}
+use path::{Path, PathBuf};
+
+struct Config { root: &'static Path }
+
+fn f(cfg: Config, name: &str) -> PathBuf {
+    cfg.root.join(name)
+}
+"#,
+            "Replace `todo!()` with `cfg.root.join(name)`",
+        );
+    }
+
+    #[test]
+    fn fills_with_method_on_generic_receiver() {
+        check_assist_by_label(
+            term_search,
+            r#"
+//- minicore: todo, option
+struct Map<K, V> { entries: Vec<(K, V)> }
+impl<K, V> Map<K, V> {
+    fn get(&self, key: &K) -> Option<&V> { None }
+}
+struct Vec<T>(T);
+
+fn f(map: Map<String, u32>, key: String) -> Option<&u32> {
+    $0todo!()
+}
+struct String;
+"#,
+            r#"
+struct Map<K, V> { entries: Vec<(K, V)> }
+impl<K, V> Map<K, V> {
+    fn get(&self, key: &K) -> Option<&V> { None }
+}
+struct Vec<T>(T);
+
+fn f(map: Map<String, u32>, key: String) -> Option<&u32> {
+    map.get(&key)
+}
+struct String;
+"#,
+            "Replace `todo!()` with `map.get(&key)`",
+        );
+    }
+
+    #[test]
+    fn fills_with_generic_function() {
+        check_assist_by_label(
+            term_search,
+            r#"
+//- minicore: todo
+mod wrapper {
+    pub struct Wrapper<T>(T);
+    pub fn wrap<T>(value: T) -> Wrapper<T> { Wrapper(value) }
+}
+use wrapper::{wrap, Wrapper};
+
+fn f(x: u32) -> Wrapper<u32> {
+    $0todo!()
+}
+"#,
+            r#"
+mod wrapper {
+    pub struct Wrapper<T>(T);
+    pub fn wrap<T>(value: T) -> Wrapper<T> { Wrapper(value) }
+}
+use wrapper::{wrap, Wrapper};
+
+fn f(x: u32) -> Wrapper<u32> {
+    wrap(x)
+}
+"#,
+            "Replace `todo!()` with `wrap(x)`",
+        );
+    }
+
+    #[test]
+    fn fills_let_initializer() {
+        check_assist_by_label(
+            term_search,
+            r#"
+//- minicore: todo
+struct Point { x: i32, y: i32 }
+fn f(x: i32, y: i32) {
+    let p: Point = todo!()$0;
+}
+"#,
+            r#"
+struct Point { x: i32, y: i32 }
+fn f(x: i32, y: i32) {
+    let p: Point = Point { x, y };
+}
+"#,
+            "Replace `todo!()` with `Point { x, y }`",
+        );
+    }
+
+    #[test]
+    fn fills_reference() {
+        check_assist(
+            term_search,
+            r#"
+//- minicore: todo
+struct S;
+fn f(s: S) {
+    let r: &S = todo$0!();
+}
+"#,
+            r#"
+struct S;
+fn f(s: S) {
+    let r: &S = &s;
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_without_expected_type() {
+        check_assist_not_applicable(
+            term_search,
+            r#"
+//- minicore: todo
+fn f(x: i32) {
+    todo$0!();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn not_applicable_to_other_macros() {
+        check_assist_not_applicable(
+            term_search,
+            r#"
+fn f(x: i32) -> i32 {
+    unimplemented$0!()
+}
+"#,
+        );
+    }
+}
@@ -249,6 +249,7 @@ pub fn test_some_range(a: int) -> bool {
         Convert integer base
         Extract into variable
         Extract into function
+        Extract into closure
         Replace if let with match
     "#]]
     .assert_eq(&expected);
@@ -278,6 +279,7 @@ pub fn test_some_range(a: int) -> bool {
             Convert integer base
             Extract into variable
             Extract into function
+            Extract into closure
             Replace if let with match
         "#]]
         .assert_eq(&expected);
@@ -292,6 +294,7 @@ pub fn test_some_range(a: int) -> bool {
         expect![[r#"
             Extract into variable
             Extract into function
+            Extract into closure
         "#]]
         .assert_eq(&expected);
     }
@@ -325,7 +328,7 @@ pub fn test_some_range(a: int) -> bool {
 
     {
         let assists = assists(&db, &cfg, AssistResolveStrategy::None, frange);
-        assert_eq!(2, assists.len());
+        assert_eq!(3, assists.len());
         let mut assists = assists.into_iter();
 
         let extract_into_variable_assist = assists.next().unwrap();
@@ -369,7 +372,7 @@ pub fn test_some_range(a: int) -> bool {
             }),
             frange,
         );
-        assert_eq!(2, assists.len());
+        assert_eq!(3, assists.len());
         let mut assists = assists.into_iter();
 
         let extract_into_variable_assist = assists.next().unwrap();
@@ -413,7 +416,7 @@ pub fn test_some_range(a: int) -> bool {
             }),
             frange,
         );
-        assert_eq!(2, assists.len());
+        assert_eq!(3, assists.len());
         let mut assists = assists.into_iter();
 
         let extract_into_variable_assist = assists.next().unwrap();
@@ -470,7 +473,7 @@ pub fn test_some_range(a: int) -> bool {
 
     {
         let assists = assists(&db, &cfg, AssistResolveStrategy::All, frange);
-        assert_eq!(2, assists.len());
+        assert_eq!(3, assists.len());
         let mut assists = assists.into_iter();
 
         let extract_into_variable_assist = assists.next().unwrap();
@@ -364,6 +364,89 @@ fn main() {
     )
 }
 
+#[test]
+fn doctest_convert_let_else_to_match() {
+    check_doc_test(
+        "convert_let_else_to_match",
+        r#####"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let Some(mut x) = opt else$0 { return };
+}
+"#####,
+        r#####"
+fn main(opt: Option<i32>) {
+    let mut x = match opt {
+        Some(x) => x,
+        _ => { return }
+    };
+}
+"#####,
+    )
+}
+
+#[test]
+fn doctest_convert_match_to_let_else() {
+    check_doc_test(
+        "convert_match_to_let_else",
+        r#####"
+//- minicore: option
+fn main(opt: Option<i32>) {
+    let$0 x = match opt {
+        Some(it) => it,
+        None => return,
+    };
+}
+"#####,
+        r#####"
+fn main(opt: Option<i32>) {
+    let Some(x) = opt else { return };
+}
+"#####,
+    )
+}
+
+#[test]
+fn doctest_convert_named_struct_to_tuple_struct() {
+    check_doc_test(
+        "convert_named_struct_to_tuple_struct",
+        r#####"
+struct Point$0 { x: f32, y: f32 }
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Point { x, y }
+    }
+
+    pub fn x(&self) -> f32 {
+        self.x
+    }
+
+    pub fn y(&self) -> f32 {
+        self.y
+    }
+}
+"#####,
+        r#####"
+struct Point(f32, f32);
+
+impl Point {
+    pub fn new(x: f32, y: f32) -> Self {
+        Point(x, y)
+    }
+
+    pub fn x(&self) -> f32 {
+        self.0
+    }
+
+    pub fn y(&self) -> f32 {
+        self.1
+    }
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_convert_to_guarded_return() {
     check_doc_test(
@@ -499,6 +582,28 @@ fn qux(bar: Bar, baz: Baz) {}
     )
 }
 
+#[test]
+fn doctest_extract_closure() {
+    check_doc_test(
+        "extract_closure",
+        r#####"
+fn main() {
+    let n = 1;
+    let m = $0n * 2$0 + 1;
+}
+"#####,
+        r#####"
+fn main() {
+    let n = 1;
+    let $0fun_name = || -> i32 {
+        n * 2
+    };
+    let m = fun_name() + 1;
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_extract_function() {
     check_doc_test(
@@ -792,6 +897,51 @@ impl Person {
     )
 }
 
+#[test]
+fn doctest_generate_delegate_trait() {
+    check_doc_test(
+        "generate_delegate_trait",
+        r#####"
+trait Len {
+    const EMPTY: bool;
+    fn len(&self) -> usize;
+}
+struct Bytes;
+impl Len for Bytes {
+    const EMPTY: bool = false;
+    fn len(&self) -> usize { 0 }
+}
+
+struct Wrapper {
+    inn$0er: Bytes,
+}
+"#####,
+        r#####"
+trait Len {
+    const EMPTY: bool;
+    fn len(&self) -> usize;
+}
+struct Bytes;
+impl Len for Bytes {
+    const EMPTY: bool = false;
+    fn len(&self) -> usize { 0 }
+}
+
+struct Wrapper {
+    inner: Bytes,
+}
+
+impl Len for Wrapper {
+    const EMPTY: bool = <Bytes as Len>::EMPTY;
+
+    fn len(&self) -> usize {
+        <Bytes as Len>::len(&self.inner)
+    }
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_generate_deref() {
     check_doc_test(
@@ -839,6 +989,28 @@ struct Point {
     )
 }
 
+#[test]
+fn doctest_generate_documentation_template() {
+    check_doc_test(
+        "generate_documentation_template",
+        r#####"
+pub fn add$0(a: i32, b: i32) -> i32 { a + b }
+"#####,
+        r#####"
+/// .
+///
+/// # Examples
+///
+/// ```
+/// use test::add;
+///
+/// assert_eq!(add(a, b), );
+/// ```
+pub fn add(a: i32, b: i32) -> i32 { a + b }
+"#####,
+    )
+}
+
 #[test]
 fn doctest_generate_enum_as_method() {
     check_doc_test(
@@ -1980,6 +2152,26 @@ use std::{collections::HashMap};
     )
 }
 
+#[test]
+fn doctest_term_search() {
+    check_doc_test(
+        "term_search",
+        r#####"
+//- minicore: todo
+struct Config { port: u16 }
+fn port(cfg: &Config) -> u16 {
+    todo$0!()
+}
+"#####,
+        r#####"
+struct Config { port: u16 }
+fn port(cfg: &Config) -> u16 {
+    cfg.port
+}
+"#####,
+    )
+}
+
 #[test]
 fn doctest_toggle_ignore() {
     check_doc_test(
@@ -12,6 +12,7 @@ pub(crate) mod postfix;
 pub(crate) mod qualified_path;
 pub(crate) mod record;
 pub(crate) mod snippet;
+pub(crate) mod term_search;
 pub(crate) mod trait_impl;
 pub(crate) mod unqualified_path;
 
@@ -0,0 +1,168 @@
+//! Completes whole expressions of the expected type, like `cfg.root.join(name)`
+//! for `let p: PathBuf = $0`.
+use hir::term_search::{term_search, TermSearchConfig};
+
+use crate::{
+    item::{CompletionKind, CompletionRelevance, CompletionRelevanceTypeMatch},
+    CompletionContext, CompletionItem, CompletionItemKind, Completions,
+};
+
+pub(crate) fn complete_term_search(acc: &mut Completions, ctx: &CompletionContext) -> Option<()> {
+    if !ctx.config.enable_term_search || !ctx.expects_expression() || !ctx.is_trivial_path() {
+        return None;
+    }
+    let expected_type = ctx.expected_type.as_ref()?;
+    let module = ctx.scope.module()?;
+
+    let terms = term_search(&ctx.scope, expected_type, TermSearchConfig::default());
+    // Plain names are completed by `unqualified_path` already.
+    for term in terms.into_iter().filter(|it| !it.is_name(ctx.db)) {
+        let text = term.gen_source_code(ctx.db, module);
+        let mut item = CompletionItem::new(CompletionKind::Magic, ctx.source_range(), &text);
+        item.kind(CompletionItemKind::Snippet).insert_text(text).set_relevance(
+            CompletionRelevance {
+                type_match: Some(CompletionRelevanceTypeMatch::Exact),
+                ..CompletionRelevance::default()
+            },
+        );
+        item.add_to(acc);
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use expect_test::{expect, Expect};
+
+    use crate::{
+        tests::{check_edit_with_config, filtered_completion_list_with_config, TEST_CONFIG},
+        CompletionConfig, CompletionKind,
+    };
+
+    fn config() -> CompletionConfig {
+        CompletionConfig { enable_term_search: true, ..TEST_CONFIG }
+    }
+
+    fn check(ra_fixture: &str, expect: Expect) {
+        let actual =
+            filtered_completion_list_with_config(config(), ra_fixture, CompletionKind::Magic);
+        expect.assert_eq(&actual)
+    }
+
+    #[test]
+    fn completes_method_chain_with_args() {
+        check(
+            r#"
+mod path {
+    pub struct Path;
+    pub struct PathBuf;
+    impl Path {
+        pub fn join(&self, other: &str) -> PathBuf { PathBuf }
+    }
+}
+use path::{Path, PathBuf};
+
+struct Config { root: &'static Path }
+
+fn f(cfg: Config, name: &str) {
+    let p: PathBuf = $0;
+}
+"#,
+            expect![[r#"
+                sn cfg.root.join(name)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_constructors() {
+        check(
+            r#"
+struct Point { x: i32, y: i32 }
+enum Shape { Dot(Point), Empty }
+
+fn f(p: Point) {
+    let s: Shape = $0;
+}
+"#,
+            expect![[r#"
+                sn Shape::Dot(p)
+            "#]],
+        );
+        check(
+            r#"
+struct Point { x: i32, y: i32 }
+
+fn f(x: i32, y: i32) {
+    let p: Point = $0;
+}
+"#,
+            expect![[r#"
+                sn Point { x, y }
+            "#]],
+        );
+    }
+
+    #[test]
+    fn completes_trait_functions() {
+        check(
+            r#"
+//- minicore: default, from
+struct Meters(u32);
+impl Default for Meters {
+    fn default() -> Self { Meters(0) }
+}
+impl From<u32> for Meters {
+    fn from(it: u32) -> Self { Meters(it) }
+}
+
+fn f(n: u32) {
+    let m: Meters = $0;
+}
+"#,
+            expect![[r#"
+                sn Meters(n)
+                sn Meters::default()
+                sn Meters::from(n)
+            "#]],
+        );
+    }
+
+    #[test]
+    fn disabled_by_config() {
+        let actual = filtered_completion_list_with_config(
+            TEST_CONFIG,
+            r#"
+struct S { a: u32 }
+fn f(s: S) {
+    let x: u32 = $0;
+}
+"#,
+            CompletionKind::Magic,
+        );
+        assert!(!actual.contains("s.a"), "{}", actual);
+    }
+
+    #[test]
+    fn completes_field_edit() {
+        check_edit_with_config(
+            config(),
+            "s.inner.a",
+            r#"
+struct Inner { a: u32 }
+struct S { inner: Inner }
+fn f(s: S) {
+    let x: u32 = $0;
+}
+"#,
+            r#"
+struct Inner { a: u32 }
+struct S { inner: Inner }
+fn f(s: S) {
+    let x: u32 = s.inner.a;
+}
+"#,
+        );
+    }
+}
@@ -168,6 +168,7 @@ pub fn completions(
     completions::flyimport::import_on_the_fly(&mut acc, &ctx);
     completions::lifetime::complete_lifetime(&mut acc, &ctx);
     completions::lifetime::complete_label(&mut acc, &ctx);
+    completions::term_search::complete_term_search(&mut acc, &ctx);
 
     Some(acc)
 }
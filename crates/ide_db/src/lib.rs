@@ -19,6 +19,7 @@ pub mod path_transform;
 
 pub mod search;
 pub mod rename;
+pub mod unused_items;
 
 use std::{fmt, mem::ManuallyDrop, sync::Arc};
 
@@ -110,6 +111,7 @@ impl RootDatabase {
         db.set_crate_graph_with_durability(Default::default(), Durability::HIGH);
         db.set_local_roots_with_durability(Default::default(), Durability::HIGH);
         db.set_library_roots_with_durability(Default::default(), Durability::HIGH);
+        db.set_cached_library_symbols_with_durability(Default::default(), Durability::HIGH);
         db.set_enable_proc_attr_macros(Default::default());
         db.update_lru_capacity(lru_capacity);
         db
@@ -19,18 +19,23 @@
 //! for each library (which is assumed to never change) and an FST for each Rust
 //! file in the current workspace, and run a query against the union of all
 //! those FSTs.
+//!
+//! Library indices are additionally persisted to disk, see the [`cache`] module.
+
+mod cache;
 
 use std::{
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
     mem,
+    path::Path,
     sync::Arc,
 };
 
 use base_db::{
     salsa::{self, ParallelDatabase},
-    CrateId, FileId, SourceDatabaseExt, SourceRootId,
+    CrateId, FileId, SourceDatabaseExt, SourceRoot, SourceRootId,
 };
 use fst::{self, Streamer};
 use hir::db::DefDatabase;
@@ -103,20 +108,31 @@ pub trait SymbolsDatabase: hir::db::HirDatabase + SourceDatabaseExt {
     /// Files in libraries are assumed to never change.
     #[salsa::input]
     fn library_roots(&self) -> Arc<FxHashSet<SourceRootId>>;
+    /// Symbols of library roots read from the on-disk cache, see [`load_library_symbols`].
+    #[salsa::input]
+    fn cached_library_symbols(&self) -> Arc<CachedLibrarySymbols>;
+}
+
+/// Symbols of library roots, with the cache key of the root contents they were built from.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CachedLibrarySymbols {
+    roots: FxHashMap<SourceRootId, (u64, Vec<FileSymbol>)>,
 }
 
 fn library_symbols(db: &dyn SymbolsDatabase) -> Arc<FxHashMap<SourceRootId, SymbolIndex>> {
     let _p = profile::span("library_symbols");
 
     let roots = db.library_roots();
+    let cached = db.cached_library_symbols();
     let res = roots
         .iter()
         .map(|&root_id| {
-            let root = db.source_root(root_id);
-            let files = root
-                .iter()
-                .map(|it| (it, SourceDatabaseExt::file_text(db, it)))
-                .collect::<Vec<_>>();
+            let (root, files) = library_files(db, root_id);
+            if let Some((key, symbols)) = cached.roots.get(&root_id) {
+                if *key == cache::CacheEntry::new(&root, &files).key() {
+                    return (root_id, SymbolIndex::new(symbols.clone()));
+                }
+            }
             let symbol_index = SymbolIndex::for_files(
                 files.into_par_iter().map(|(file, text)| (file, SourceFile::parse(&text))),
             );
@@ -126,6 +142,69 @@ fn library_symbols(db: &dyn SymbolsDatabase) -> Arc<FxHashMap<SourceRootId, Symb
     Arc::new(res)
 }
 
+fn library_files(
+    db: &dyn SymbolsDatabase,
+    root_id: SourceRootId,
+) -> (Arc<SourceRoot>, Vec<(FileId, Arc<String>)>) {
+    let root = db.source_root(root_id);
+    let files = root.iter().map(|it| (it, SourceDatabaseExt::file_text(db, it))).collect();
+    (root, files)
+}
+
+/// Reads the cached symbols of library roots from `dir`, for roots that don't already have
+/// up-to-date symbols in [`SymbolsDatabase::cached_library_symbols`]. Returns `None` if nothing
+/// changed.
+pub fn load_library_symbols(
+    db: &dyn SymbolsDatabase,
+    dir: &Path,
+    version: &str,
+) -> Option<CachedLibrarySymbols> {
+    let _p = profile::span("load_library_symbols");
+    let version_dir = cache::version_dir(dir, version);
+    let cached = db.cached_library_symbols();
+
+    let mut changed = false;
+    let mut roots = FxHashMap::default();
+    for &root_id in db.library_roots().iter() {
+        let (root, files) = library_files(db, root_id);
+        let entry = cache::CacheEntry::new(&root, &files);
+        match cached.roots.get(&root_id) {
+            Some((key, symbols)) if *key == entry.key() => {
+                roots.insert(root_id, (*key, symbols.clone()));
+            }
+            _ => {
+                if let Some(symbols) = entry.load(&version_dir) {
+                    roots.insert(root_id, (entry.key(), symbols));
+                    changed = true;
+                }
+            }
+        }
+    }
+    changed |= roots.len() != cached.roots.len();
+    changed.then(|| CachedLibrarySymbols { roots })
+}
+
+/// Writes the symbols of library roots that aren't cached yet to `dir`, and evicts stale entries.
+pub fn store_library_symbols(db: &dyn SymbolsDatabase, dir: &Path, version: &str) {
+    let _p = profile::span("store_library_symbols");
+    let version_dir = cache::version_dir(dir, version);
+    let symbols = db.library_symbols();
+
+    let mut used = FxHashSet::default();
+    for &root_id in db.library_roots().iter() {
+        let (root, files) = library_files(db, root_id);
+        let entry = cache::CacheEntry::new(&root, &files);
+        let path = entry.path(&version_dir);
+        if !path.exists() {
+            if let Some(index) = symbols.get(&root_id) {
+                entry.store(&version_dir, &index.symbols);
+            }
+        }
+        used.insert(path);
+    }
+    cache::evict(&version_dir, &used);
+}
+
 fn file_symbols(db: &dyn SymbolsDatabase, file_id: FileId) -> Arc<SymbolIndex> {
     db.unwind_if_cancelled();
     let parse = db.parse(file_id);
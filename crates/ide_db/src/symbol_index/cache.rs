@@ -0,0 +1,384 @@
+//! Persists the symbol indices of library source roots, so that they don't have to be rebuilt on
+//! every restart.
+//!
+//! Libraries are assumed to never change, so an index stays valid as long as the paths and texts
+//! of the files in its root stay the same; their hash is the name of the cache entry. Each
+//! rust-analyzer version gets its own subdirectory of the cache directory, directories of other
+//! versions are removed when entries are written. As `FileId`s are not stable across restarts,
+//! symbols refer to their files by path.
+//!
+//! Reading and writing entries happens outside of salsa queries: the main loop loads entries
+//! into [`SymbolsDatabase::cached_library_symbols`] and writes them out once the library symbols
+//! have been computed.
+//!
+//! [`SymbolsDatabase::cached_library_symbols`]: super::SymbolsDatabase::cached_library_symbols
+
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use base_db::{FileId, SourceRoot};
+use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
+use stdx::format_to;
+use syntax::{SyntaxKind, SyntaxNodePtr, TextRange, TextSize};
+
+use super::{FileSymbol, FileSymbolKind};
+
+const HEADER: &str = "rust-analyzer library symbols v1";
+const VERSION_DIR_PREFIX: &str = "symbols-";
+/// Entries not used by the current session are kept this long, other workspaces may share the
+/// cache directory.
+const MAX_UNUSED_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The directory holding the entries of the given rust-analyzer version.
+pub(super) fn version_dir(dir: &Path, version: &str) -> PathBuf {
+    let version: String = version
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    dir.join(format!("{}{}", VERSION_DIR_PREFIX, version))
+}
+
+/// Removes the directories of other versions, and the entries of `version_dir` that are neither
+/// in `used` nor were written recently.
+pub(super) fn evict(version_dir: &Path, used: &FxHashSet<PathBuf>) {
+    if let (Some(parent), Some(current)) = (version_dir.parent(), version_dir.file_name()) {
+        for entry in read_dir(parent) {
+            let name = entry.file_name();
+            let is_other_version = name != current
+                && name.to_str().map_or(false, |it| it.starts_with(VERSION_DIR_PREFIX))
+                && entry.file_type().map_or(false, |it| it.is_dir());
+            if is_other_version {
+                remove(&entry.path(), fs::remove_dir_all(entry.path()));
+            }
+        }
+    }
+
+    let now = SystemTime::now();
+    for entry in read_dir(version_dir) {
+        let path = entry.path();
+        if used.contains(&path) {
+            continue;
+        }
+        let age = entry.metadata().and_then(|it| it.modified()).map(|it| now.duration_since(it));
+        if let Ok(Ok(age)) = age {
+            if age > MAX_UNUSED_AGE {
+                remove(&path, fs::remove_file(&path));
+            }
+        }
+    }
+
+    fn read_dir(dir: &Path) -> impl Iterator<Item = fs::DirEntry> {
+        fs::read_dir(dir).into_iter().flatten().filter_map(Result::ok)
+    }
+
+    fn remove(path: &Path, res: io::Result<()>) {
+        if let Err(err) = res {
+            tracing::warn!("failed to evict symbol cache entry {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// The cache entry of one library source root.
+pub(super) struct CacheEntry {
+    /// Hash of the paths and texts of the files of the root.
+    key: u64,
+    /// File paths of the root, sorted, with the matching ids of this session.
+    files: Vec<(String, FileId)>,
+}
+
+impl CacheEntry {
+    pub(super) fn new(root: &SourceRoot, texts: &[(FileId, Arc<String>)]) -> CacheEntry {
+        let mut files = texts
+            .iter()
+            .map(|(file_id, text)| {
+                let path = root.path_for_file(file_id).map(|it| it.to_string()).unwrap_or_default();
+                (path, *file_id, text)
+            })
+            .collect::<Vec<_>>();
+        files.sort_by(|(lhs, ..), (rhs, ..)| lhs.cmp(rhs));
+
+        let mut hasher = FxHasher::default();
+        for (path, _, text) in &files {
+            path.hash(&mut hasher);
+            text.hash(&mut hasher);
+        }
+        let files = files.into_iter().map(|(path, file_id, _)| (path, file_id)).collect();
+        CacheEntry { key: hasher.finish(), files }
+    }
+
+    pub(super) fn key(&self) -> u64 {
+        self.key
+    }
+
+    pub(super) fn path(&self, version_dir: &Path) -> PathBuf {
+        version_dir.join(format!("{:016x}.symbols", self.key))
+    }
+
+    /// Returns `None` if there is no usable entry.
+    pub(super) fn load(&self, version_dir: &Path) -> Option<Vec<FileSymbol>> {
+        let path = self.path(version_dir);
+        let file = fs::File::open(&path).ok()?;
+        match self.read(io::BufReader::new(file)) {
+            Ok(it) => Some(it),
+            Err(err) => {
+                tracing::warn!("ignoring symbol cache entry {}: {}", path.display(), err);
+                None
+            }
+        }
+    }
+
+    pub(super) fn store(&self, version_dir: &Path, symbols: &[FileSymbol]) {
+        let path = self.path(version_dir);
+        if let Err(err) = self.write(&path, symbols) {
+            tracing::warn!("failed to write symbol cache entry {}: {}", path.display(), err);
+        }
+    }
+    fn read(&self, reader: impl BufRead) -> io::Result<Vec<FileSymbol>> {
+        let invalid = |msg: &str| io::Error::new(io::ErrorKind::InvalidData, msg.to_string());
+
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(invalid("unknown format"));
+        }
+        let file_ids = self.files.iter().map(|(path, file_id)| (path.as_str(), *file_id));
+        let file_ids = file_ids.collect::<FxHashMap<_, _>>();
+
+        let mut files = Vec::new();
+        let mut symbols = Vec::new();
+        for line in lines {
+            let line = line?;
+            if let Some(path) = line.strip_prefix("F\t") {
+                let file_id = *file_ids.get(path).ok_or_else(|| invalid("unknown file"))?;
+                files.push(file_id);
+                continue;
+            }
+            let symbol = line
+                .strip_prefix("S\t")
+                .and_then(|it| parse_symbol(it, &files))
+                .ok_or_else(|| invalid("malformed symbol"))?;
+            symbols.push(symbol);
+        }
+        if files.len() != self.files.len() {
+            return Err(invalid("file list doesn't match"));
+        }
+        Ok(symbols)
+    }
+
+    fn write(&self, path: &Path, symbols: &[FileSymbol]) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Several instances may share the cache, so only ever expose complete entries.
+        let tmp_path = path.with_extension(format!("tmp{}", std::process::id()));
+        let mut out = BufWriter::new(fs::File::create(&tmp_path)?);
+
+        writeln!(out, "{}", HEADER)?;
+        let mut file_indices = FxHashMap::default();
+        for (idx, (path, file_id)) in self.files.iter().enumerate() {
+            writeln!(out, "F\t{}", path)?;
+            file_indices.insert(*file_id, idx);
+        }
+        for symbol in symbols {
+            let file_idx = match file_indices.get(&symbol.file_id) {
+                Some(it) => it,
+                None => continue,
+            };
+            writeln!(out, "S\t{}\t{}", file_idx, format_symbol(symbol))?;
+        }
+        out.into_inner()?.sync_all()?;
+        fs::rename(&tmp_path, path)
+    }
+}
+
+const KINDS: &[(&str, SyntaxKind, FileSymbolKind)] = &[
+    ("fn", SyntaxKind::FN, FileSymbolKind::Function),
+    ("struct", SyntaxKind::STRUCT, FileSymbolKind::Struct),
+    ("enum", SyntaxKind::ENUM, FileSymbolKind::Enum),
+    ("trait", SyntaxKind::TRAIT, FileSymbolKind::Trait),
+    ("mod", SyntaxKind::MODULE, FileSymbolKind::Module),
+    ("type", SyntaxKind::TYPE_ALIAS, FileSymbolKind::TypeAlias),
+    ("const", SyntaxKind::CONST, FileSymbolKind::Const),
+    ("static", SyntaxKind::STATIC, FileSymbolKind::Static),
+    ("macro_rules", SyntaxKind::MACRO_RULES, FileSymbolKind::Macro),
+    ("macro", SyntaxKind::MACRO_DEF, FileSymbolKind::Macro),
+    ("union", SyntaxKind::UNION, FileSymbolKind::Union),
+];
+
+/// `<kind> <start> <end> <name start> <name end> <name> <container>`, tab separated, with `-`
+/// for missing values.
+fn format_symbol(symbol: &FileSymbol) -> String {
+    let tag = KINDS.iter().find(|(_, kind, _)| *kind == symbol.ptr.kind()).map_or("", |it| it.0);
+    let mut buf = String::new();
+    format_to!(
+        buf,
+        "{}\t{}\t{}",
+        tag,
+        u32::from(symbol.range.start()),
+        u32::from(symbol.range.end())
+    );
+    match symbol.name_range {
+        Some(range) => {
+            format_to!(buf, "\t{}\t{}", u32::from(range.start()), u32::from(range.end()))
+        }
+        None => buf.push_str("\t-\t-"),
+    }
+    format_to!(buf, "\t{}\t{}", symbol.name, symbol.container_name.as_deref().unwrap_or("-"));
+    buf
+}
+
+fn parse_symbol(line: &str, files: &[FileId]) -> Option<FileSymbol> {
+    let mut fields = line.split('\t');
+    let mut next = || fields.next();
+    let file_id = *files.get(next()?.parse::<usize>().ok()?)?;
+    let tag = next()?;
+    let (_, syntax_kind, kind) = KINDS.iter().find(|(it, ..)| *it == tag)?;
+    let range = parse_range(next()?, next()?)?;
+    let name_range = match (next()?, next()?) {
+        ("-", "-") => None,
+        (start, end) => Some(parse_range(start, end)?),
+    };
+    let name = next()?.into();
+    let container_name = match next()? {
+        "-" => None,
+        it => Some(it.into()),
+    };
+    Some(FileSymbol {
+        file_id,
+        name,
+        kind: *kind,
+        range,
+        ptr: SyntaxNodePtr::from_parts(*syntax_kind, range),
+        name_range,
+        container_name,
+    })
+}
+
+fn parse_range(start: &str, end: &str) -> Option<TextRange> {
+    let start = TextSize::from(start.parse::<u32>().ok()?);
+    let end = TextSize::from(end.parse::<u32>().ok()?);
+    (start <= end).then(|| TextRange::new(start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use base_db::{FileSet, VfsPath};
+    use syntax::SourceFile;
+
+    use super::*;
+    use crate::symbol_index::source_file_to_file_symbols;
+
+    fn library(files: &[(&str, &str)]) -> (SourceRoot, Vec<(FileId, Arc<String>)>) {
+        let mut file_set = FileSet::default();
+        let mut texts = Vec::new();
+        for (idx, (path, text)) in files.iter().enumerate() {
+            let file_id = FileId(idx as u32);
+            file_set.insert(file_id, VfsPath::new_virtual_path(path.to_string()));
+            texts.push((file_id, Arc::new(text.to_string())));
+        }
+        (SourceRoot::new_library(file_set), texts)
+    }
+
+    fn symbols(texts: &[(FileId, Arc<String>)]) -> Vec<FileSymbol> {
+        texts
+            .iter()
+            .flat_map(|(file_id, text)| {
+                source_file_to_file_symbols(&SourceFile::parse(text).tree(), *file_id)
+            })
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("ra-symbol-cache-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn roundtrip() {
+        let dir = temp_dir("roundtrip");
+        let (root, texts) = library(&[
+            (
+                "/lib.rs",
+                "mod inner { pub struct S; impl S { fn f() {} } }\nmacro_rules! m { () => {} }",
+            ),
+            ("/other.rs", "pub trait T { const C: u8; type A; }\npub macro m2() {}"),
+        ]);
+        let symbols = symbols(&texts);
+
+        let entry = CacheEntry::new(&root, &texts);
+        assert!(entry.load(&dir).is_none());
+        entry.store(&dir, &symbols);
+
+        // Another session can hand out other ids for the same files.
+        let (root, texts) = library(&[
+            ("/other.rs", "pub trait T { const C: u8; type A; }\npub macro m2() {}"),
+            (
+                "/lib.rs",
+                "mod inner { pub struct S; impl S { fn f() {} } }\nmacro_rules! m { () => {} }",
+            ),
+        ]);
+        let loaded = CacheEntry::new(&root, &texts).load(&dir).unwrap();
+        let expected = symbols
+            .into_iter()
+            .map(|mut it| {
+                it.file_id = if it.file_id == FileId(0) { FileId(1) } else { FileId(0) };
+                it
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(loaded, expected);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn changed_text_misses() {
+        let dir = temp_dir("changed");
+        let (root, texts) = library(&[("/lib.rs", "fn f() {}")]);
+        CacheEntry::new(&root, &texts).store(&dir, &symbols(&texts));
+
+        let (root, texts) = library(&[("/lib.rs", "fn g() {}")]);
+        assert!(CacheEntry::new(&root, &texts).load(&dir).is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn corrupted_entry_is_ignored() {
+        let dir = temp_dir("corrupted");
+        let (root, texts) = library(&[("/lib.rs", "fn f() {}")]);
+        let entry = CacheEntry::new(&root, &texts);
+        entry.store(&dir, &symbols(&texts));
+        fs::write(entry.path(&dir), format!("{}\nF\t/lib.rs\nS\t0\tfn\t3", HEADER)).unwrap();
+        assert!(entry.load(&dir).is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evicts_other_versions() {
+        let dir = temp_dir("evict");
+        let old = version_dir(&dir, "0.0.0 (old)");
+        let current = version_dir(&dir, "0.0.1");
+        let (root, texts) = library(&[("/lib.rs", "fn f() {}")]);
+        let entry = CacheEntry::new(&root, &texts);
+        entry.store(&old, &symbols(&texts));
+        entry.store(&current, &symbols(&texts));
+        let unrelated = dir.join("unrelated");
+        fs::create_dir_all(&unrelated).unwrap();
+
+        let used = std::iter::once(entry.path(&current)).collect();
+        evict(&current, &used);
+        assert!(!old.exists());
+        assert!(entry.path(&current).exists());
+        assert!(unrelated.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
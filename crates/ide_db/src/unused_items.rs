@@ -0,0 +1,171 @@
+//! Finds `pub` items that nothing in the workspace refers to.
+//!
+//! rustc's dead code lint can't tell whether a public item of a library is
+//! used, as any dependent crate might use it. We know all the crates of the
+//! workspace though, so we can just search them for usages with
+//! [`crate::search`].
+//!
+//! Some items are used without ever being named in the source: the items of
+//! trait impls are used through the trait, and `#[no_mangle]` functions are
+//! called from other languages, so these are never reported.
+
+use base_db::{FileId, FileRange};
+use hir::{
+    AsAssocItem, AssocItem, AssocItemContainer, HasVisibility, Impl, Module, ModuleDef, Semantics,
+    Visibility,
+};
+use rustc_hash::FxHashMap;
+use syntax::{ast, AstNode};
+
+use crate::{defs::Definition, search::FileReference, RootDatabase};
+
+/// A public item that isn't used anywhere in the workspace.
+#[derive(Debug, Clone)]
+pub struct UnusedItem {
+    pub def: ModuleDef,
+    /// What kind of item this is, as in "function `foo` is never used".
+    pub kind: &'static str,
+    /// The range of the item's name.
+    pub range: FileRange,
+}
+
+/// Returns the public items defined in `file_id` that aren't used anywhere.
+pub fn unused_public_items(sema: &Semantics<RootDatabase>, file_id: FileId) -> Vec<UnusedItem> {
+    let _p = profile::span("unused_public_items");
+    let db = sema.db;
+    // A file can belong to several crates, its items are only unused if they
+    // are unused in all of them.
+    let mut items = FxHashMap::default();
+    for module in sema.to_module_defs(file_id) {
+        let mut defs = Vec::new();
+        collect_public_items(db, module, file_id, &mut defs);
+        for def in defs.into_iter().filter(|&def| is_candidate(db, def)) {
+            let range = match Definition::ModuleDef(def).range_for_rename(sema) {
+                Some(range) if range.file_id == file_id => range,
+                // Defined by a macro.
+                _ => continue,
+            };
+            let (_, used) = items
+                .entry(range.range)
+                .or_insert_with(|| (UnusedItem { def, kind: kind(db, def), range }, false));
+            *used = *used || is_used(sema, def, range);
+        }
+    }
+    let mut res: Vec<_> =
+        items.into_iter().filter(|(_, (_, used))| !used).map(|(_, (item, _))| item).collect();
+    res.sort_by_key(|it| it.range.range.start());
+    res
+}
+
+fn collect_public_items(
+    db: &RootDatabase,
+    module: Module,
+    file_id: FileId,
+    acc: &mut Vec<ModuleDef>,
+) {
+    for def in module.declarations(db) {
+        match def {
+            // Inline modules, the others are visited for their own file.
+            ModuleDef::Module(module) => {
+                if module.definition_source(db).file_id.original_file(db) == file_id {
+                    collect_public_items(db, module, file_id, acc);
+                }
+                continue;
+            }
+            ModuleDef::Trait(trait_) => {
+                acc.extend(trait_.items(db).into_iter().filter_map(|it| match it {
+                    AssocItem::Function(it) => Some(ModuleDef::Function(it)),
+                    AssocItem::Const(_) | AssocItem::TypeAlias(_) => None,
+                }));
+            }
+            ModuleDef::Variant(_) | ModuleDef::BuiltinType(_) => continue,
+            ModuleDef::Function(_)
+            | ModuleDef::Adt(_)
+            | ModuleDef::Const(_)
+            | ModuleDef::Static(_)
+            | ModuleDef::TypeAlias(_) => (),
+        }
+        acc.push(def);
+    }
+    for impl_ in module.impl_defs(db) {
+        // The items of trait impls are used through the trait.
+        if impl_.trait_(db).is_none() {
+            acc.extend(impl_.items(db).into_iter().map(ModuleDef::from));
+        }
+    }
+}
+
+fn is_candidate(db: &RootDatabase, def: ModuleDef) -> bool {
+    if def.visibility(db) != Visibility::Public {
+        return false;
+    }
+    // Used by other languages, or by the compiler.
+    let attrs = def.attrs(db);
+    !["no_mangle", "export_name", "proc_macro", "proc_macro_attribute", "proc_macro_derive"]
+        .iter()
+        .any(|&attr| attrs.as_ref().map_or(false, |attrs| attrs.by_key(attr).exists()))
+}
+
+fn kind(db: &RootDatabase, def: ModuleDef) -> &'static str {
+    match def {
+        ModuleDef::Function(it) if it.self_param(db).is_some() => "method",
+        ModuleDef::Function(_) => "function",
+        ModuleDef::Adt(hir::Adt::Struct(_)) => "struct",
+        ModuleDef::Adt(hir::Adt::Union(_)) => "union",
+        ModuleDef::Adt(hir::Adt::Enum(_)) => "enum",
+        ModuleDef::Trait(_) => "trait",
+        ModuleDef::TypeAlias(_) => "type alias",
+        ModuleDef::Const(_) => "constant",
+        ModuleDef::Static(_) => "static",
+        ModuleDef::Module(_) | ModuleDef::Variant(_) | ModuleDef::BuiltinType(_) => "item",
+    }
+}
+
+fn is_used(sema: &Semantics<RootDatabase>, def: ModuleDef, name_range: FileRange) -> bool {
+    // Calls of a trait method usually resolve to one of its implementations.
+    let mut defs = vec![def];
+    if let Some(AssocItemContainer::Trait(trait_)) =
+        def.as_assoc_item(sema.db).map(|it| it.container(sema.db))
+    {
+        let name = def.name(sema.db);
+        defs.extend(
+            Impl::all_for_trait(sema.db, trait_)
+                .into_iter()
+                .flat_map(|impl_| impl_.items(sema.db))
+                .filter(|it| it.name(sema.db) == name)
+                .map(ModuleDef::from),
+        );
+    }
+    defs.into_iter().any(|def| {
+        Definition::ModuleDef(def).usages(sema).all().into_iter().any(|(file_id, references)| {
+            references.iter().any(|it| is_use(file_id, it, name_range))
+        })
+    })
+}
+
+/// Whether `reference` uses the item named at `name_range`, rather than just
+/// mentioning it in its own definition.
+fn is_use(file_id: FileId, reference: &FileReference, name_range: FileRange) -> bool {
+    let name_ref = match &reference.name {
+        ast::NameLike::NameRef(it) => it,
+        // The definitions of trait impl items.
+        ast::NameLike::Name(_) | ast::NameLike::Lifetime(_) => return false,
+    };
+    // Recursive calls and types.
+    let in_definition = file_id == name_range.file_id
+        && name_ref.syntax().ancestors().any(|node| {
+            node.children()
+                .find_map(ast::Name::cast)
+                .map_or(false, |name| name.syntax().text_range() == name_range.range)
+        });
+    !in_definition && !is_impl_self_ty(name_ref)
+}
+
+fn is_impl_self_ty(name_ref: &ast::NameRef) -> bool {
+    (|| {
+        let ty = name_ref.syntax().ancestors().find_map(ast::PathType::cast)?;
+        let impl_ = ast::Impl::cast(ty.syntax().parent()?)?;
+        Some(impl_.self_ty()?.syntax() == ty.syntax())
+    })()
+    .unwrap_or(false)
+}
@@ -110,6 +110,25 @@ fn f() {
 
     #[cfg_attr(not(never), inline, cfg(no))] fn h() {}
   //^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ weak: code is inactive due to #[cfg] directives: no is disabled
+"#,
+        );
+    }
+
+    #[test]
+    fn inactive_in_every_target() {
+        check(
+            r#"
+//- /x86.rs crate:x86 cfg:target_arch=x86_64
+#[path = "shared.rs"]
+mod shared;
+//- /arm.rs crate:arm cfg:target_arch=arm
+#[path = "shared.rs"]
+mod shared;
+//- /shared.rs
+  #[cfg(target_arch = "arm")] fn arm() {}
+  #[cfg(target_arch = "x86_64")] fn x86() {}
+  #[cfg(target_arch = "riscv64")] fn riscv() {}
+//^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ weak: code is inactive due to #[cfg] directives: target_arch = "riscv64" is disabled
 "#,
         );
     }
@@ -0,0 +1,125 @@
+//! Reports matches of the structural search replace rules from `.ssr` rule
+//! files, offering the replacement as a fix.
+
+use ide_db::{
+    base_db::{FileId, FilePosition, FileRange, SourceDatabaseExt},
+    source_change::SourceChange,
+};
+use ide_ssr::MatchFinder;
+use syntax::{TextRange, TextSize};
+
+use crate::{fix, Diagnostic, DiagnosticsContext, Severity};
+
+// Diagnostic: ssr-rule
+//
+// This diagnostic is shown for code matched by one of the rules in the configured
+// `rust-analyzer.ssr.ruleFiles`. The message is the description of the rule.
+pub(crate) fn ssr_rule(ctx: &DiagnosticsContext, acc: &mut Vec<Diagnostic>, file_id: FileId) {
+    if ctx.config.ssr_rules.is_empty() {
+        return;
+    }
+    let db = ctx.sema.db;
+    let file_range = TextRange::up_to(TextSize::of(&*db.file_text(file_id)));
+    for named_rule in ctx.config.ssr_rules.iter() {
+        let mut match_finder = MatchFinder::in_context(
+            db,
+            FilePosition { file_id, offset: 0.into() },
+            vec![FileRange { file_id, range: file_range }],
+        );
+        // Paths in the rule might not resolve from this file, in which case
+        // there can't be any matches either.
+        if match_finder.add_rule(named_rule.rule()).is_err() {
+            continue;
+        }
+        let message = if named_rule.description.is_empty() {
+            format!("Matches rule `{}`", named_rule.name)
+        } else {
+            format!("{} (rule `{}`)", named_rule.description, named_rule.name)
+        };
+        for m in match_finder.matches().matches {
+            let range = m.range();
+            if range.file_id != file_id {
+                continue;
+            }
+            let edit = match_finder.edit_for_match(m);
+            acc.push(
+                Diagnostic::new("ssr-rule", message.clone(), range.range)
+                    .severity(Severity::WeakWarning)
+                    .with_fixes(Some(vec![fix(
+                        "apply_ssr_rule",
+                        &format!("Apply rule `{}`", named_rule.name),
+                        SourceChange::from_text_edit(file_id, edit),
+                        range.range,
+                    )])),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ide_db::{
+        assists::AssistResolveStrategy,
+        base_db::{fixture::WithFixture, SourceDatabaseExt},
+        RootDatabase,
+    };
+    use ide_ssr::parse_rule_file;
+    use test_utils::assert_eq_text;
+
+    use crate::{tests::check_diagnostics_with_config, DiagnosticsConfig};
+
+    fn config(rules: &str) -> DiagnosticsConfig {
+        DiagnosticsConfig { ssr_rules: parse_rule_file(rules).unwrap(), ..Default::default() }
+    }
+
+    #[test]
+    fn reports_matches() {
+        check_diagnostics_with_config(
+            config(
+                r#"
+/// Prefer `is_empty`.
+len_zero: $a.len() == 0 ==>> $a.is_empty()
+unknown_path: foo::bar($a) ==>> $a
+"#,
+            ),
+            r#"
+struct V;
+impl V {
+    fn len(&self) -> usize { 0 }
+    fn is_empty(&self) -> bool { true }
+}
+fn f(v: V) {
+    if v.len() == 0 {}
+     //^^^^^^^^^^^^ 💡 weak: Prefer `is_empty`. (rule `len_zero`)
+    if v.len() == 1 {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn fix_applies_the_rule() {
+        let (db, file_id) = RootDatabase::with_single_file(
+            r#"
+struct V;
+impl V { fn len(&self) -> usize { 0 } fn is_empty(&self) -> bool { true } }
+fn f(v: V) -> bool { v.len() == 0 }
+"#,
+        );
+        let config = config("len_zero: $a.len() == 0 ==>> $a.is_empty()");
+        let diagnostic =
+            crate::diagnostics(&db, &config, &AssistResolveStrategy::All, file_id).pop().unwrap();
+        let fix = &diagnostic.fixes.unwrap()[0];
+        let mut actual = db.file_text(file_id).to_string();
+        for edit in fix.source_change.as_ref().unwrap().source_file_edits.values() {
+            edit.apply(&mut actual);
+        }
+        assert_eq_text!(
+            r#"struct V;
+impl V { fn len(&self) -> usize { 0 } fn is_empty(&self) -> bool { true } }
+fn f(v: V) -> bool { v.is_empty() }
+"#,
+            &actual
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Reports public items that aren't used anywhere in the workspace.
+
+use ide_db::{base_db::FileId, unused_items::unused_public_items};
+
+use crate::{Diagnostic, DiagnosticsContext, Severity};
+
+// Diagnostic: unused-public-item
+//
+// This diagnostic is shown for a `pub` function, type, trait or trait method
+// that nothing in the workspace uses. It is off by default, as it searches the
+// whole workspace; enable it with `rust-analyzer.diagnostics.unusedPublicItems.enable`.
+pub(crate) fn unused_public_item(
+    ctx: &DiagnosticsContext,
+    acc: &mut Vec<Diagnostic>,
+    file_id: FileId,
+) {
+    if !ctx.config.unused_public_items {
+        return;
+    }
+    for item in unused_public_items(&ctx.sema, file_id) {
+        let name = item.def.name(ctx.sema.db).map(|it| it.to_string()).unwrap_or_default();
+        acc.push(
+            Diagnostic::new(
+                "unused-public-item",
+                format!("{} `{}` is never used in the workspace", item.kind, name),
+                item.range.range,
+            )
+            .severity(Severity::WeakWarning)
+            .with_unused(true),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{tests::check_diagnostics_with_config, DiagnosticsConfig};
+
+    fn check(ra_fixture: &str) {
+        let config = DiagnosticsConfig { unused_public_items: true, ..Default::default() };
+        check_diagnostics_with_config(config, ra_fixture)
+    }
+
+    #[test]
+    fn reports_unused_items() {
+        check(
+            r#"
+//- /lib.rs crate:lib
+pub fn used() {}
+pub fn unused() {}
+     //^^^^^^ weak: function `unused` is never used in the workspace
+fn private() {}
+pub struct Empty;
+         //^^^^^ weak: struct `Empty` is never used in the workspace
+impl Empty {}
+pub struct S;
+impl S {
+    pub fn new() -> S { S }
+         //^^^ weak: function `new` is never used in the workspace
+    pub fn recursive(&self) { self.recursive() }
+         //^^^^^^^^^ weak: method `recursive` is never used in the workspace
+}
+pub mod inline {
+    pub const C: u32 = 0;
+            //^ weak: constant `C` is never used in the workspace
+}
+//- /main.rs crate:main deps:lib
+fn main() {
+    lib::used();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn trait_methods() {
+        check(
+            r#"
+//- /lib.rs crate:lib
+pub trait Tr {
+    fn called(&self);
+    fn not_called(&self);
+     //^^^^^^^^^^ weak: method `not_called` is never used in the workspace
+}
+pub struct S;
+impl Tr for S {
+    fn called(&self) {}
+    fn not_called(&self) {}
+}
+//- /main.rs crate:main deps:lib
+use lib::Tr;
+fn main() {
+    lib::S.called();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn exclusions() {
+        check(
+            r#"
+//- /lib.rs crate:lib
+#[no_mangle]
+pub extern "C" fn from_c() {}
+#[export_name = "other"]
+pub extern "C" fn exported() {}
+pub(crate) fn crate_visible() {}
+"#,
+        );
+    }
+}
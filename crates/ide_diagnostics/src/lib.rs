@@ -48,6 +48,8 @@ mod handlers {
     pub(crate) mod field_shorthand;
     pub(crate) mod useless_braces;
     pub(crate) mod unlinked_file;
+    pub(crate) mod ssr_rule;
+    pub(crate) mod unused_public_item;
 }
 
 #[cfg(test)]
@@ -61,7 +63,8 @@ use ide_db::{
     source_change::SourceChange,
     RootDatabase,
 };
-use rustc_hash::FxHashSet;
+use ide_ssr::NamedSsrRule;
+use rustc_hash::{FxHashMap, FxHashSet};
 use syntax::{ast::AstNode, TextRange};
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -117,6 +120,10 @@ impl Diagnostic {
         self.unused = unused;
         self
     }
+
+    fn is_duplicate_of(&self, other: &Diagnostic) -> bool {
+        self.code == other.code && self.range == other.range && self.message == other.message
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -127,10 +134,14 @@ pub enum Severity {
     WeakWarning,
 }
 
-#[derive(Default, Debug, Clone)]
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct DiagnosticsConfig {
     pub disable_experimental: bool,
     pub disabled: FxHashSet<String>,
+    /// Structural search replace rules whose matches are reported as diagnostics.
+    pub ssr_rules: Vec<NamedSsrRule>,
+    /// Whether to report public items that aren't used in the workspace.
+    pub unused_public_items: bool,
 }
 
 struct DiagnosticsContext<'a> {
@@ -162,47 +173,66 @@ pub fn diagnostics(
         handlers::field_shorthand::field_shorthand(&mut res, file_id, &node);
     }
 
-    let module = sema.to_module_def(file_id);
+    // A file can belong to several crates, for example when the workspace is
+    // analyzed for more than one target. Diagnostics are merged across all of
+    // them.
+    let modules = sema.to_module_defs(file_id).collect::<Vec<_>>();
 
     let ctx = DiagnosticsContext { config, sema, resolve };
-    if module.is_none() {
+    if modules.is_empty() {
         handlers::unlinked_file::unlinked_file(&ctx, &mut res, file_id);
     }
+    handlers::ssr_rule::ssr_rule(&ctx, &mut res, file_id);
+    handlers::unused_public_item::unused_public_item(&ctx, &mut res, file_id);
 
-    let mut diags = Vec::new();
-    if let Some(m) = module {
-        m.diagnostics(db, &mut diags)
-    }
+    let mut semantic_diags: Vec<Diagnostic> = Vec::new();
+    // Code is only inactive if it is inactive in every crate.
+    let mut inactive: FxHashMap<TextRange, (Diagnostic, usize)> = FxHashMap::default();
+    for m in &modules {
+        let mut diags = Vec::new();
+        m.diagnostics(db, &mut diags);
+        for diag in diags {
+            #[rustfmt::skip]
+            let d = match diag {
+                AnyDiagnostic::AddReferenceHere(d) => handlers::add_reference_here::add_reference_here(&ctx, &d),
+                AnyDiagnostic::BreakOutsideOfLoop(d) => handlers::break_outside_of_loop::break_outside_of_loop(&ctx, &d),
+                AnyDiagnostic::IncorrectCase(d) => handlers::incorrect_case::incorrect_case(&ctx, &d),
+                AnyDiagnostic::MacroError(d) => handlers::macro_error::macro_error(&ctx, &d),
+                AnyDiagnostic::MismatchedArgCount(d) => handlers::mismatched_arg_count::mismatched_arg_count(&ctx, &d),
+                AnyDiagnostic::MissingFields(d) => handlers::missing_fields::missing_fields(&ctx, &d),
+                AnyDiagnostic::MissingMatchArms(d) => handlers::missing_match_arms::missing_match_arms(&ctx, &d),
+                AnyDiagnostic::MissingOkOrSomeInTailExpr(d) => handlers::missing_ok_or_some_in_tail_expr::missing_ok_or_some_in_tail_expr(&ctx, &d),
+                AnyDiagnostic::MissingUnsafe(d) => handlers::missing_unsafe::missing_unsafe(&ctx, &d),
+                AnyDiagnostic::NoSuchField(d) => handlers::no_such_field::no_such_field(&ctx, &d),
+                AnyDiagnostic::RemoveThisSemicolon(d) => handlers::remove_this_semicolon::remove_this_semicolon(&ctx, &d),
+                AnyDiagnostic::ReplaceFilterMapNextWithFindMap(d) => handlers::replace_filter_map_next_with_find_map::replace_filter_map_next_with_find_map(&ctx, &d),
+                AnyDiagnostic::UnimplementedBuiltinMacro(d) => handlers::unimplemented_builtin_macro::unimplemented_builtin_macro(&ctx, &d),
+                AnyDiagnostic::UnresolvedExternCrate(d) => handlers::unresolved_extern_crate::unresolved_extern_crate(&ctx, &d),
+                AnyDiagnostic::UnresolvedImport(d) => handlers::unresolved_import::unresolved_import(&ctx, &d),
+                AnyDiagnostic::UnresolvedMacroCall(d) => handlers::unresolved_macro_call::unresolved_macro_call(&ctx, &d),
+                AnyDiagnostic::UnresolvedModule(d) => handlers::unresolved_module::unresolved_module(&ctx, &d),
+                AnyDiagnostic::UnresolvedProcMacro(d) => handlers::unresolved_proc_macro::unresolved_proc_macro(&ctx, &d),
 
-    for diag in diags {
-        #[rustfmt::skip]
-        let d = match diag {
-            AnyDiagnostic::AddReferenceHere(d) => handlers::add_reference_here::add_reference_here(&ctx, &d),
-            AnyDiagnostic::BreakOutsideOfLoop(d) => handlers::break_outside_of_loop::break_outside_of_loop(&ctx, &d),
-            AnyDiagnostic::IncorrectCase(d) => handlers::incorrect_case::incorrect_case(&ctx, &d),
-            AnyDiagnostic::MacroError(d) => handlers::macro_error::macro_error(&ctx, &d),
-            AnyDiagnostic::MismatchedArgCount(d) => handlers::mismatched_arg_count::mismatched_arg_count(&ctx, &d),
-            AnyDiagnostic::MissingFields(d) => handlers::missing_fields::missing_fields(&ctx, &d),
-            AnyDiagnostic::MissingMatchArms(d) => handlers::missing_match_arms::missing_match_arms(&ctx, &d),
-            AnyDiagnostic::MissingOkOrSomeInTailExpr(d) => handlers::missing_ok_or_some_in_tail_expr::missing_ok_or_some_in_tail_expr(&ctx, &d),
-            AnyDiagnostic::MissingUnsafe(d) => handlers::missing_unsafe::missing_unsafe(&ctx, &d),
-            AnyDiagnostic::NoSuchField(d) => handlers::no_such_field::no_such_field(&ctx, &d),
-            AnyDiagnostic::RemoveThisSemicolon(d) => handlers::remove_this_semicolon::remove_this_semicolon(&ctx, &d),
-            AnyDiagnostic::ReplaceFilterMapNextWithFindMap(d) => handlers::replace_filter_map_next_with_find_map::replace_filter_map_next_with_find_map(&ctx, &d),
-            AnyDiagnostic::UnimplementedBuiltinMacro(d) => handlers::unimplemented_builtin_macro::unimplemented_builtin_macro(&ctx, &d),
-            AnyDiagnostic::UnresolvedExternCrate(d) => handlers::unresolved_extern_crate::unresolved_extern_crate(&ctx, &d),
-            AnyDiagnostic::UnresolvedImport(d) => handlers::unresolved_import::unresolved_import(&ctx, &d),
-            AnyDiagnostic::UnresolvedMacroCall(d) => handlers::unresolved_macro_call::unresolved_macro_call(&ctx, &d),
-            AnyDiagnostic::UnresolvedModule(d) => handlers::unresolved_module::unresolved_module(&ctx, &d),
-            AnyDiagnostic::UnresolvedProcMacro(d) => handlers::unresolved_proc_macro::unresolved_proc_macro(&ctx, &d),
-
-            AnyDiagnostic::InactiveCode(d) => match handlers::inactive_code::inactive_code(&ctx, &d) {
-                Some(it) => it,
-                None => continue,
+                AnyDiagnostic::InactiveCode(d) => {
+                    if let Some(d) = handlers::inactive_code::inactive_code(&ctx, &d) {
+                        inactive.entry(d.range).or_insert((d, 0)).1 += 1;
+                    }
+                    continue;
+                }
+            };
+            if !semantic_diags.iter().any(|it| it.is_duplicate_of(&d)) {
+                semantic_diags.push(d)
             }
-        };
-        res.push(d)
+        }
     }
+    res.extend(semantic_diags);
+    let mut inactive = inactive
+        .into_iter()
+        .filter(|(_, (_, count))| *count == modules.len())
+        .map(|(_, (d, _))| d)
+        .collect::<Vec<_>>();
+    inactive.sort_by_key(|d| d.range.start());
+    res.extend(inactive);
 
     res.retain(|d| {
         !ctx.config.disabled.contains(d.code.as_str())
@@ -9,6 +9,13 @@
 // The syntax for a structural search replace command is `<search_pattern> ==>> <replace_pattern>`.
 // A `$<name>` placeholder in the search pattern will match any AST node and `$<name>` will reference it in the replacement.
 // Within a macro call, a placeholder will match up until whatever token follows the placeholder.
+// A placeholder used more than once in the search pattern must match the same code each time.
+// Within an argument list, `$<name>*` matches any number of arguments, e.g. `foo($a, $rest*)`.
+//
+// The search pattern may also be an attribute, e.g. `#[derive($a)]`, or a sequence of statements,
+// e.g. `let $a = $b.lock().unwrap(); $a.$m()`, which matches consecutive statements of a block.
+// The replacement of a sequence of statements may be empty in order to remove them. When a path
+// matches within a `use` item, the use tree is moved into a `use` item of its own if needed.
 //
 // All paths in both the search pattern and the replacement template must resolve in the context
 // in which this command is invoked. Paths in the search pattern will then match the code if they
@@ -38,8 +45,14 @@
 //
 // | kind(literal) | Is a literal (e.g. `42` or `"forty two"`)
 // | not(a)        | Negates the constraint `a`
+// | type(T)       | Is an expression of type `T`, where `_` matches any type (e.g. `Vec<_>`)
+// | impls(Trait)  | Is an expression whose type implements `Trait` (e.g. `From<u32>`)
 // |===
 //
+// Types and traits in constraints are resolved in the same scope as the paths of the rule.
+// Omitted trait arguments with a default, like the `Rhs` of `PartialEq`, stand for the type of
+// the expression itself.
+//
 // Available via the command `rust-analyzer.ssr`.
 //
 // ```rust
@@ -66,6 +79,18 @@
 // // Place the cursor on the line below to see the assist 💡.
 // // foo($a, $b) ==>> ($a).foo($b)
 // ```
+//
+// Named rules can be kept in `.ssr` rule files, with `///` comments describing them:
+//
+// ```
+// /// Prefer `is_empty` over comparing the length with zero.
+// len_zero: ${x:type(Vec<_>)}.len() == 0 ==>> $x.is_empty()
+// ```
+//
+// Matches of the rules in the files listed in `rust-analyzer.ssr.ruleFiles` are shown as
+// diagnostics, with the replacement as a quick fix. On the command line,
+// `rust-analyzer ssr --rules lints.ssr --check` prints all matches and fails if there are any,
+// while `--fix` applies the rules.
 
 mod from_comment;
 mod matching;
@@ -73,6 +98,7 @@ mod nester;
 mod parsing;
 mod replacing;
 mod resolving;
+mod rule_file;
 mod search;
 #[macro_use]
 mod errors;
@@ -84,6 +110,7 @@ pub use crate::errors::SsrError;
 pub use crate::from_comment::ssr_from_comment;
 pub use crate::matching::Match;
 use crate::matching::MatchFailureReason;
+pub use crate::rule_file::{parse_rule_file, NamedSsrRule};
 use hir::Semantics;
 use ide_db::base_db::{FileId, FilePosition, FileRange};
 use resolving::ResolvedRule;
@@ -191,6 +218,13 @@ impl<'db> MatchFinder<'db> {
             .collect()
     }
 
+    /// Returns the edit replacing just `m`, which must have been found by this instance.
+    pub fn edit_for_match(&self, m: Match) -> TextEdit {
+        use ide_db::base_db::SourceDatabaseExt;
+        let file_text = self.sema.db.file_text(m.range.file_id);
+        replacing::matches_to_edit(&SsrMatches { matches: vec![m] }, &file_text, &self.rules)
+    }
+
     /// Adds a search pattern. For use if you intend to only call `find_matches_in_file`. If you
     /// intend to do replacement, use `add_rule` instead.
     pub fn add_search_pattern(&mut self, pattern: SsrPattern) -> Result<(), SsrError> {
@@ -342,6 +376,10 @@ impl Match {
     pub fn matched_text(&self) -> String {
         self.matched_node.text().to_string()
     }
+
+    pub fn range(&self) -> FileRange {
+        self.range
+    }
 }
 
 impl std::error::Error for SsrError {}
@@ -7,10 +7,16 @@ use crate::{
     SsrMatches,
 };
 use hir::Semantics;
-use ide_db::base_db::FileRange;
+use ide_db::base_db::{FileRange, SourceDatabaseExt};
 use rustc_hash::FxHashMap;
-use std::{cell::Cell, iter::Peekable};
-use syntax::{ast, SyntaxElement, SyntaxElementChildren, SyntaxKind, SyntaxNode, SyntaxToken};
+use std::{
+    cell::Cell,
+    iter::{self, Peekable},
+};
+use syntax::{
+    ast, Direction, SyntaxElement, SyntaxElementChildren, SyntaxKind, SyntaxNode, SyntaxToken,
+    TextRange, TextSize,
+};
 use syntax::{
     ast::{AstNode, AstToken},
     SmolStr,
@@ -126,10 +132,11 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
     ) -> Result<Match, MatchFailed> {
         let match_state = Matcher { sema, restrict_range: *restrict_range, rule };
         // First pass at matching, where we check that node types and idents match.
-        match_state.attempt_match_node(&mut Phase::First, &rule.pattern.node, code)?;
-        match_state.validate_range(&sema.original_range(code))?;
+        let last = match_state.attempt_match_root(&mut Phase::First, code)?;
+        let range = match_state.cover_range(code, &last);
+        match_state.validate_range(&range)?;
         let mut the_match = Match {
-            range: sema.original_range(code),
+            range,
             matched_node: code.clone(),
             placeholder_values: FxHashMap::default(),
             ignored_comments: Vec::new(),
@@ -139,11 +146,7 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         };
         // Second matching pass, where we record placeholder matches, ignored comments and maybe do
         // any other more expensive checks that we didn't want to do on the first pass.
-        match_state.attempt_match_node(
-            &mut Phase::Second(&mut the_match),
-            &rule.pattern.node,
-            code,
-        )?;
+        match_state.attempt_match_root(&mut Phase::Second(&mut the_match), code)?;
         the_match.depth = sema.ancestors_with_macros(the_match.matched_node.clone()).count();
         if let Some(template) = &rule.template {
             the_match.render_template_paths(template, sema)?;
@@ -151,6 +154,102 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         Ok(the_match)
     }
 
+    /// Matches the whole pattern against `code`, returning the last node that was matched. This is
+    /// `code` itself unless the pattern is a sequence of statements.
+    fn attempt_match_root(
+        &self,
+        phase: &mut Phase,
+        code: &SyntaxNode,
+    ) -> Result<SyntaxNode, MatchFailed> {
+        let pattern = &self.rule.pattern.node;
+        if pattern.kind() == SyntaxKind::STMT_LIST {
+            return self.attempt_match_stmt_sequence(phase, pattern, code);
+        }
+        self.attempt_match_node(phase, pattern, code)?;
+        Ok(code.clone())
+    }
+
+    /// Matches the statements of `pattern` against `code` and the statements following it in the
+    /// same block.
+    fn attempt_match_stmt_sequence(
+        &self,
+        phase: &mut Phase,
+        pattern: &SyntaxNode,
+        code: &SyntaxNode,
+    ) -> Result<SyntaxNode, MatchFailed> {
+        if code.parent().map(|it| it.kind()) != Some(SyntaxKind::STMT_LIST) {
+            fail_match!("Code `{}` isn't a statement", code.text());
+        }
+        let mut code_elements = iter::successors(Some(code.clone()), |it| it.next_sibling());
+        let mut last = code.clone();
+        for p in pattern.children() {
+            let c = code_elements
+                .next()
+                .ok_or_else(|| match_error!("Block ended before the pattern `{}`", p.text()))?;
+            last = match ast::ExprStmt::cast(c.clone()).and_then(|stmt| stmt.expr()) {
+                // A trailing expression in the pattern also matches an expression statement, in
+                // which case the semicolon is kept.
+                Some(expr) if ast::Expr::can_cast(p.kind()) => {
+                    self.attempt_match_node(phase, &p, expr.syntax())?;
+                    expr.syntax().clone()
+                }
+                _ => {
+                    self.attempt_match_node(phase, &p, &c)?;
+                    c
+                }
+            };
+        }
+        for element in code
+            .siblings_with_tokens(Direction::Next)
+            .take_while(|it| it.text_range().end() <= last.text_range().start())
+        {
+            if let SyntaxElement::Token(token) = element {
+                phase.record_ignored_comments(&token);
+            }
+        }
+        Ok(last)
+    }
+
+    /// Returns the original range from the start of `first` to the end of `last`.
+    fn cover_range(&self, first: &SyntaxNode, last: &SyntaxNode) -> FileRange {
+        let range = self.sema.original_range(first);
+        if first == last {
+            return range;
+        }
+        let last = self.sema.original_range(last);
+        FileRange { file_id: range.file_id, range: range.range.cover(last.range) }
+    }
+
+    /// Binds `placeholder` to the code at `range`. A placeholder that is used more than once in the
+    /// pattern must match the same code each time.
+    fn record_placeholder(
+        &self,
+        match_out: &mut Match,
+        placeholder: &Placeholder,
+        range: FileRange,
+    ) -> Result<(), MatchFailed> {
+        if let Some(existing) = match_out.placeholder_values.get(&placeholder.ident) {
+            let (existing, new) = (self.text_of(existing.range), self.text_of(range));
+            if existing != new {
+                fail_match!(
+                    "Placeholder `{}` matched both `{}` and `{}`",
+                    placeholder.ident,
+                    existing,
+                    new
+                );
+            }
+            return Ok(());
+        }
+        match_out
+            .placeholder_values
+            .insert(placeholder.ident.clone(), PlaceholderMatch::from_range(range));
+        Ok(())
+    }
+
+    fn text_of(&self, range: FileRange) -> String {
+        self.sema.db.file_text(range.file_id)[range.range].to_string()
+    }
+
     /// Checks that `range` is within the permitted range if any. This is applicable when we're
     /// processing a macro expansion and we want to fail the match if we're working with a node that
     /// didn't originate from the token tree of the macro call.
@@ -181,10 +280,7 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                 // We validated the range for the node when we started the match, so the placeholder
                 // probably can't fail range validation, but just to be safe...
                 self.validate_range(&original_range)?;
-                matches_out.placeholder_values.insert(
-                    placeholder.ident.clone(),
-                    PlaceholderMatch::from_range(original_range),
-                );
+                self.record_placeholder(matches_out, placeholder, original_range)?;
             }
             return Ok(());
         }
@@ -214,6 +310,9 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
             }
             SyntaxKind::TOKEN_TREE => self.attempt_match_token_tree(phase, pattern, code),
             SyntaxKind::PATH => self.attempt_match_path(phase, pattern, code),
+            SyntaxKind::ARG_LIST if self.has_repeated_placeholder(pattern) => {
+                self.attempt_match_arg_list(phase, pattern, code)
+            }
             _ => self.attempt_match_node_children(phase, pattern, code),
         }
     }
@@ -326,10 +425,37 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                     fail_match!("Constraint {:?} failed for '{}'", constraint, code.text());
                 }
             }
+            Constraint::Type(text) => {
+                let ty = self.type_of(code)?;
+                let pattern = &self.rule.pattern.constraint_types[text];
+                if !pattern.matches(self.sema.db, &ty) {
+                    fail_match!("Code '{}' isn't of type {}", code.text(), text);
+                }
+            }
+            Constraint::Impls(text) => {
+                let ty = self.type_of(code)?;
+                let pattern = &self.rule.pattern.constraint_traits[text];
+                let args: Vec<hir::Type> = pattern
+                    .args
+                    .iter()
+                    .map(|arg| arg.clone().unwrap_or_else(|| ty.clone()))
+                    .collect();
+                if !ty.impls_trait(self.sema.db, pattern.trait_, &args) {
+                    fail_match!("Type of '{}' doesn't implement {}", code.text(), text);
+                }
+            }
         }
         Ok(())
     }
 
+    fn type_of(&self, code: &SyntaxNode) -> Result<hir::Type, MatchFailed> {
+        ast::Expr::cast(code.clone())
+            .and_then(|expr| self.sema.type_of_expr(&expr))
+            .map(|info| info.original)
+            .filter(|ty| !ty.is_unknown())
+            .ok_or_else(|| match_error!("Couldn't infer the type of '{}'", code.text()))
+    }
+
     /// Paths are matched based on whether they refer to the same thing, even if they're written
     /// differently.
     fn attempt_match_path(
@@ -485,15 +611,13 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
                     last_matched_token = next;
                 }
                 if let Phase::Second(match_out) = phase {
-                    match_out.placeholder_values.insert(
-                        placeholder.ident.clone(),
-                        PlaceholderMatch::from_range(FileRange {
-                            file_id: self.sema.original_range(code).file_id,
-                            range: first_matched_token
-                                .text_range()
-                                .cover(last_matched_token.text_range()),
-                        }),
-                    );
+                    let range = FileRange {
+                        file_id: self.sema.original_range(code).file_id,
+                        range: first_matched_token
+                            .text_range()
+                            .cover(last_matched_token.text_range()),
+                    };
+                    self.record_placeholder(match_out, placeholder, range)?;
                 }
                 continue;
             }
@@ -521,6 +645,69 @@ impl<'db, 'sema> Matcher<'db, 'sema> {
         Ok(())
     }
 
+    fn has_repeated_placeholder(&self, arg_list: &SyntaxNode) -> bool {
+        arg_list.children().any(|arg| self.is_repeated_placeholder(&arg))
+    }
+
+    fn is_repeated_placeholder(&self, node: &SyntaxNode) -> bool {
+        self.get_placeholder_for_node(node).map_or(false, |placeholder| placeholder.repeated)
+    }
+
+    /// Matches an argument list containing a placeholder like `$args*`, which matches any number
+    /// of arguments, including none.
+    fn attempt_match_arg_list(
+        &self,
+        phase: &mut Phase,
+        pattern: &SyntaxNode,
+        code: &SyntaxNode,
+    ) -> Result<(), MatchFailed> {
+        let pattern_args: Vec<SyntaxNode> = pattern.children().collect();
+        let code_args: Vec<SyntaxNode> = code.children().collect();
+        let repeated_idx =
+            pattern_args.iter().position(|arg| self.is_repeated_placeholder(arg)).unwrap();
+        if pattern_args[repeated_idx + 1..].iter().any(|arg| self.is_repeated_placeholder(arg)) {
+            fail_match!("Only one repeated placeholder is supported per argument list");
+        }
+        let fixed_len = pattern_args.len() - 1;
+        if code_args.len() < fixed_len {
+            fail_match!(
+                "Pattern wanted at least {} arguments, code has {}",
+                fixed_len,
+                code_args.len()
+            );
+        }
+        let repeated_end = repeated_idx + code_args.len() - fixed_len;
+        let (prefix, suffix) = (&pattern_args[..repeated_idx], &pattern_args[repeated_idx + 1..]);
+        for (p, c) in
+            prefix.iter().zip(&code_args).chain(suffix.iter().zip(&code_args[repeated_end..]))
+        {
+            self.attempt_match_node(phase, p, c)?;
+        }
+        let placeholder = self.get_placeholder_for_node(&pattern_args[repeated_idx]).unwrap();
+        let repeated = &code_args[repeated_idx..repeated_end];
+        for arg in repeated {
+            for constraint in &placeholder.constraints {
+                self.check_constraint(constraint, arg)?;
+            }
+        }
+        if let Phase::Second(match_out) = phase {
+            let range = match (repeated.first(), repeated.last()) {
+                (Some(first), Some(last)) => self.cover_range(first, last),
+                // Nothing to bind to, so we use an empty range just after the `(`.
+                _ => {
+                    let range = self.sema.original_range(code);
+                    FileRange {
+                        file_id: range.file_id,
+                        range: TextRange::empty(range.range.start() + TextSize::of('(')),
+                    }
+                }
+            };
+            self.validate_range(&range)?;
+            self.record_placeholder(match_out, placeholder, range)?;
+        }
+        Ok(())
+    }
+
     fn attempt_match_ufcs_to_method_call(
         &self,
         phase: &mut Phase,
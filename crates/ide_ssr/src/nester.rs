@@ -30,6 +30,9 @@ pub(crate) fn nest_and_remove_collisions(
 #[derive(Default)]
 struct MatchCollector {
     matches_by_node: FxHashMap<SyntaxNode, Match>,
+    /// Matched nodes of matches that cover more than their matched node, i.e. matches of statement
+    /// sequences.
+    sequences: Vec<SyntaxNode>,
 }
 
 impl MatchCollector {
@@ -48,7 +51,34 @@ impl MatchCollector {
                 return;
             }
         }
-        self.matches_by_node.insert(matched_node, m);
+        // A sequence can't be found via ancestors, so we check the ranges of matches that either
+        // are or might overlap a sequence.
+        let is_sequence = m.range != sema.original_range(&m.matched_node);
+        if is_sequence || !self.sequences.is_empty() {
+            let candidates: Vec<SyntaxNode> = if is_sequence {
+                self.matches_by_node.keys().cloned().collect()
+            } else {
+                self.sequences.clone()
+            };
+            for node in candidates {
+                let existing = self.matches_by_node.get_mut(&node).unwrap();
+                if existing.range.file_id == m.range.file_id
+                    && existing.range.range.start() < m.range.range.end()
+                    && m.range.range.start() < existing.range.range.end()
+                {
+                    try_add_sub_match(m, existing, sema);
+                    return;
+                }
+            }
+        }
+        self.insert(m, is_sequence);
+    }
+
+    fn insert(&mut self, m: Match, is_sequence: bool) {
+        if is_sequence {
+            self.sequences.push(m.matched_node.clone());
+        }
+        self.matches_by_node.insert(m.matched_node.clone(), m);
     }
 }
 
@@ -66,7 +96,8 @@ fn try_add_sub_match(m: Match, existing: &mut Match, sema: &hir::Semantics<ide_d
             // exceptional.
             let mut collector = MatchCollector::default();
             for m in std::mem::take(&mut p.inner_matches.matches) {
-                collector.matches_by_node.insert(m.matched_node.clone(), m);
+                let is_sequence = m.range != sema.original_range(&m.matched_node);
+                collector.insert(m, is_sequence);
             }
             collector.add_match(m, sema);
             p.inner_matches = collector.into();
@@ -37,6 +37,8 @@ pub(crate) struct Placeholder {
     /// A unique name used in place of this placeholder when we parse the pattern as Rust code.
     stand_in_name: String,
     pub(crate) constraints: Vec<Constraint>,
+    /// Whether this placeholder was written as `$name*`, which matches any number of arguments.
+    pub(crate) repeated: bool,
 }
 
 /// Represents a `$var` in an SSR query.
@@ -47,6 +49,10 @@ pub(crate) struct Var(pub(crate) String);
 pub(crate) enum Constraint {
     Kind(NodeKind),
     Not(Box<Constraint>),
+    /// The placeholder is an expression of the given type, written as in the rule, e.g. `Vec<_>`.
+    Type(String),
+    /// The placeholder is an expression whose type implements the given trait.
+    Impls(String),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -84,10 +90,33 @@ impl ParsedRule {
         builder.try_add(ast::Path::parse(&raw_pattern), raw_template.map(ast::Path::parse));
         builder.try_add(ast::Pat::parse(&raw_pattern), raw_template.map(ast::Pat::parse));
         builder.try_add(ast::Stmt::parse(&raw_pattern), raw_template_stmt);
+        builder.try_add(ast::Attr::parse(&raw_pattern), raw_template.map(ast::Attr::parse));
+        // A pattern that is a sequence of statements, e.g. `let $a = $b; $a.foo()`. A single
+        // statement was already handled above, whereas the replacement may be a single statement,
+        // or even empty in order to delete the matched statements.
+        builder.try_add(
+            parse_stmt_sequence(&raw_pattern, 2),
+            raw_template.map(|t| parse_stmt_sequence(t, 0)),
+        );
         builder.build()
     }
 }
 
+/// Parses `text` as the statements of a block. Fails if there are fewer than `min_len` statements,
+/// counting a trailing expression as a statement.
+fn parse_stmt_sequence(text: &str, min_len: usize) -> Result<ast::StmtList, ()> {
+    let stmt_list = match ast::Expr::parse(&format!("{{{}}}", text))? {
+        ast::Expr::BlockExpr(block) => block.stmt_list().ok_or(())?,
+        _ => return Err(()),
+    };
+    // A macro call in statement position is both a statement and the tail expression, so we count
+    // the nodes instead.
+    if stmt_list.syntax().children().count() < min_len {
+        return Err(());
+    }
+    Ok(stmt_list)
+}
+
 struct RuleBuilder {
     placeholders_by_stand_in: FxHashMap<SmolStr, Placeholder>,
     rules: Vec<ParsedRule>,
@@ -191,7 +220,9 @@ impl RawPattern {
         let mut res = FxHashMap::default();
         for t in &self.tokens {
             if let PatternElement::Placeholder(placeholder) = t {
-                res.insert(SmolStr::new(placeholder.stand_in_name.clone()), placeholder.clone());
+                // Constraints are only given on the first use of a placeholder.
+                res.entry(SmolStr::new(placeholder.stand_in_name.clone()))
+                    .or_insert_with(|| placeholder.clone());
             }
         }
         res
@@ -211,15 +242,26 @@ impl FromStr for SsrPattern {
 /// Returns `pattern_str`, parsed as a search or replace pattern. If `remove_whitespace` is true,
 /// then any whitespace tokens will be removed, which we do for the search pattern, but not for the
 /// replace pattern.
+///
+/// A placeholder may be used more than once, in which case it must match the same code each time.
 fn parse_pattern(pattern_str: &str) -> Result<Vec<PatternElement>, SsrError> {
     let mut res = Vec::new();
     let mut placeholder_names = FxHashSet::default();
     let mut tokens = tokenize(pattern_str)?.into_iter();
     while let Some(token) = tokens.next() {
         if token.kind == T![$] {
-            let placeholder = parse_placeholder(&mut tokens)?;
-            if !placeholder_names.insert(placeholder.ident.clone()) {
-                bail!("Placeholder `{}` repeats more than once", placeholder.ident);
+            let mut placeholder = parse_placeholder(&mut tokens)?;
+            if is_repetition(tokens.as_slice()) {
+                tokens.next();
+                placeholder.repeated = true;
+            }
+            if !placeholder_names.insert(placeholder.ident.clone())
+                && !placeholder.constraints.is_empty()
+            {
+                bail!(
+                    "Constraints on placeholder `{}` must be given where it is first used",
+                    placeholder.ident
+                );
             }
             res.push(PatternElement::Placeholder(placeholder));
         } else {
@@ -229,6 +271,18 @@ fn parse_pattern(pattern_str: &str) -> Result<Vec<PatternElement>, SsrError> {
     Ok(res)
 }
 
+/// Returns whether `tokens`, which follow a placeholder, start with a `*` marking the placeholder as
+/// repeated. The `*` must end the argument, so that `$a*$b` is still a multiplication.
+fn is_repetition(tokens: &[Token]) -> bool {
+    match tokens {
+        [star, rest @ ..] if star.kind == T![*] => rest
+            .iter()
+            .find(|t| !t.kind.is_trivia())
+            .map_or(true, |t| matches!(t.kind, T![,] | T![')'])),
+        _ => false,
+    }
+}
+
 /// Checks for errors in a rule. e.g. the replace pattern referencing placeholders that the search
 /// pattern didn't define.
 fn validate_rule(rule: &SsrRule) -> Result<(), SsrError> {
@@ -333,10 +387,45 @@ fn parse_constraint(tokens: &mut std::vec::IntoIter<Token>) -> Result<Constraint
             expect_token(tokens, ")")?;
             Ok(Constraint::Not(Box::new(sub)))
         }
+        "type" => {
+            expect_token(tokens, "(")?;
+            Ok(Constraint::Type(parse_constraint_arg(tokens, "type")?))
+        }
+        "impls" => {
+            expect_token(tokens, "(")?;
+            Ok(Constraint::Impls(parse_constraint_arg(tokens, "impls")?))
+        }
         x => bail!("Unsupported constraint type '{}'", x),
     }
 }
 
+/// Collects the text of all tokens up to the `)` closing the argument of constraint `name`. The
+/// opening `(` has already been consumed.
+fn parse_constraint_arg(
+    tokens: &mut std::vec::IntoIter<Token>,
+    name: &str,
+) -> Result<String, SsrError> {
+    let mut depth = 0;
+    let mut arg = String::new();
+    loop {
+        let t = tokens.next().ok_or_else(|| {
+            SsrError(format!("Unexpected end of constraint while parsing {}", name))
+        })?;
+        match t.kind {
+            T!['('] => depth += 1,
+            T![')'] if depth == 0 => break,
+            T![')'] => depth -= 1,
+            _ => {}
+        }
+        arg.push_str(&t.text);
+    }
+    let arg = arg.trim();
+    if arg.is_empty() {
+        bail!("Expected a type or trait in {} constraint", name);
+    }
+    Ok(arg.to_string())
+}
+
 fn expect_token(tokens: &mut std::vec::IntoIter<Token>, expected: &str) -> Result<(), SsrError> {
     if let Some(t) = tokens.next() {
         if t.text == expected {
@@ -361,6 +450,7 @@ impl Placeholder {
         Self {
             stand_in_name: format!("__placeholder_{}", name),
             constraints,
+            repeated: false,
             ident: Var(name.to_string()),
         }
     }
@@ -3,8 +3,11 @@
 use crate::{resolving::ResolvedRule, Match, SsrMatches};
 use itertools::Itertools;
 use rustc_hash::{FxHashMap, FxHashSet};
+use std::iter;
 use syntax::ast::{self, AstNode, AstToken};
-use syntax::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize};
+use syntax::{
+    Direction, SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken, TextRange, TextSize, T,
+};
 
 use text_edit::TextEdit;
 
@@ -26,15 +29,140 @@ fn matches_to_edit_at_offset(
     rules: &[ResolvedRule],
 ) -> TextEdit {
     let mut edit_builder = TextEdit::builder();
+    let moved_use_trees: FxHashSet<SyntaxNode> = matches
+        .matches
+        .iter()
+        .filter_map(|m| nested_use_tree(m).map(|(tree, _)| tree.syntax().clone()))
+        .collect();
     for m in &matches.matches {
-        edit_builder.replace(
-            m.range.range.checked_sub(relative_start).unwrap(),
-            render_replace(m, file_src, rules),
-        );
+        let replacement = render_replace(m, file_src, rules);
+        match nested_use_tree(m) {
+            Some((tree, path)) => {
+                for (range, text) in move_use_tree(&tree, &path, &replacement, &moved_use_trees) {
+                    edit_builder.replace(range.checked_sub(relative_start).unwrap(), text);
+                }
+            }
+            None => {
+                let mut range = m.range.range;
+                if replacement.is_empty() && is_sequence_match(m, rules) {
+                    // Deleted statements take the whitespace that follows them along.
+                    let whitespace = file_src[range.end().into()..]
+                        .chars()
+                        .take_while(|c| c.is_whitespace())
+                        .map(TextSize::of)
+                        .sum::<TextSize>();
+                    range = TextRange::new(range.start(), range.end() + whitespace);
+                }
+                edit_builder.replace(range.checked_sub(relative_start).unwrap(), replacement)
+            }
+        }
     }
     edit_builder.finish()
 }
 
+fn is_sequence_match(m: &Match, rules: &[ResolvedRule]) -> bool {
+    rules[m.rule_index].pattern.node.kind() == SyntaxKind::STMT_LIST
+}
+
+/// If `m` matched the start of the path of a use tree that is nested within another, e.g. `bar` in
+/// `use foo::{bar, baz};`, returns that use tree and the matched path.
+fn nested_use_tree(m: &Match) -> Option<(ast::UseTree, ast::Path)> {
+    let path = ast::Path::cast(m.matched_node.clone())?;
+    if path.syntax().text_range() != m.range.range {
+        // Use trees within macro expansions aren't supported.
+        return None;
+    }
+    let tree = path
+        .syntax()
+        .ancestors()
+        .find(|it| it.kind() != SyntaxKind::PATH)
+        .and_then(ast::UseTree::cast)?;
+    if tree.path()?.syntax().text_range().start() != path.syntax().text_range().start()
+        || tree.syntax().parent()?.kind() != SyntaxKind::USE_TREE_LIST
+    {
+        return None;
+    }
+    Some((tree, path))
+}
+
+/// Returns the edits that move `tree` into a use item of its own, with `path` replaced by
+/// `replacement`. We can't replace `path` where it is, since `replacement` is a path from the crate
+/// root or a prelude, not from the prefix of the enclosing use tree. `moved_trees` are all the use
+/// trees that are being moved, which we need in order to avoid overlapping edits when removing
+/// commas.
+fn move_use_tree(
+    tree: &ast::UseTree,
+    path: &ast::Path,
+    replacement: &str,
+    moved_trees: &FxHashSet<SyntaxNode>,
+) -> Vec<(TextRange, String)> {
+    let tree_range = tree.syntax().text_range();
+    let rest = tree.syntax().text().slice(path.syntax().text_range().end() - tree_range.start()..);
+    let new_tree = format!("{}{}", replacement, rest);
+
+    let list = tree.syntax().parent().and_then(ast::UseTreeList::cast);
+    let use_item = tree.syntax().ancestors().find_map(ast::Use::cast);
+    let (list, use_item) = match (list, use_item) {
+        (Some(list), Some(use_item)) => (list, use_item),
+        _ => return Vec::new(),
+    };
+    let outer_tree = list.parent_use_tree();
+    // If the tree is all there is in the use item, we can replace the use item's tree.
+    if list.use_trees().count() == 1
+        && outer_tree.syntax().parent() == Some(use_item.syntax().clone())
+    {
+        return vec![(outer_tree.syntax().text_range(), new_tree)];
+    }
+
+    let next_non_trivia = |element: SyntaxElement, direction| {
+        let step = move |it: &SyntaxElement| match direction {
+            Direction::Next => it.next_sibling_or_token(),
+            Direction::Prev => it.prev_sibling_or_token(),
+        };
+        iter::successors(step(&element), step).find(|it| !it.kind().is_trivia())
+    };
+    let tree_element = SyntaxElement::Node(tree.syntax().clone());
+    let removed_range = match next_non_trivia(tree_element.clone(), Direction::Next) {
+        Some(comma) if comma.kind() == T![,] => {
+            let end = next_non_trivia(comma.clone(), Direction::Next)
+                .map_or(comma.text_range().end(), |it| it.text_range().start());
+            TextRange::new(tree_range.start(), end)
+        }
+        _ => match next_non_trivia(tree_element, Direction::Prev) {
+            Some(comma)
+                if comma.kind() == T![,]
+                    && !next_non_trivia(comma.clone(), Direction::Prev)
+                        .and_then(|it| it.into_node())
+                        .map_or(false, |it| moved_trees.contains(&it)) =>
+            {
+                TextRange::new(comma.text_range().start(), tree_range.end())
+            }
+            _ => tree_range,
+        },
+    };
+    // Keep any attributes and visibility of the use item.
+    let item_start = use_item.syntax().text_range().start();
+    let prefix = match use_item.use_token() {
+        Some(use_token) => use_item
+            .syntax()
+            .text()
+            .slice(..use_token.text_range().start() - item_start)
+            .to_string(),
+        None => String::new(),
+    };
+    let indent = use_item
+        .syntax()
+        .prev_sibling_or_token()
+        .and_then(|it| ast::Whitespace::cast(it.into_token()?))
+        .map(|ws| ws.text().rsplit('\n').next().unwrap_or_default().to_string())
+        .unwrap_or_default();
+    let new_item = format!("\n{}{}use {};", indent, prefix, new_tree);
+    vec![
+        (removed_range, String::new()),
+        (TextRange::empty(use_item.syntax().text_range().end()), new_item),
+    ]
+}
+
 struct ReplacementRenderer<'a> {
     match_info: &'a Match,
     file_src: &'a str,
@@ -66,6 +194,22 @@ fn render_replace(match_info: &Match, file_src: &str, rules: &[ResolvedRule]) ->
         placeholder_tokens_requiring_parenthesis: FxHashSet::default(),
         placeholder_tokens_by_range: FxHashMap::default(),
     };
+    if template.node.kind() == SyntaxKind::STMT_LIST {
+        // A sequence of statements is rendered without the braces of the block it was parsed in.
+        // Comments from between the matched statements go on lines of their own before it.
+        let start = usize::from(match_info.range.range.start());
+        let line_start = file_src[..start].rfind('\n').map_or(0, |it| it + 1);
+        let indent = &file_src[line_start..start];
+        for comment in &match_info.ignored_comments {
+            renderer.out.push_str(&format!("{}\n{}", comment, indent));
+        }
+        for element in template.node.children_with_tokens() {
+            if !matches!(element.kind(), T!['{'] | T!['}']) {
+                renderer.render_node_or_token(&element);
+            }
+        }
+        return renderer.out.trim().to_string();
+    }
     renderer.render_node(&template.node);
     renderer.maybe_rerender_with_extra_parenthesis(&template.node);
     for comment in &match_info.ignored_comments {
@@ -153,13 +297,17 @@ impl ReplacementRenderer<'_> {
                 if needs_parenthesis {
                     self.out.push('(');
                 }
-                self.placeholder_tokens_by_range.insert(
-                    TextRange::new(
-                        TextSize::of(&self.out),
-                        TextSize::of(&self.out) + TextSize::of(&matched_text),
-                    ),
-                    token.clone(),
-                );
+                // A repeated placeholder matched a list of arguments, not a single node, so it
+                // can't be split.
+                if !placeholder.repeated {
+                    self.placeholder_tokens_by_range.insert(
+                        TextRange::new(
+                            TextSize::of(&self.out),
+                            TextSize::of(&self.out) + TextSize::of(&matched_text),
+                        ),
+                        token.clone(),
+                    );
+                }
                 self.out.push_str(&matched_text);
                 if needs_parenthesis {
                     self.out.push(')');
@@ -1,9 +1,9 @@
 //! This module is responsible for resolving paths within rules.
 
-use crate::errors::error;
+use crate::errors::{bail, error};
 use crate::{parsing, SsrError};
 use ide_db::base_db::FilePosition;
-use parsing::Placeholder;
+use parsing::{Constraint, Placeholder};
 use rustc_hash::FxHashMap;
 use syntax::{ast, SmolStr, SyntaxKind, SyntaxNode, SyntaxToken};
 
@@ -25,6 +25,25 @@ pub(crate) struct ResolvedPattern {
     pub(crate) resolved_paths: FxHashMap<SyntaxNode, ResolvedPath>,
     pub(crate) ufcs_function_calls: FxHashMap<SyntaxNode, UfcsCallInfo>,
     pub(crate) contains_self: bool,
+    /// Types and traits from `type(..)` and `impls(..)` constraints, keyed by their text.
+    pub(crate) constraint_types: FxHashMap<String, TypePattern>,
+    pub(crate) constraint_traits: FxHashMap<String, TraitPattern>,
+}
+
+/// A trait from an `impls(..)` constraint, with its type arguments. Omitted arguments with a
+/// default are `None` and stand for the checked type itself, like the `Rhs = Self` of `PartialEq`.
+pub(crate) struct TraitPattern {
+    pub(crate) trait_: hir::Trait,
+    pub(crate) args: Vec<Option<hir::Type>>,
+}
+
+/// A type from a `type(..)` constraint. `_` matches any type, and so do omitted type arguments.
+pub(crate) enum TypePattern {
+    Any,
+    Adt(hir::Adt, Vec<TypePattern>),
+    Builtin(hir::BuiltinType),
+    Ref { mutable: bool, inner: Box<TypePattern> },
+    Tuple(Vec<TypePattern>),
 }
 
 pub(crate) struct ResolvedPath {
@@ -101,12 +120,147 @@ impl Resolver<'_, '_> {
                 SyntaxElement::Token(t) => t.kind() == T![self],
                 _ => false,
             });
+        let mut constraint_types = FxHashMap::default();
+        let mut constraint_traits = FxHashMap::default();
+        for placeholder in self.placeholders_by_stand_in.values() {
+            for constraint in &placeholder.constraints {
+                self.resolve_constraint(constraint, &mut constraint_types, &mut constraint_traits)?;
+            }
+        }
         Ok(ResolvedPattern {
             node: pattern,
             resolved_paths,
             placeholders_by_stand_in: self.placeholders_by_stand_in.clone(),
             ufcs_function_calls,
             contains_self,
+            constraint_types,
+            constraint_traits,
+        })
+    }
+
+    fn resolve_constraint(
+        &self,
+        constraint: &Constraint,
+        types: &mut FxHashMap<String, TypePattern>,
+        traits: &mut FxHashMap<String, TraitPattern>,
+    ) -> Result<(), SsrError> {
+        match constraint {
+            Constraint::Kind(_) => {}
+            Constraint::Not(sub) => self.resolve_constraint(sub, types, traits)?,
+            Constraint::Type(text) => {
+                let ty = ast::Type::parse(text)
+                    .map_err(|()| error!("Invalid type `{}` in type constraint", text))?;
+                types.insert(text.clone(), self.resolve_type_pattern(&ty)?);
+            }
+            Constraint::Impls(text) => {
+                let path = ast::Path::parse(text)
+                    .map_err(|()| error!("Invalid trait `{}` in impls constraint", text))?;
+                let trait_ = match self.resolution_scope.resolve_path(&path) {
+                    Some(hir::PathResolution::Def(hir::ModuleDef::Trait(trait_))) => trait_,
+                    _ => bail!("Failed to resolve trait `{}`", text),
+                };
+                traits.insert(text.clone(), self.resolve_trait_pattern(trait_, &path, text)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_trait_pattern(
+        &self,
+        trait_: hir::Trait,
+        path: &ast::Path,
+        text: &str,
+    ) -> Result<TraitPattern, SsrError> {
+        let db = self.resolution_scope.scope.db;
+        if hir::GenericDef::from(trait_)
+            .params(db)
+            .iter()
+            .any(|param| matches!(param, hir::GenericParam::ConstParam(_)))
+        {
+            bail!("Unsupported trait `{}` with const parameters in impls constraint", text);
+        }
+        let mut written = Vec::new();
+        for arg in path
+            .segment()
+            .and_then(|it| it.generic_arg_list())
+            .into_iter()
+            .flat_map(|it| it.generic_args())
+        {
+            match arg {
+                ast::GenericArg::TypeArg(it) => written.extend(it.ty()),
+                ast::GenericArg::LifetimeArg(_) => {}
+                _ => bail!("Unsupported generic argument `{}` in impls constraint", arg),
+            }
+        }
+        let params = trait_.type_params(db);
+        if written.len() > params.len() {
+            bail!("Too many type arguments for trait `{}`", text);
+        }
+        let mut args = Vec::new();
+        for (idx, param) in params.into_iter().enumerate() {
+            match written.get(idx) {
+                Some(ty) => {
+                    let resolved = self
+                        .resolution_scope
+                        .scope
+                        .speculative_resolve_type(ty)
+                        .filter(|it| !it.is_unknown())
+                        .ok_or_else(|| error!("Failed to resolve type `{}`", ty))?;
+                    args.push(Some(resolved));
+                }
+                None if param.default(db).map_or(false, |it| !it.is_unknown()) => args.push(None),
+                None => bail!(
+                    "Missing type argument `{}` for trait `{}` in impls constraint",
+                    param.name(db),
+                    text
+                ),
+            }
+        }
+        Ok(TraitPattern { trait_, args })
+    }
+
+    fn resolve_type_pattern(&self, ty: &ast::Type) -> Result<TypePattern, SsrError> {
+        Ok(match ty {
+            ast::Type::InferType(_) => TypePattern::Any,
+            ast::Type::ParenType(it) => match it.ty() {
+                Some(inner) => self.resolve_type_pattern(&inner)?,
+                None => bail!("Invalid type `{}` in type constraint", ty),
+            },
+            ast::Type::RefType(it) => match it.ty() {
+                Some(inner) => TypePattern::Ref {
+                    mutable: it.mut_token().is_some(),
+                    inner: Box::new(self.resolve_type_pattern(&inner)?),
+                },
+                None => bail!("Invalid type `{}` in type constraint", ty),
+            },
+            ast::Type::TupleType(it) => TypePattern::Tuple(
+                it.fields().map(|it| self.resolve_type_pattern(&it)).collect::<Result<_, _>>()?,
+            ),
+            ast::Type::PathType(it) => {
+                let path =
+                    it.path().ok_or_else(|| error!("Invalid type `{}` in type constraint", ty))?;
+                let args = path
+                    .segment()
+                    .and_then(|it| it.generic_arg_list())
+                    .into_iter()
+                    .flat_map(|it| it.generic_args())
+                    .filter_map(|arg| match arg {
+                        ast::GenericArg::TypeArg(it) => it.ty(),
+                        _ => None,
+                    })
+                    .map(|it| self.resolve_type_pattern(&it))
+                    .collect::<Result<_, _>>()?;
+                match self.resolution_scope.resolve_path(&path) {
+                    Some(hir::PathResolution::Def(hir::ModuleDef::Adt(adt))) => {
+                        TypePattern::Adt(adt, args)
+                    }
+                    Some(hir::PathResolution::Def(hir::ModuleDef::BuiltinType(builtin))) => {
+                        TypePattern::Builtin(builtin)
+                    }
+                    _ => bail!("Failed to resolve type `{}`", ty),
+                }
+            }
+            _ => bail!("Unsupported type `{}` in type constraint", ty),
         })
     }
 
@@ -117,6 +271,10 @@ impl Resolver<'_, '_> {
         resolved_paths: &mut FxHashMap<SyntaxNode, ResolvedPath>,
     ) -> Result<(), SsrError> {
         use syntax::ast::AstNode;
+        if node.kind() == SyntaxKind::ATTR {
+            // Paths in attributes, like `derive`, can't be resolved, so they're matched as text.
+            return Ok(());
+        }
         if let Some(path) = ast::Path::cast(node.clone()) {
             if is_self(&path) {
                 // Self cannot be resolved like other paths.
@@ -182,6 +340,29 @@ impl Resolver<'_, '_> {
     }
 }
 
+impl TypePattern {
+    pub(crate) fn matches(&self, db: &dyn hir::db::HirDatabase, ty: &hir::Type) -> bool {
+        match self {
+            TypePattern::Any => true,
+            TypePattern::Adt(adt, args) => {
+                ty.as_adt() == Some(*adt)
+                    && args.iter().zip(ty.type_arguments()).all(|(arg, ty)| arg.matches(db, &ty))
+            }
+            TypePattern::Builtin(builtin) => ty.as_builtin() == Some(*builtin),
+            TypePattern::Ref { mutable, inner } => {
+                ty.is_mutable_reference() == *mutable
+                    && ty.remove_ref().map_or(false, |ty| inner.matches(db, &ty))
+            }
+            TypePattern::Tuple(fields) => {
+                let ty_fields = ty.tuple_fields(db);
+                (!fields.is_empty() || ty.is_unit())
+                    && fields.len() == ty_fields.len()
+                    && fields.iter().zip(&ty_fields).all(|(field, ty)| field.matches(db, ty))
+            }
+        }
+    }
+}
+
 impl<'db> ResolutionScope<'db> {
     pub(crate) fn new(
         sema: &hir::Semantics<'db, ide_db::RootDatabase>,
@@ -206,6 +387,11 @@ impl<'db> ResolutionScope<'db> {
         self.node.ancestors().find(|node| node.kind() == SyntaxKind::FN)
     }
 
+    /// Returns the crate in which SSR was invoked.
+    pub(crate) fn current_crate(&self) -> Option<hir::Crate> {
+        Some(self.scope.module()?.krate())
+    }
+
     fn resolve_path(&self, path: &ast::Path) -> Option<hir::PathResolution> {
         // First try resolving the whole path. This will work for things like
         // `std::collections::HashMap`, but will fail for things like
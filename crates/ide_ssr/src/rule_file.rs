@@ -0,0 +1,153 @@
+//! Parsing of `.ssr` rule files, which hold any number of named rules:
+//!
+//! ```text
+//! // Plain comments are ignored.
+//! /// Doc comments describe the rule that follows them.
+//! len_zero: $a.len() == 0 ==>> $a.is_empty()
+//! unwrap_or_default:
+//!     $a.unwrap_or_else(Default::default)
+//!     ==>> $a.unwrap_or_default()
+//! ```
+//!
+//! A rule continues on the following lines for as long as they are indented.
+
+use rustc_hash::FxHashSet;
+
+use crate::{errors::bail, SsrError, SsrRule};
+
+/// A rule from a rule file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedSsrRule {
+    pub name: String,
+    /// Text of the doc comments preceding the rule, may be empty.
+    pub description: String,
+    /// The 1-based line on which the rule starts.
+    pub line: u32,
+    /// Source of the rule, which we keep around instead of the parsed [`SsrRule`] so that rules can
+    /// be sent across threads.
+    text: String,
+}
+
+impl NamedSsrRule {
+    pub fn rule(&self) -> SsrRule {
+        self.text.parse().expect("rule was validated when parsing the rule file")
+    }
+}
+
+pub fn parse_rule_file(text: &str) -> Result<Vec<NamedSsrRule>, SsrError> {
+    let mut res: Vec<NamedSsrRule> = Vec::new();
+    let mut names = FxHashSet::default();
+    let mut description = Vec::new();
+    let mut current: Option<NamedSsrRule> = None;
+
+    for (idx, line) in text.lines().enumerate() {
+        let line_no = idx as u32 + 1;
+        let trimmed = line.trim();
+        if line.starts_with(char::is_whitespace) && !trimmed.is_empty() {
+            match &mut current {
+                Some(rule) if !trimmed.starts_with("//") => {
+                    if !rule.text.is_empty() {
+                        rule.text.push(' ');
+                    }
+                    rule.text.push_str(trimmed);
+                    continue;
+                }
+                Some(_) => continue,
+                None => bail!("line {}: Indented line doesn't continue a rule", line_no),
+            }
+        }
+        finish_rule(&mut current, &mut res)?;
+
+        if let Some(doc) = trimmed.strip_prefix("///") {
+            description.push(doc.trim().to_string());
+        } else if trimmed.is_empty() || trimmed.starts_with("//") {
+            continue;
+        } else {
+            let (name, rule) = match trimmed.split_once(':') {
+                // `::` belongs to a path in a rule without a name.
+                Some((name, rule)) if !rule.starts_with(':') => (name, rule),
+                _ => bail!("line {}: Expected `<name>: <rule>`", line_no),
+            };
+            let name = name.trim();
+            if name.is_empty()
+                || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+            {
+                bail!("line {}: Invalid rule name `{}`", line_no, name);
+            }
+            if !names.insert(name.to_string()) {
+                bail!("line {}: Rule `{}` is defined more than once", line_no, name);
+            }
+            current = Some(NamedSsrRule {
+                name: name.to_string(),
+                description: description.join(" ").trim().to_string(),
+                line: line_no,
+                text: rule.trim().to_string(),
+            });
+            description.clear();
+        }
+    }
+    finish_rule(&mut current, &mut res)?;
+    Ok(res)
+}
+
+fn finish_rule(
+    current: &mut Option<NamedSsrRule>,
+    acc: &mut Vec<NamedSsrRule>,
+) -> Result<(), SsrError> {
+    if let Some(rule) = current.take() {
+        if let Err(err) = rule.text.parse::<SsrRule>() {
+            bail!("line {}: Rule `{}`: {}", rule.line, rule.name, err.0);
+        }
+        acc.push(rule);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_named_rules() {
+        let rules = parse_rule_file(
+            r#"
+// Not a description.
+/// Prefer `is_empty`
+/// over comparing the length.
+len_zero: $a.len() == 0 ==>> $a.is_empty()
+
+unwrap_or_default:
+    $a.unwrap_or_else(Default::default)
+    // Comments may appear within a rule.
+    ==>> $a.unwrap_or_default()
+"#,
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[0].name, "len_zero");
+        assert_eq!(rules[0].description, "Prefer `is_empty` over comparing the length.");
+        assert_eq!(rules[0].line, 5);
+        assert_eq!(rules[1].name, "unwrap_or_default");
+        assert_eq!(rules[1].description, "");
+        assert_eq!(
+            rules[1].text,
+            "$a.unwrap_or_else(Default::default) ==>> $a.unwrap_or_default()"
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let err = |text| parse_rule_file(text).unwrap_err().to_string();
+        assert_eq!(
+            err("\nfoo: $a ==>> $b"),
+            "Parse error: line 2: Rule `foo`: Replacement contains undefined placeholders: $b"
+        );
+        assert_eq!(err("a::b($x) ==>> c($x)"), "Parse error: line 1: Expected `<name>: <rule>`");
+        assert_eq!(err("a b: x ==>> y"), "Parse error: line 1: Invalid rule name `a b`");
+        assert_eq!(
+            err("a: x ==>> y\na: y ==>> x"),
+            "Parse error: line 2: Rule `a` is defined more than once"
+        );
+        assert_eq!(err("  x ==>> y"), "Parse error: line 1: Indented line doesn't continue a rule");
+    }
+}
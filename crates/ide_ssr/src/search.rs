@@ -11,6 +11,7 @@ use ide_db::{
     search::{SearchScope, UsageSearchResult},
 };
 use rustc_hash::FxHashSet;
+use std::cmp::Reverse;
 use syntax::{ast, AstNode, SyntaxKind, SyntaxNode};
 
 /// A cache for the results of find_usages. This is for when we have multiple patterns that have the
@@ -41,7 +42,11 @@ impl<'db> MatchFinder<'db> {
             }
             return;
         }
-        if pick_path_for_usages(&rule.pattern).is_none() {
+        // The matched node of a statement sequence is its first statement, which we can't find
+        // from a path within a later statement.
+        if self.pick_path_for_usages(&rule.pattern).is_none()
+            || rule.pattern.node.kind() == SyntaxKind::STMT_LIST
+        {
             self.slow_scan(rule, matches_out);
             return;
         }
@@ -55,14 +60,10 @@ impl<'db> MatchFinder<'db> {
         usage_cache: &mut UsageCache,
         matches_out: &mut Vec<Match>,
     ) {
-        if let Some(resolved_path) = pick_path_for_usages(pattern) {
+        if let Some(resolved_path) = self.pick_path_for_usages(pattern) {
             let definition: Definition = resolved_path.resolution.clone().into();
             for file_range in self.find_usages(usage_cache, definition).file_ranges() {
                 if let Some(node_to_match) = self.find_node_to_match(resolved_path, file_range) {
-                    if !is_search_permitted_ancestors(&node_to_match) {
-                        cov_mark::hit!(use_declaration_with_braces);
-                        continue;
-                    }
                     self.try_add_match(rule, &node_to_match, &None, matches_out);
                 }
             }
@@ -169,9 +170,6 @@ impl<'db> MatchFinder<'db> {
         restrict_range: &Option<FileRange>,
         matches_out: &mut Vec<Match>,
     ) {
-        if !is_search_permitted(code) {
-            return;
-        }
         self.try_add_match(rule, code, restrict_range, matches_out);
         // If we've got a macro call, we already tried matching it pre-expansion, which is the only
         // way to match the whole macro, now try expanding it and matching the expansion.
@@ -226,25 +224,33 @@ impl<'db> MatchFinder<'db> {
         }
         false
     }
-}
 
-/// Returns whether we support matching within `node` and all of its ancestors.
-fn is_search_permitted_ancestors(node: &SyntaxNode) -> bool {
-    if let Some(parent) = node.parent() {
-        if !is_search_permitted_ancestors(&parent) {
-            return false;
-        }
+    /// Returns a path that's suitable for path resolution. We exclude builtin types, since they
+    /// aren't something that we can find references to. We then prefer paths to private items,
+    /// followed by items of the current crate, since their usages are searched for in a smaller
+    /// scope than say a path from std. Among those, we somewhat arbitrarily pick the path that is
+    /// the longest as this is hopefully more likely to be less common, making it faster to find.
+    fn pick_path_for_usages<'a>(&self, pattern: &'a ResolvedPattern) -> Option<&'a ResolvedPath> {
+        // FIXME: We could go further than this and intersect the search scopes for all resolved
+        // paths then search only in that scope.
+        let db = self.sema.db;
+        let current_crate = self.resolution_scope.current_crate();
+        pattern
+            .resolved_paths
+            .iter()
+            .filter(|(_, p)| {
+                !matches!(p.resolution, hir::PathResolution::Def(hir::ModuleDef::BuiltinType(_)))
+            })
+            .map(|(node, resolved)| {
+                let definition: Definition = resolved.resolution.clone().into();
+                let is_public = definition.visibility(db) == Some(hir::Visibility::Public);
+                let is_foreign = current_crate.is_none()
+                    || definition.module(db).map(|it| it.krate()) != current_crate;
+                ((is_public, is_foreign, Reverse(node.text().len())), resolved)
+            })
+            .min_by_key(|(key, _)| *key)
+            .map(|(_, resolved)| resolved)
     }
-    is_search_permitted(node)
-}
-
-/// Returns whether we support matching within this kind of node.
-fn is_search_permitted(node: &SyntaxNode) -> bool {
-    // FIXME: Properly handle use declarations. At the moment, if our search pattern is `foo::bar`
-    // and the code is `use foo::{baz, bar}`, we'll match `bar`, since it resolves to `foo::bar`.
-    // However we'll then replace just the part we matched `bar`. We probably need to instead remove
-    // `bar` and insert a new use declaration.
-    node.kind() != SyntaxKind::USE
 }
 
 impl UsageCache {
@@ -259,22 +265,3 @@ impl UsageCache {
         None
     }
 }
-
-/// Returns a path that's suitable for path resolution. We exclude builtin types, since they aren't
-/// something that we can find references to. We then somewhat arbitrarily pick the path that is the
-/// longest as this is hopefully more likely to be less common, making it faster to find.
-fn pick_path_for_usages(pattern: &ResolvedPattern) -> Option<&ResolvedPath> {
-    // FIXME: Take the scope of the resolved path into account. e.g. if there are any paths that are
-    // private to the current module, then we definitely would want to pick them over say a path
-    // from std. Possibly we should go further than this and intersect the search scopes for all
-    // resolved paths then search only in that scope.
-    pattern
-        .resolved_paths
-        .iter()
-        .filter(|(_, p)| {
-            !matches!(p.resolution, hir::PathResolution::Def(hir::ModuleDef::BuiltinType(_)))
-        })
-        .map(|(node, resolved)| (node.text().len(), resolved))
-        .max_by(|(a, _), (b, _)| a.cmp(b))
-        .map(|(_, resolved)| resolved)
-}
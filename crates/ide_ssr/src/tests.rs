@@ -30,8 +30,8 @@ fn parser_two_delimiters() {
 #[test]
 fn parser_repeated_name() {
     assert_eq!(
-        parse_error_text("foo($a, $a) ==>>"),
-        "Parse error: Placeholder `$a` repeats more than once"
+        parse_error_text("foo($a, ${a:kind(literal)}) ==>>"),
+        "Parse error: Constraints on placeholder `$a` must be given where it is first used"
     );
 }
 
@@ -51,6 +51,14 @@ fn parser_invalid_template() {
     );
 }
 
+#[test]
+fn parser_unterminated_type_constraint() {
+    assert_eq!(
+        parse_error_text("${a:type(Vec<_> ==>> ()"),
+        "Parse error: Unexpected end of constraint while parsing type"
+    );
+}
+
 #[test]
 fn parser_undefined_placeholder_in_replacement() {
     assert_eq!(
@@ -524,6 +532,131 @@ fn literal_constraint() {
     assert_matches("Some(${a:not(kind(literal))})", code, &["Some(x1)", "Some(40 + 2)"]);
 }
 
+#[test]
+fn type_constraint() {
+    let code = r#"
+        struct Vec<T> { t: T }
+        impl<T> Vec<T> { fn len(&self) -> usize { 0 } }
+        struct Wrapper(i32);
+        fn foo<T>(t: T) {}
+        fn f(v: Vec<i32>, w: &Vec<bool>, x: Wrapper, t: (i32, bool)) {
+            foo(v);
+            foo(w);
+            foo(x);
+            foo(t);
+            foo(42u8);
+        }
+        "#;
+    assert_matches("foo(${a:type(Vec<_>)})", code, &["foo(v)"]);
+    assert_matches("foo(${a:type(Vec<i32>)})", code, &["foo(v)"]);
+    assert_matches("foo(${a:type(&Vec<bool>)})", code, &["foo(w)"]);
+    assert_matches("foo(${a:type(&mut Vec<bool>)})", code, &[]);
+    assert_matches("foo(${a:type((i32, _))})", code, &["foo(t)"]);
+    assert_matches("foo(${a:type(u8)})", code, &["foo(42u8)"]);
+    assert_matches(
+        "foo(${a:not(type(Wrapper))})",
+        code,
+        &["foo(v)", "foo(w)", "foo(t)", "foo(42u8)"],
+    );
+}
+
+#[test]
+fn type_constraint_replace() {
+    assert_ssr_transform(
+        "${x:type(Vec<_>)}.len() == 0 ==>> $x.is_empty()",
+        r#"
+        struct Vec<T> { t: T }
+        impl<T> Vec<T> { fn len(&self) -> usize { 0 } fn is_empty(&self) -> bool { true } }
+        struct Set;
+        impl Set { fn len(&self) -> usize { 0 } }
+        fn f(v: Vec<i32>, s: Set) {
+            if v.len() == 0 && s.len() == 0 {}
+        }
+        "#,
+        expect![[r#"
+            struct Vec<T> { t: T }
+            impl<T> Vec<T> { fn len(&self) -> usize { 0 } fn is_empty(&self) -> bool { true } }
+            struct Set;
+            impl Set { fn len(&self) -> usize { 0 } }
+            fn f(v: Vec<i32>, s: Set) {
+                if v.is_empty() && s.len() == 0 {}
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn impls_constraint() {
+    let code = r#"
+        mod error { pub trait Error {} }
+        struct MyError;
+        impl error::Error for MyError {}
+        struct NotAnError;
+        fn report<E>(e: E) {}
+        fn f(a: MyError, b: NotAnError) {
+            report(a);
+            report(b);
+        }
+        "#;
+    assert_matches("report(${e:impls(error::Error)})", code, &["report(a)"]);
+    assert_matches("report(${e:not(impls(error::Error))})", code, &["report(b)"]);
+}
+
+#[test]
+fn impls_constraint_with_default_type_argument() {
+    let code = r#"
+        trait PartialEq<Rhs = Self> { fn eq(&self, other: &Rhs) -> bool; }
+        struct Comparable;
+        impl PartialEq for Comparable { fn eq(&self, _: &Comparable) -> bool { true } }
+        struct NotComparable;
+        fn check<T>(t: T) {}
+        fn f(a: Comparable, b: NotComparable) {
+            check(a);
+            check(b);
+        }
+        "#;
+    assert_matches("check(${e:impls(PartialEq)})", code, &["check(a)"]);
+}
+
+#[test]
+fn impls_constraint_with_type_arguments() {
+    let code = r#"
+        trait From<T> { fn from(t: T) -> Self; }
+        struct Celsius;
+        impl From<u32> for Celsius { fn from(_: u32) -> Celsius { Celsius } }
+        struct Kelvin;
+        impl From<i64> for Kelvin { fn from(_: i64) -> Kelvin { Kelvin } }
+        fn check<T>(t: T) {}
+        fn f(a: Celsius, b: Kelvin) {
+            check(a);
+            check(b);
+        }
+        "#;
+    assert_matches("check(${e:impls(From<u32>)})", code, &["check(a)"]);
+    assert_matches("check(${e:impls(From<i64>)})", code, &["check(b)"]);
+}
+
+#[test]
+fn impls_constraint_with_missing_type_argument() {
+    let (db, position, selections) = single_file("trait From<T> {} fn foo() {}");
+    let mut match_finder = MatchFinder::in_context(&db, position, selections);
+    let err = match_finder.add_rule("foo(${a:impls(From)}) ==>> ()".parse().unwrap()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Parse error: Missing type argument `T` for trait `From` in impls constraint"
+    );
+}
+
+#[test]
+fn unresolved_constraint_types() {
+    let (db, position, selections) = single_file("struct Foo; fn foo() {}");
+    let mut match_finder = MatchFinder::in_context(&db, position, selections);
+    let err = match_finder.add_rule("foo(${a:type(Bar)}) ==>> ()".parse().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "Parse error: Failed to resolve type `Bar`");
+    let err = match_finder.add_rule("foo(${a:impls(Foo)}) ==>> ()".parse().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "Parse error: Failed to resolve trait `Foo`");
+}
+
 #[test]
 fn match_reordered_struct_instantiation() {
     assert_matches(
@@ -995,10 +1128,8 @@ fn overlapping_possible_matches() {
 
 #[test]
 fn use_declaration_with_braces() {
-    // It would be OK for a path rule to match and alter a use declaration. We shouldn't mess it up
-    // though. In particular, we must not change `use foo::{baz, bar}` to `use foo::{baz,
-    // foo2::bar2}`.
-    cov_mark::check!(use_declaration_with_braces);
+    // We must not change `use foo::{baz, bar}` to `use foo::{baz, foo2::bar2}`, since paths in use
+    // trees are relative to their prefix. Instead the use tree is moved into a use item of its own.
     assert_ssr_transform(
         "foo::bar ==>> foo2::bar2",
         r#"
@@ -1007,13 +1138,166 @@ fn use_declaration_with_braces() {
         use foo::{baz, bar};
         fn main() { bar() }
         "#,
-        expect![["
+        expect![[r#"
+            mod foo { pub fn bar() {} pub fn baz() {} }
+            mod foo2 { pub fn bar2() {} }
+            use foo::{baz};
+            use foo2::bar2;
+            fn main() { foo2::bar2() }
+        "#]],
+    );
+    assert_ssr_transform(
+        "foo::bar ==>> foo2::bar2",
+        r#"
         mod foo { pub fn bar() {} pub fn baz() {} }
         mod foo2 { pub fn bar2() {} }
-        use foo::{baz, bar};
-        fn main() { foo2::bar2() }
-        "]],
-    )
+        pub use foo::{bar as b, baz};
+        fn main() { b() }
+        "#,
+        expect![[r#"
+            mod foo { pub fn bar() {} pub fn baz() {} }
+            mod foo2 { pub fn bar2() {} }
+            pub use foo::{baz};
+            pub use foo2::bar2 as b;
+            fn main() { b() }
+        "#]],
+    );
+}
+
+#[test]
+fn use_declaration_rewritten() {
+    assert_ssr_transform(
+        "foo::bar ==>> foo2::bar2",
+        r#"
+        mod foo { pub mod bar { pub fn f() {} } }
+        mod foo2 { pub mod bar2 { pub fn f() {} } }
+        use foo::bar;
+        use foo::{bar::f};
+        fn main() { bar::f(); f(); }
+        "#,
+        expect![[r#"
+            mod foo { pub mod bar { pub fn f() {} } }
+            mod foo2 { pub mod bar2 { pub fn f() {} } }
+            use foo2::bar2;
+            use foo2::bar2::f;
+            fn main() { foo2::bar2::f(); f(); }
+        "#]],
+    );
+}
+
+#[test]
+fn match_statement_sequence() {
+    assert_ssr_transform(
+        "let $a = $b.lock().unwrap(); $a.$m($args*) ==>> $b.lock().unwrap().$m($args*)",
+        r#"
+        struct M<T>(T);
+        struct G<T>(T);
+        impl<T> M<T> { fn lock(&self) -> Option<G<T>> { None } }
+        impl<T> G<T> { fn push(&mut self, _: T) {} fn clear(&mut self) {} }
+        fn f(m: M<i32>) {
+            let g = m.lock().unwrap();
+            // comment
+            g.push(1);
+            let h = m.lock().unwrap();
+            h.clear()
+        }
+        fn g(m: M<i32>) {
+            let g = m.lock().unwrap();
+            other.push(1);
+        }
+        "#,
+        expect![[r#"
+            struct M<T>(T);
+            struct G<T>(T);
+            impl<T> M<T> { fn lock(&self) -> Option<G<T>> { None } }
+            impl<T> G<T> { fn push(&mut self, _: T) {} fn clear(&mut self) {} }
+            fn f(m: M<i32>) {
+                // comment
+                m.lock().unwrap().push(1);
+                m.lock().unwrap().clear()
+            }
+            fn g(m: M<i32>) {
+                let g = m.lock().unwrap();
+                other.push(1);
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn statement_sequence_can_be_deleted() {
+    assert_ssr_transform(
+        "drop($a); drop($a); ==>>",
+        r#"
+        fn drop<T>(_: T) {}
+        fn f() {
+            drop(1);
+            drop(1);
+            drop(1);
+            drop(2);
+        }
+        "#,
+        expect![[r#"
+            fn drop<T>(_: T) {}
+            fn f() {
+                drop(1);
+                drop(2);
+            }
+        "#]],
+    );
+}
+
+#[test]
+fn repeated_placeholder_must_match_same_code() {
+    assert_matches("$a + $a", "fn f() { let x = 1; x + x; x + 1; (x) + x; }", &["x + x"]);
+}
+
+#[test]
+fn repeated_args_placeholder() {
+    assert_ssr_transform(
+        "foo($a, $rest*) ==>> bar($rest*, $a)",
+        r#"
+        fn foo(_: i32, _: i32, _: i32) {}
+        fn bar(_: i32, _: i32, _: i32) {}
+        fn f() { foo(1, 2, 3); }
+        "#,
+        expect![[r#"
+            fn foo(_: i32, _: i32, _: i32) {}
+            fn bar(_: i32, _: i32, _: i32) {}
+            fn f() { bar(2, 3, 1); }
+        "#]],
+    );
+    assert_matches(
+        "foo($args*)",
+        "fn foo(_: i32, _: i32) {} fn f() { foo(1, 2); foo(); }",
+        &["foo(1, 2)", "foo()"],
+    );
+    assert_matches(
+        "foo(${a:kind(literal)}*)",
+        "fn foo(_: i32, _: i32) {} fn f(x: i32) { foo(1, 2); foo(1, x); }",
+        &["foo(1, 2)"],
+    );
+    // Without a following `,` or `)`, a `*` is a multiplication.
+    assert_matches("$a*$b", "fn f() { 2 * 3; }", &["2 * 3"]);
+}
+
+#[test]
+fn match_attribute() {
+    assert_ssr_transform(
+        "#[derive($a)] ==>> #[derive($a, Default)]",
+        r#"
+        #[derive(Debug, Clone)]
+        struct A;
+        #[allow(dead_code)]
+        struct B;
+        "#,
+        expect![[r##"
+            #[derive(Debug, Clone, Default)]
+            struct A;
+            #[allow(dead_code)]
+            struct B;
+        "##]],
+    );
 }
 
 #[test]
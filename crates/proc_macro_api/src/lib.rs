@@ -11,9 +11,13 @@ mod version;
 
 use paths::AbsPathBuf;
 use std::{
-    ffi::OsStr,
+    ffi::{OsStr, OsString},
     fmt, io,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
 };
 
 use serde::{Deserialize, Serialize};
@@ -33,17 +37,35 @@ pub enum ProcMacroKind {
     Attr,
 }
 
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProcMacroServerConfig {
+    /// How long a single request may take. A server which doesn't respond in
+    /// time is killed, and restarted for the next request.
+    pub timeout: Option<Duration>,
+    /// Load every dylib in a separate server process, so that a misbehaving
+    /// proc macro can only affect the macros of its own crate.
+    pub process_per_dylib: bool,
+}
+
 /// A handle to an external process which load dylibs with macros (.so or .dll)
 /// and runs actual macro expansion functions.
 #[derive(Debug)]
 pub struct ProcMacroServer {
+    process_path: AbsPathBuf,
+    args: Vec<OsString>,
+    config: ProcMacroServerConfig,
     /// Currently, the proc macro process expands all procedural macros sequentially.
     ///
     /// That means that concurrent salsa requests may block each other when expanding proc macros,
     /// which is unfortunate, but simple and good enough for the time being.
     ///
     /// Therefore, we just wrap the `ProcMacroProcessSrv` in a mutex here.
+    ///
+    /// If every dylib gets its own process, this one goes to the first dylib.
     process: Arc<Mutex<ProcMacroProcessSrv>>,
+    /// Whether `process` was handed out to a dylib already, only used with
+    /// `process_per_dylib`.
+    process_taken: AtomicBool,
 }
 
 pub struct MacroDylib {
@@ -68,8 +90,8 @@ impl MacroDylib {
 
 /// A handle to a specific macro (a `#[proc_macro]` annotated function).
 ///
-/// It exists withing a context of a specific [`ProcMacroProcess`] -- currently
-/// we share a single expander process for all macros.
+/// It exists withing a context of a specific [`ProcMacroProcess`] -- unless
+/// configured otherwise, we share a single expander process for all macros.
 #[derive(Debug, Clone)]
 pub struct ProcMacro {
     process: Arc<Mutex<ProcMacroProcessSrv>>,
@@ -112,9 +134,52 @@ impl ProcMacroServer {
     pub fn spawn(
         process_path: AbsPathBuf,
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        config: ProcMacroServerConfig,
+    ) -> io::Result<ProcMacroServer> {
+        let args: Vec<OsString> = args.into_iter().map(|s| s.as_ref().into()).collect();
+        ProcMacroServer::run(process_path, args, config, true)
+    }
+
+    /// Spawns the server shipped with a toolchain, `toolchain_srv`, if there
+    /// is one, and the server at `process_path` if that fails. Unlike the
+    /// latter, the toolchain's server has to answer the version check.
+    pub fn spawn_preferring(
+        toolchain_srv: Option<AbsPathBuf>,
+        process_path: AbsPathBuf,
+        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        config: ProcMacroServerConfig,
     ) -> io::Result<ProcMacroServer> {
-        let process = ProcMacroProcessSrv::run(process_path, args)?;
-        Ok(ProcMacroServer { process: Arc::new(Mutex::new(process)) })
+        if let Some(toolchain_srv) = toolchain_srv {
+            match ProcMacroServer::run(toolchain_srv.clone(), Vec::new(), config.clone(), false) {
+                Ok(it) => return Ok(it),
+                Err(err) => tracing::warn!(
+                    "Failed to run proc_macro_srv from sysroot {}, falling back to {}: {}",
+                    toolchain_srv.display(),
+                    process_path.display(),
+                    err
+                ),
+            }
+        }
+        ProcMacroServer::spawn(process_path, args, config)
+    }
+
+    fn run(
+        process_path: AbsPathBuf,
+        args: Vec<OsString>,
+        config: ProcMacroServerConfig,
+        allow_version_0: bool,
+    ) -> io::Result<ProcMacroServer> {
+        // Start one process up front, so that a server which can't be spawned
+        // is reported right away. Its version check covers all later ones.
+        let process =
+            ProcMacroProcessSrv::run(process_path.clone(), &args, config.timeout, allow_version_0)?;
+        Ok(ProcMacroServer {
+            process_path,
+            args,
+            config,
+            process: Arc::new(Mutex::new(process)),
+            process_taken: AtomicBool::new(false),
+        })
     }
 
     pub fn load_dylib(
@@ -122,14 +187,30 @@ impl ProcMacroServer {
         dylib: MacroDylib,
     ) -> Result<Result<Vec<ProcMacro>, String>, ServerError> {
         let _p = profile::span("ProcMacroClient::by_dylib_path");
+        let process = if !self.config.process_per_dylib
+            || !self.process_taken.swap(true, Ordering::Relaxed)
+        {
+            self.process.clone()
+        } else {
+            let process = ProcMacroProcessSrv::spawn(
+                self.process_path.clone(),
+                &self.args,
+                self.config.timeout,
+            )
+            .map_err(|err| ServerError {
+                message: "failed to spawn proc-macro server".into(),
+                io: Some(err),
+            })?;
+            Arc::new(Mutex::new(process))
+        };
         let macros =
-            self.process.lock().unwrap_or_else(|e| e.into_inner()).find_proc_macros(&dylib.path)?;
+            process.lock().unwrap_or_else(|e| e.into_inner()).find_proc_macros(&dylib.path)?;
 
         let res = macros.map(|macros| {
             macros
                 .into_iter()
                 .map(|(name, kind)| ProcMacro {
-                    process: self.process.clone(),
+                    process: process.clone(),
                     name,
                     kind,
                     dylib_path: dylib.path.clone(),
@@ -164,11 +245,29 @@ impl ProcMacro {
             env,
         };
 
+        let mut process = self.process.lock().unwrap_or_else(|e| e.into_inner());
+        if process.has_timed_out(&self.dylib_path, &self.name) {
+            return Err(ServerError {
+                message: format!(
+                    "proc-macro `{}` timed out before and is not expanded again",
+                    self.name
+                ),
+                io: None,
+            });
+        }
         let request = msg::Request::ExpandMacro(task);
-        let response = self.process.lock().unwrap_or_else(|e| e.into_inner()).send_task(request)?;
+        let response = process
+            .send_task(request)
+            .map_err(|err| ServerError {
+                message: format!(
+                    "proc-macro `{}` crashed or hung the proc-macro server, which will be restarted ({})",
+                    self.name, err.message
+                ),
+                io: err.io,
+            })?;
         match response {
             msg::Response::ExpandMacro(it) => Ok(it.map(|it| it.to_subtree())),
-            msg::Response::ListMacros { .. } => {
+            msg::Response::ListMacros { .. } | msg::Response::ApiVersionCheck(_) => {
                 Err(ServerError { message: "unexpected response".to_string(), io: None })
             }
         }
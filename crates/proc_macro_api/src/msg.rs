@@ -12,16 +12,26 @@ use crate::ProcMacroKind;
 
 pub use crate::msg::flat::FlatTree;
 
+/// Version of the protocol spoken by this client.
+///
+/// Servers answer `ApiVersionCheck` with the version they speak. Servers which
+/// speak a newer version are rejected, as their requests and responses don't
+/// match the ones of this client. Servers from before the handshake don't
+/// answer it and count as version 0.
+pub const CURRENT_API_VERSION: u32 = 1;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Request {
     ListMacros { dylib_path: PathBuf },
     ExpandMacro(ExpandMacro),
+    ApiVersionCheck {},
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
     ListMacros(Result<Vec<(String, ProcMacroKind)>, String>),
     ExpandMacro(Result<FlatTree, PanicMessage>),
+    ApiVersionCheck(u32),
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1,39 +1,111 @@
 //! Handle process life-time and message passing for proc-macro client
 
 use std::{
+    collections::HashSet,
     ffi::{OsStr, OsString},
-    io::{self, BufRead, BufReader, Write},
+    io::{self, BufReader},
+    path::PathBuf,
     process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::Duration,
 };
 
 use paths::{AbsPath, AbsPathBuf};
 use stdx::JodChild;
 
 use crate::{
-    msg::{Message, Request, Response},
+    msg::{Message, Request, Response, CURRENT_API_VERSION},
     ProcMacroKind, ServerError,
 };
 
+/// A proc-macro server process, which is transparently restarted if it
+/// crashes or gets stuck.
 #[derive(Debug)]
 pub(crate) struct ProcMacroProcessSrv {
-    _process: Process,
-    stdin: ChildStdin,
-    stdout: BufReader<ChildStdout>,
+    process_path: AbsPathBuf,
+    args: Vec<OsString>,
+    timeout: Option<Duration>,
+    /// Dylibs loaded so far, which have to be loaded again after a restart.
+    dylibs: Vec<AbsPathBuf>,
+    /// Macros which ran into the timeout, as `(dylib, name)`. They are not
+    /// expanded again, so that a macro which always hangs doesn't stall every
+    /// expansion for the full timeout.
+    timed_out: HashSet<(PathBuf, String)>,
+    /// `None` if the process died and wasn't restarted yet.
+    running: Option<Running>,
 }
 
 impl ProcMacroProcessSrv {
+    /// Spawns the server and checks which protocol version it speaks. Unless
+    /// `allow_version_0` is set, a server which doesn't answer the version
+    /// check is rejected instead of taken as one from before the handshake.
     pub(crate) fn run(
         process_path: AbsPathBuf,
         args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        timeout: Option<Duration>,
+        allow_version_0: bool,
     ) -> io::Result<ProcMacroProcessSrv> {
-        let mut process = Process::run(process_path, args)?;
-        let (stdin, stdout) = process.stdio().expect("couldn't access child stdio");
-
-        let srv = ProcMacroProcessSrv { _process: process, stdin, stdout };
-
+        let mut srv = ProcMacroProcessSrv::spawn(process_path, args, timeout)?;
+        let running = srv.running.as_mut().unwrap();
+        let version = match running.version_check(timeout) {
+            Ok(version) => version,
+            Err(err) if !allow_version_0 => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("proc-macro server didn't answer the version check: {}", err),
+                ));
+            }
+            Err(err) => {
+                // Servers from before the handshake exit on the unknown
+                // request, but otherwise speak the same protocol.
+                tracing::info!(
+                    "proc-macro server {} didn't answer the version check ({}), assuming version 0",
+                    srv.process_path.display(),
+                    err
+                );
+                srv.running = None;
+                srv.restart()
+                    .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+                0
+            }
+        };
+        if version > CURRENT_API_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "proc-macro server speaks protocol version {}, newer than the supported {}",
+                    version, CURRENT_API_VERSION
+                ),
+            ));
+        }
+        tracing::info!(
+            "proc-macro server {} speaks protocol version {}",
+            srv.process_path.display(),
+            version
+        );
         Ok(srv)
     }
 
+    /// Spawns the server without the version check, for servers which were
+    /// checked by [`ProcMacroProcessSrv::run`] already.
+    pub(crate) fn spawn(
+        process_path: AbsPathBuf,
+        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
+        timeout: Option<Duration>,
+    ) -> io::Result<ProcMacroProcessSrv> {
+        let args: Vec<OsString> = args.into_iter().map(|s| s.as_ref().into()).collect();
+        let running = Running::spawn(&process_path, &args)?;
+        Ok(ProcMacroProcessSrv {
+            process_path,
+            args,
+            timeout,
+            dylibs: Vec::new(),
+            timed_out: HashSet::default(),
+            running: Some(running),
+        })
+    }
+
     pub(crate) fn find_proc_macros(
         &mut self,
         dylib_path: &AbsPath,
@@ -43,31 +115,147 @@ impl ProcMacroProcessSrv {
         let response = self.send_task(request)?;
 
         match response {
-            Response::ListMacros(it) => Ok(it),
-            Response::ExpandMacro { .. } => {
+            Response::ListMacros(it) => {
+                if it.is_ok() && !self.dylibs.iter().any(|it| it == dylib_path) {
+                    self.dylibs.push(dylib_path.to_path_buf());
+                }
+                Ok(it)
+            }
+            Response::ExpandMacro { .. } | Response::ApiVersionCheck(_) => {
                 Err(ServerError { message: "unexpected response".to_string(), io: None })
             }
         }
     }
 
+    /// Whether expanding `macro_name` from `dylib_path` ran into the timeout
+    /// before.
+    pub(crate) fn has_timed_out(&self, dylib_path: &AbsPath, macro_name: &str) -> bool {
+        self.timed_out.contains(&(dylib_path.to_path_buf().into(), macro_name.to_string()))
+    }
+
     pub(crate) fn send_task(&mut self, req: Request) -> Result<Response, ServerError> {
-        let mut buf = String::new();
-        send_request(&mut self.stdin, &mut self.stdout, req, &mut buf)
+        let expanded_macro = match &req {
+            Request::ExpandMacro(task) => Some((task.lib.clone(), task.macro_name.clone())),
+            Request::ListMacros { .. } | Request::ApiVersionCheck {} => None,
+        };
+        if self.running.is_none() {
+            self.restart()?;
+        }
+        let running = self.running.as_mut().unwrap();
+        let res = running.send_request(req, self.timeout);
+        if res.is_err() {
+            if running.timed_out {
+                if let Some(key) = expanded_macro {
+                    self.timed_out.insert(key);
+                }
+            }
+            // Either the process is gone, or it's stuck and we are about to
+            // kill it. In both cases, the next request starts a new one.
+            self.running = None;
+        }
+        res
+    }
+
+    fn restart(&mut self) -> Result<(), ServerError> {
+        tracing::info!("restarting proc-macro server {}", self.process_path.display());
+        let mut running = Running::spawn(&self.process_path, &self.args).map_err(|err| {
+            ServerError { message: "failed to restart proc-macro server".into(), io: Some(err) }
+        })?;
+        for dylib_path in &self.dylibs {
+            let request = Request::ListMacros { dylib_path: dylib_path.to_path_buf().into() };
+            running.send_request(request, self.timeout)?;
+        }
+        self.running = Some(running);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Running {
+    _process: Process,
+    stdin: ChildStdin,
+    /// Responses are read on a separate thread, so that we can stop waiting
+    /// for a stuck process.
+    responses: Receiver<io::Result<Response>>,
+    /// Whether the last request ran into the timeout.
+    timed_out: bool,
+}
+
+impl Running {
+    fn spawn(process_path: &AbsPath, args: &[OsString]) -> io::Result<Running> {
+        let mut process = Process::run(process_path, args)?;
+        let (stdin, stdout) = process.stdio().expect("couldn't access child stdio");
+        let responses = spawn_reader(stdout)?;
+        Ok(Running { _process: process, stdin, responses, timed_out: false })
+    }
+
+    fn version_check(&mut self, timeout: Option<Duration>) -> Result<u32, ServerError> {
+        match self.send_request(Request::ApiVersionCheck {}, timeout)? {
+            Response::ApiVersionCheck(version) => Ok(version),
+            _ => Err(ServerError { message: "unexpected response".to_string(), io: None }),
+        }
+    }
+
+    fn send_request(
+        &mut self,
+        req: Request,
+        timeout: Option<Duration>,
+    ) -> Result<Response, ServerError> {
+        req.write(&mut self.stdin).map_err(|err| ServerError {
+            message: "failed to write request".into(),
+            io: Some(err),
+        })?;
+        let res = match timeout {
+            Some(timeout) => self.responses.recv_timeout(timeout),
+            None => self.responses.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match res {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(err)) => {
+                Err(ServerError { message: "failed to read response".into(), io: Some(err) })
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                self.timed_out = true;
+                Err(ServerError {
+                    message: format!("timed out after {:?}", timeout.unwrap_or_default()),
+                    io: None,
+                })
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(ServerError { message: "server exited".into(), io: None })
+            }
+        }
     }
 }
 
+fn spawn_reader(stdout: BufReader<ChildStdout>) -> io::Result<Receiver<io::Result<Response>>> {
+    let (sender, receiver) = mpsc::channel();
+    thread::Builder::new().name("ProcMacroServerReader".to_owned()).spawn(move || {
+        let mut stdout = stdout;
+        let mut buf = String::new();
+        loop {
+            let res = match Response::read(&mut stdout, &mut buf) {
+                Ok(Some(response)) => Ok(response),
+                Ok(None) => break,
+                Err(err) => Err(err),
+            };
+            let failed = res.is_err();
+            if sender.send(res).is_err() || failed {
+                break;
+            }
+        }
+    })?;
+    Ok(receiver)
+}
+
 #[derive(Debug)]
 struct Process {
     child: JodChild,
 }
 
 impl Process {
-    fn run(
-        path: AbsPathBuf,
-        args: impl IntoIterator<Item = impl AsRef<OsStr>>,
-    ) -> io::Result<Process> {
-        let args: Vec<OsString> = args.into_iter().map(|s| s.as_ref().into()).collect();
-        let child = JodChild(mk_child(&path, &args)?);
+    fn run(path: &AbsPath, args: &[OsString]) -> io::Result<Process> {
+        let child = JodChild(mk_child(path, args)?);
         Ok(Process { child })
     }
 
@@ -85,6 +273,8 @@ fn mk_child(
     args: impl IntoIterator<Item = impl AsRef<OsStr>>,
 ) -> io::Result<Child> {
     Command::new(path.as_os_str())
+        // Servers shipped with a toolchain refuse to start without it.
+        .env("RUST_ANALYZER_INTERNALS_DO_NOT_USE", "this is unstable")
         .args(args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
@@ -92,15 +282,150 @@ fn mk_child(
         .spawn()
 }
 
-fn send_request(
-    mut writer: &mut impl Write,
-    mut reader: &mut impl BufRead,
-    req: Request,
-    buf: &mut String,
-) -> Result<Response, ServerError> {
-    req.write(&mut writer)
-        .map_err(|err| ServerError { message: "failed to write request".into(), io: Some(err) })?;
-    let res = Response::read(&mut reader, buf)
-        .map_err(|err| ServerError { message: "failed to read response".into(), io: Some(err) })?;
-    res.ok_or_else(|| ServerError { message: "server exited".into(), io: None })
+#[cfg(test)]
+#[cfg(unix)]
+mod tests {
+    use crate::{ProcMacroServer, ProcMacroServerConfig};
+
+    use super::*;
+
+    /// A fake server which answers the version check with `version` and then
+    /// runs `script`.
+    fn shell(
+        version: u32,
+        script: &str,
+        timeout: Option<Duration>,
+    ) -> io::Result<ProcMacroProcessSrv> {
+        let script = format!("read line; echo '{{\"ApiVersionCheck\":{}}}'; {}", version, script);
+        ProcMacroProcessSrv::run(
+            AbsPathBuf::assert("/bin/sh".into()),
+            ["-c", &script],
+            timeout,
+            false,
+        )
+    }
+
+    fn list_macros() -> Request {
+        Request::ListMacros { dylib_path: "/does/not/exist.so".into() }
+    }
+
+    #[test]
+    fn stuck_server_times_out_and_restarts() {
+        let mut srv = shell(1, "sleep 60", Some(Duration::from_millis(100))).unwrap();
+        let err = srv.send_task(list_macros()).unwrap_err();
+        assert_eq!(err.message, "timed out after 100ms");
+        assert!(srv.running.is_none());
+
+        // The next request gets a fresh process, without another version
+        // check, so the fake server's canned answer goes to the request.
+        assert!(matches!(srv.send_task(list_macros()), Ok(Response::ApiVersionCheck(1))));
+        assert!(srv.running.is_some());
+    }
+
+    #[test]
+    fn macro_which_timed_out_is_remembered() {
+        let mut srv = shell(1, "sleep 60", Some(Duration::from_millis(100))).unwrap();
+        let dylib = AbsPathBuf::assert("/does/not/exist.so".into());
+        let task = crate::msg::ExpandMacro {
+            macro_body: crate::msg::FlatTree::new(&tt::Subtree::default()),
+            macro_name: "hangs".into(),
+            attributes: None,
+            lib: dylib.to_path_buf().into(),
+            env: Vec::new(),
+        };
+        assert!(!srv.has_timed_out(&dylib, "hangs"));
+        assert!(srv.send_task(Request::ExpandMacro(task)).is_err());
+        assert!(srv.has_timed_out(&dylib, "hangs"));
+        assert!(!srv.has_timed_out(&dylib, "other"));
+    }
+
+    #[test]
+    fn crashed_server_restarts() {
+        let mut srv = shell(1, "exit 1", None).unwrap();
+        assert!(srv.send_task(list_macros()).is_err());
+        assert!(srv.running.is_none());
+
+        assert!(srv.restart().is_ok());
+        assert!(srv.running.is_some());
+    }
+
+    #[test]
+    fn newer_server_is_rejected() {
+        assert!(shell(CURRENT_API_VERSION, "sleep 60", None).is_ok());
+        assert!(shell(CURRENT_API_VERSION + 1, "sleep 60", None).is_err());
+    }
+
+    #[test]
+    fn server_without_version_check_falls_back_to_version_0() {
+        // Like a server from before the handshake, this one dies on the
+        // version check, so it has to be started again.
+        let run = |allow_version_0| {
+            ProcMacroProcessSrv::run(
+                AbsPathBuf::assert("/bin/sh".into()),
+                ["-c", "read line; exit 1"],
+                None,
+                allow_version_0,
+            )
+        };
+        assert!(run(true).unwrap().running.is_some());
+        assert!(run(false).is_err());
+    }
+
+    #[test]
+    fn toolchain_server_which_exits_falls_back() {
+        let toolchain_srv = AbsPathBuf::assert(
+            std::env::temp_dir().join(format!("ra-fake-toolchain-srv-{}", std::process::id())),
+        );
+        // Like a toolchain server started without the environment variable
+        // it expects, this one exits without answering.
+        std::fs::write(&toolchain_srv, "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::set_permissions(
+            &toolchain_srv,
+            std::os::unix::fs::PermissionsExt::from_mode(0o755),
+        )
+        .unwrap();
+        let bundled = AbsPathBuf::assert("/bin/sh".into());
+        let script =
+            format!("read line; echo '{{\"ApiVersionCheck\":{}}}'; sleep 60", CURRENT_API_VERSION);
+
+        let srv = ProcMacroServer::spawn_preferring(
+            Some(toolchain_srv.clone()),
+            bundled.clone(),
+            ["-c", &script],
+            ProcMacroServerConfig::default(),
+        )
+        .unwrap();
+        assert_eq!(srv.process_path, bundled);
+
+        std::fs::remove_file(toolchain_srv).unwrap();
+    }
+
+    #[test]
+    fn toolchain_server_gets_its_environment_variable() {
+        let script = format!(
+            "test -n \"$RUST_ANALYZER_INTERNALS_DO_NOT_USE\" || exit 1; {}",
+            format_args!(
+                "read line; echo '{{\"ApiVersionCheck\":{}}}'; sleep 60",
+                CURRENT_API_VERSION
+            )
+        );
+        let srv = ProcMacroProcessSrv::run(
+            AbsPathBuf::assert("/bin/sh".into()),
+            ["-c", &script],
+            None,
+            false,
+        );
+        assert!(srv.is_ok());
+    }
+
+    #[test]
+    fn missing_server_is_rejected() {
+        let srv = ProcMacroProcessSrv::run(
+            AbsPathBuf::assert("/does/not/exist".into()),
+            Vec::<OsString>::new(),
+            None,
+            true,
+        );
+        assert!(srv.is_err());
+    }
 }
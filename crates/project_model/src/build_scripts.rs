@@ -7,18 +7,20 @@
 //! here, but it covers procedural macros as well.
 
 use std::{
+    fs, io,
     path::PathBuf,
     process::{Command, Stdio},
+    time::SystemTime,
 };
 
 use anyhow::Result;
 use cargo_metadata::{camino::Utf8Path, Message};
 use la_arena::ArenaMap;
-use paths::AbsPathBuf;
+use paths::{AbsPath, AbsPathBuf};
 use rustc_hash::FxHashMap;
 use serde::Deserialize;
 
-use crate::{cfg_flag::CfgFlag, CargoConfig, CargoWorkspace, Package};
+use crate::{cfg_flag::CfgFlag, CargoConfig, CargoWorkspace, Package, TargetKind};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct WorkspaceBuildScripts {
@@ -41,12 +43,41 @@ pub(crate) struct BuildScriptOutput {
     pub(crate) proc_macro_dylib_path: Option<AbsPathBuf>,
 }
 
+/// The shape of a single entry in a build script outputs file, which maps
+/// package ids (or just names) to these. Relative paths are resolved against
+/// the directory of the file.
+#[derive(Deserialize)]
+struct BuildScriptOutputData {
+    #[serde(default)]
+    cfgs: Vec<CfgFlag>,
+    #[serde(default)]
+    envs: Vec<(String, String)>,
+    out_dir: Option<PathBuf>,
+    proc_macro_dylib_path: Option<PathBuf>,
+}
+
 impl WorkspaceBuildScripts {
     pub(crate) fn run(
         config: &CargoConfig,
         workspace: &CargoWorkspace,
         progress: &dyn Fn(String),
     ) -> Result<WorkspaceBuildScripts> {
+        if let Some(path) = &config.build_script_outputs {
+            match WorkspaceBuildScripts::from_file(path, workspace) {
+                Ok(Some(res)) => return Ok(res),
+                Ok(None) => {
+                    tracing::info!("{} is missing or stale, running cargo check", path.display())
+                }
+                Err(err) => {
+                    tracing::warn!(
+                        "failed to load build script outputs {}: {}",
+                        path.display(),
+                        err
+                    )
+                }
+            }
+        }
+
         let mut cmd = Command::new(toolchain::cargo());
 
         if config.wrap_rustc_in_build_scripts {
@@ -177,14 +208,8 @@ impl WorkspaceBuildScripts {
         )?;
 
         for package in workspace.packages() {
-            let package_build_data = &mut res.outputs[package];
             // inject_cargo_env(package, package_build_data);
-            if let Some(out_dir) = &package_build_data.out_dir {
-                // NOTE: cargo and rustc seem to hide non-UTF-8 strings from env! and option_env!()
-                if let Some(out_dir) = out_dir.as_os_str().to_str().map(|s| s.to_owned()) {
-                    package_build_data.envs.push(("OUT_DIR".to_string(), out_dir));
-                }
-            }
+            inject_out_dir_env(&mut res.outputs[package]);
         }
 
         if !output.status.success() {
@@ -197,11 +222,87 @@ impl WorkspaceBuildScripts {
         Ok(res)
     }
 
+    /// Loads pre-computed build script outputs, as produced by an external
+    /// build system.
+    ///
+    /// Returns `None` if the file doesn't exist, if it is older than the
+    /// lockfile or a manifest or build script of a local package, or if it
+    /// lacks the outputs of a package with a build script or proc-macros.
+    pub(crate) fn from_file(
+        path: &AbsPath,
+        workspace: &CargoWorkspace,
+    ) -> Result<Option<WorkspaceBuildScripts>> {
+        let modified = match fs::metadata(path).and_then(|it| it.modified()) {
+            Ok(it) => it,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let newer_than_file =
+            |path: &AbsPath| mtime(path).map_or(false, |it: SystemTime| it > modified);
+        if newer_than_file(&workspace.workspace_root().join("Cargo.lock")) {
+            return Ok(None);
+        }
+
+        let text = fs::read_to_string(path)?;
+        let mut data: FxHashMap<String, BuildScriptOutputData> = serde_json::from_str(&text)?;
+        let base = path.parent().unwrap_or(path);
+
+        let mut res = WorkspaceBuildScripts::default();
+        for package in workspace.packages() {
+            let package_data = &workspace[package];
+            let mut needs_outputs = false;
+            for &target in &package_data.targets {
+                let target = &workspace[target];
+                if target.kind == TargetKind::BuildScript {
+                    needs_outputs = true;
+                    if package_data.is_local && newer_than_file(&target.root) {
+                        return Ok(None);
+                    }
+                }
+                needs_outputs |= target.is_proc_macro;
+            }
+            if package_data.is_local && newer_than_file(&package_data.manifest) {
+                return Ok(None);
+            }
+
+            let data = match data.remove(&package_data.id) {
+                Some(it) => Some(it),
+                None => data.remove(&package_data.name),
+            };
+            let mut output = match data {
+                Some(data) => BuildScriptOutput {
+                    cfgs: data.cfgs,
+                    envs: data.envs,
+                    out_dir: data.out_dir.map(|it| base.join(it)),
+                    proc_macro_dylib_path: data.proc_macro_dylib_path.map(|it| base.join(it)),
+                },
+                None if needs_outputs => return Ok(None),
+                None => BuildScriptOutput::default(),
+            };
+            inject_out_dir_env(&mut output);
+            res.outputs.insert(package, output);
+        }
+        Ok(Some(res))
+    }
+
     pub fn error(&self) -> Option<&str> {
         self.error.as_deref()
     }
 }
 
+fn inject_out_dir_env(output: &mut BuildScriptOutput) {
+    if let Some(out_dir) = &output.out_dir {
+        // NOTE: cargo and rustc seem to hide non-UTF-8 strings from env! and option_env!()
+        if let Some(out_dir) = out_dir.as_os_str().to_str().map(|s| s.to_owned()) {
+            output.envs.push(("OUT_DIR".to_string(), out_dir));
+        }
+    }
+}
+
+fn mtime(path: &AbsPath) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|it| it.modified()).ok()
+}
+
 // FIXME: File a better way to know if it is a dylib.
 fn is_dylib(path: &Utf8Path) -> bool {
     match path.extension().map(|e| e.to_string().to_lowercase()) {
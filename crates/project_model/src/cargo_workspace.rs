@@ -85,6 +85,10 @@ pub struct CargoConfig {
     /// rustc target
     pub target: Option<String>,
 
+    /// Additional rustc targets to analyze the workspace for, on top of
+    /// `target`.
+    pub extra_targets: Vec<String>,
+
     /// Don't load sysroot crates (`std`, `core` & friends). Might be useful
     /// when debugging isolated issues.
     pub no_sysroot: bool,
@@ -96,6 +100,10 @@ pub struct CargoConfig {
     pub unset_test_crates: UnsetTestCrates,
 
     pub wrap_rustc_in_build_scripts: bool,
+
+    /// Pre-computed build script outputs to use instead of running
+    /// `cargo check`, as long as they are up to date.
+    pub build_script_outputs: Option<AbsPathBuf>,
 }
 
 impl CargoConfig {
@@ -406,6 +414,31 @@ impl CargoWorkspace {
         }
     }
 
+    /// Returns the workspace member owning `path`, together with all members
+    /// depending on it, directly or transitively. Empty if `path` doesn't
+    /// belong to a member.
+    pub fn affected_members(&self, path: &AbsPath) -> Vec<Package> {
+        let owner = self
+            .packages()
+            .filter(|&pkg| self[pkg].is_member && path.starts_with(self[pkg].manifest.parent()))
+            .max_by_key(|&pkg| self[pkg].manifest.parent().as_ref().as_os_str().len());
+        let mut res: Vec<Package> = owner.into_iter().collect();
+        let mut i = 0;
+        while i < res.len() {
+            let pkg = res[i];
+            for rdep in self.packages() {
+                if self[rdep].is_member
+                    && !res.contains(&rdep)
+                    && self[rdep].dependencies.iter().any(|dep| dep.pkg == pkg)
+                {
+                    res.push(rdep);
+                }
+            }
+            i += 1;
+        }
+        res
+    }
+
     pub fn parent_manifests(&self, manifest_path: &ManifestPath) -> Option<Vec<ManifestPath>> {
         let mut found = false;
         let parent_manifests = self
@@ -5,7 +5,7 @@ use std::str::FromStr;
 
 use cfg::CfgOptions;
 
-#[derive(Clone, Eq, PartialEq, Debug)]
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
 pub enum CfgFlag {
     Atom(String),
     KeyValue { key: String, value: String },
@@ -44,7 +44,7 @@ pub use crate::{
         TargetData, TargetKind, UnsetTestCrates,
     },
     manifest_path::ManifestPath,
-    project_json::{ProjectJson, ProjectJsonData},
+    project_json::{Build, ProjectJson, ProjectJsonData, Runnable, RunnableKind},
     sysroot::Sysroot,
     workspace::{CfgOverrides, PackageRoot, ProjectWorkspace},
 };
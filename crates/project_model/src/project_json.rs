@@ -5,14 +5,15 @@
 //! system to generate `rust-project.json` which can be ingested by
 //! rust-analyzer.
 
-use std::path::PathBuf;
+use std::{path::PathBuf, process::Command};
 
+use anyhow::{Context, Result};
 use base_db::{CrateDisplayName, CrateId, CrateName, Dependency, Edition};
 use paths::{AbsPath, AbsPathBuf};
 use rustc_hash::FxHashMap;
 use serde::{de, Deserialize};
 
-use crate::cfg_flag::CfgFlag;
+use crate::{cfg_flag::CfgFlag, utf8_stdout};
 
 /// Roots and crates that compose this Rust project.
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -38,6 +39,35 @@ pub struct Crate {
     pub(crate) include: Vec<AbsPathBuf>,
     pub(crate) exclude: Vec<AbsPathBuf>,
     pub(crate) is_proc_macro: bool,
+    pub(crate) build: Option<Build>,
+}
+
+/// How the build system refers to a crate, and how to run it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Build {
+    /// The build system's identifier of the target, like `//foo:bar` for Bazel.
+    pub label: String,
+    pub runnables: Vec<Runnable>,
+}
+
+/// A command template for running a crate.
+///
+/// `{label}` in the arguments is replaced with the build label, `{test_id}`
+/// with the path of the test, test module or benchmark to run.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Runnable {
+    pub kind: RunnableKind,
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: AbsPathBuf,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunnableKind {
+    Run,
+    Test,
+    Bench,
 }
 
 impl ProjectJson {
@@ -50,7 +80,13 @@ impl ProjectJson {
     ///            configuration.
     pub fn new(base: &AbsPath, data: ProjectJsonData) -> ProjectJson {
         ProjectJson {
-            sysroot_src: data.sysroot_src.map(|it| base.join(it)),
+            sysroot_src: match (data.sysroot_src, data.sysroot) {
+                (Some(sysroot_src), _) => Some(base.join(sysroot_src)),
+                (None, Some(sysroot)) => {
+                    Some(base.join(sysroot).join("lib/rustlib/src/rust/library"))
+                }
+                (None, None) => None,
+            },
             project_root: base.to_path_buf(),
             crates: data
                 .crates
@@ -97,11 +133,87 @@ impl ProjectJson {
                         include,
                         exclude,
                         is_proc_macro: crate_data.is_proc_macro,
+                        build: crate_data.build.map(|build| Build {
+                            label: build.label,
+                            runnables: build
+                                .runnables
+                                .into_iter()
+                                .map(|runnable| Runnable {
+                                    kind: runnable.kind,
+                                    program: runnable.program,
+                                    args: runnable.args,
+                                    cwd: match runnable.cwd {
+                                        Some(cwd) => base.join(cwd),
+                                        None => base.to_path_buf(),
+                                    },
+                                })
+                                .collect(),
+                        }),
                     }
                 })
                 .collect::<Vec<_>>(),
         }
     }
+    /// Runs a workspace discovery command for `file`, which is passed as the
+    /// last argument.
+    ///
+    /// The command is expected to print the `rust-project.json` of the build
+    /// target owning `file` to stdout. It runs in `base`, which is also what
+    /// relative paths in its output are relative to.
+    pub fn discover(command: &[String], base: &AbsPath, file: &AbsPath) -> Result<ProjectJson> {
+        let (program, args) = command.split_first().context("empty discovery command")?;
+        let mut cmd = Command::new(program);
+        cmd.args(args).arg(file.as_os_str()).current_dir(base);
+        let stdout = utf8_stdout(cmd)?;
+        let data = serde_json::from_str::<ProjectJsonData>(&stdout)
+            .with_context(|| format!("invalid output of {:?}", command))?;
+        Ok(ProjectJson::new(base, data))
+    }
+    /// Adds the crates of `other` to this project. Crates that are in both,
+    /// that is crates with the same root module, cfgs and target, are only
+    /// kept once.
+    pub fn merge(&mut self, other: ProjectJson) {
+        let key =
+            |krate: &Crate| (krate.root_module.clone(), krate.cfg.clone(), krate.target.clone());
+        let existing = self
+            .crates
+            .iter()
+            .enumerate()
+            .map(|(idx, krate)| (key(krate), idx))
+            .collect::<FxHashMap<_, _>>();
+
+        let mut next_idx = self.crates.len();
+        let ids = other
+            .crates
+            .iter()
+            .map(|krate| match existing.get(&key(krate)) {
+                Some(&idx) => idx,
+                None => {
+                    next_idx += 1;
+                    next_idx - 1
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for (krate, idx) in other.crates.into_iter().zip(ids.iter().copied()) {
+            if idx < self.crates.len() {
+                self.crates[idx].is_workspace_member |= krate.is_workspace_member;
+                continue;
+            }
+            let deps = krate
+                .deps
+                .iter()
+                .filter_map(|dep| {
+                    let crate_id = CrateId(*ids.get(dep.crate_id.0 as usize)? as u32);
+                    Some(Dependency::with_prelude(dep.name.clone(), crate_id, dep.is_prelude()))
+                })
+                .collect();
+            self.crates.push(Crate { deps, ..krate });
+        }
+        if self.sysroot_src.is_none() {
+            self.sysroot_src = other.sysroot_src;
+        }
+    }
     /// Returns the number of crates in the project.
     pub fn n_crates(&self) -> usize {
         self.crates.len()
@@ -116,8 +228,31 @@ impl ProjectJson {
     }
 }
 
+impl Crate {
+    /// Returns the path to the root module of the crate.
+    pub fn root_module(&self) -> &AbsPath {
+        &self.root_module
+    }
+    /// Returns whether the crate is part of the project, rather than a
+    /// dependency of it.
+    pub fn is_workspace_member(&self) -> bool {
+        self.is_workspace_member
+    }
+    pub fn build(&self) -> Option<&Build> {
+        self.build.as_ref()
+    }
+}
+
+impl Build {
+    /// Returns the template for runnables of the given kind, if any.
+    pub fn runnable(&self, kind: RunnableKind) -> Option<&Runnable> {
+        self.runnables.iter().find(|it| it.kind == kind)
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
 pub struct ProjectJsonData {
+    sysroot: Option<PathBuf>,
     sysroot_src: Option<PathBuf>,
     crates: Vec<CrateData>,
 }
@@ -138,6 +273,23 @@ struct CrateData {
     source: Option<CrateSource>,
     #[serde(default)]
     is_proc_macro: bool,
+    build: Option<BuildData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct BuildData {
+    label: String,
+    #[serde(default)]
+    runnables: Vec<RunnableData>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+struct RunnableData {
+    kind: RunnableKind,
+    program: String,
+    #[serde(default)]
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
 }
 
 #[derive(Deserialize, Debug, Clone)]
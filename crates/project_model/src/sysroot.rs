@@ -4,7 +4,11 @@
 //! but we can't process `.rlib` and need source code instead. The source code
 //! is typically installed with `rustup component add rust-src` command.
 
-use std::{env, fs, iter, ops, path::PathBuf, process::Command};
+use std::{
+    env, fs, iter, ops,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use anyhow::{format_err, Result};
 use la_arena::{Arena, Idx};
@@ -39,6 +43,22 @@ impl Sysroot {
         &self.root
     }
 
+    /// Returns the proc-macro server shipped with the toolchain this sysroot
+    /// belongs to, if the toolchain has one.
+    pub fn discover_proc_macro_srv(&self) -> Option<AbsPathBuf> {
+        // `root` is usually `$sysroot/lib/rustlib/src/rust/library`.
+        let sysroot_dir = Path::ancestors(self.root.as_ref())
+            .find(|it| it.join("lib").join("rustlib").is_dir())?;
+        let srv = sysroot_dir
+            .join("libexec")
+            .join(format!("rust-analyzer-proc-macro-srv{}", env::consts::EXE_SUFFIX));
+        if srv.is_file() {
+            Some(AbsPathBuf::assert(srv))
+        } else {
+            None
+        }
+    }
+
     pub fn public_deps(&self) -> impl Iterator<Item = (&'static str, SysrootCrate, bool)> + '_ {
         // core is added as a dependency before std in order to
         // mimic rustcs dependency order
@@ -3,15 +3,15 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use base_db::{CrateGraph, FileId};
+use base_db::{CrateGraph, CrateId, FileId};
 use cfg::{CfgAtom, CfgDiff};
 use expect_test::{expect, Expect};
 use paths::{AbsPath, AbsPathBuf};
 use serde::de::DeserializeOwned;
 
 use crate::{
-    CargoWorkspace, CfgOverrides, ProjectJson, ProjectJsonData, ProjectWorkspace, Sysroot,
-    WorkspaceBuildScripts,
+    CargoWorkspace, CfgOverrides, ProjectJson, ProjectJsonData, ProjectWorkspace, RunnableKind,
+    Sysroot, WorkspaceBuildScripts,
 };
 
 fn load_cargo(file: &str) -> CrateGraph {
@@ -27,6 +27,7 @@ fn load_cargo_with_overrides(file: &str, cfg_overrides: CfgOverrides) -> CrateGr
         sysroot: None,
         rustc: None,
         rustc_cfg: Vec::new(),
+        extra_rustc_cfgs: Vec::new(),
         cfg_overrides,
     };
     to_crate_graph(project_workspace)
@@ -1962,3 +1963,203 @@ fn rust_project_is_proc_macro_has_proc_macro_dep() {
     // on the proc_macro sysroot crate.
     crate_data.dependencies.iter().find(|&dep| dep.name.deref() == "proc_macro").unwrap();
 }
+
+#[test]
+fn cargo_hello_world_extra_targets() {
+    let cargo = CargoWorkspace::new(get_test_json_file("hello-world-metadata.json"));
+    let project_workspace = ProjectWorkspace::Cargo {
+        cargo,
+        build_scripts: WorkspaceBuildScripts::default(),
+        sysroot: None,
+        rustc: None,
+        rustc_cfg: vec!["target_arch=\"x86_64\"".parse().unwrap()],
+        extra_rustc_cfgs: vec![vec!["target_arch=\"arm\"".parse().unwrap()]],
+        cfg_overrides: CfgOverrides::default(),
+    };
+    let crate_graph = to_crate_graph(project_workspace);
+    let n_crates = load_cargo("hello-world-metadata.json").iter().count() as u32;
+    let name = |krate: CrateId| crate_graph[krate].display_name.as_ref().unwrap().to_string();
+
+    // Only the workspace member gets another instance, its dependencies are
+    // shared with the primary target.
+    let instances = crate_graph.iter().filter(|krate| krate.0 >= n_crates).collect::<Vec<_>>();
+    let mut names = instances.iter().map(|&krate| name(krate)).collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, ["an_example", "hello_world", "hello_world", "it"]);
+    for krate in crate_graph.iter() {
+        let arch = if krate.0 < n_crates { "x86_64" } else { "arm" };
+        assert_eq!(crate_graph[krate].cfg_options.get_cfg_values("target_arch"), vec![arch]);
+    }
+    for krate in instances {
+        for dep in &crate_graph[krate].dependencies {
+            let to_instance = dep.crate_id.0 >= n_crates;
+            assert_eq!(to_instance, name(dep.crate_id) == "hello_world", "{}", name(dep.crate_id));
+        }
+    }
+}
+
+#[test]
+fn cargo_hello_world_affected_members() {
+    let cargo = CargoWorkspace::new(get_test_json_file("hello-world-metadata.json"));
+    let hello_world = cargo.packages().find(|&it| cargo[it].name == "hello-world").unwrap();
+    let root = cargo[hello_world].manifest.parent().to_path_buf();
+    let names = |path: &AbsPath| {
+        cargo
+            .affected_members(path)
+            .into_iter()
+            .map(|pkg| cargo[pkg].name.clone())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&root.join("src/lib.rs")), vec!["hello-world".to_string()]);
+    // `libc` isn't a workspace member.
+    let libc = cargo.packages().find(|&it| cargo[it].name == "libc").unwrap();
+    assert!(names(&cargo[libc].manifest.parent().join("src/lib.rs")).is_empty());
+}
+
+#[test]
+fn cargo_hello_world_build_script_outputs_from_file() {
+    let cargo = CargoWorkspace::new(get_test_json_file("hello-world-metadata.json"));
+    let dir = std::env::temp_dir().join(format!("ra-build-script-outputs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = AbsPathBuf::assert(dir.join("outputs.json"));
+    let load = |json: &str| {
+        std::fs::write(&path, json).unwrap();
+        WorkspaceBuildScripts::from_file(&path, &cargo).unwrap()
+    };
+
+    let outputs = load(
+        r#"{
+            "libc 0.2.98 (registry+https://github.com/rust-lang/crates.io-index)": {
+                "cfgs": ["freebsd11", "libc_align"],
+                "envs": [["LIBC_VERSION", "0.2.98"]],
+                "out_dir": "out/libc"
+            },
+            "hello-world": {}
+        }"#,
+    )
+    .unwrap();
+    let libc = cargo.packages().find(|&it| cargo[it].name == "libc").unwrap();
+    let libc = &outputs.outputs[libc];
+    assert_eq!(libc.cfgs.len(), 2);
+    assert_eq!(libc.out_dir, Some(AbsPathBuf::assert(dir.join("out/libc"))));
+    assert_eq!(
+        libc.envs,
+        vec![
+            ("LIBC_VERSION".to_string(), "0.2.98".to_string()),
+            ("OUT_DIR".to_string(), dir.join("out/libc").to_str().unwrap().to_string()),
+        ]
+    );
+
+    // `libc` has a build script, so its outputs must be present.
+    assert!(load(r#"{ "hello-world": {} }"#).is_none());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert!(WorkspaceBuildScripts::from_file(&path, &cargo).unwrap().is_none());
+}
+
+#[test]
+#[cfg(not(windows))]
+fn rust_project_from_discovery_command() {
+    // The file to discover the project for is passed as the last argument.
+    let file = AbsPathBuf::assert(get_test_path("hello-world-project.json"));
+    let base = file.parent().unwrap();
+    let project = ProjectJson::discover(&["cat".to_string()], base, &file).unwrap();
+    assert_eq!(project.path(), base);
+    assert_eq!(project.n_crates(), 1);
+
+    let err = ProjectJson::discover(&["false".to_string()], base, &file).unwrap_err();
+    assert!(err.to_string().contains("failed"), "{}", err);
+}
+
+#[test]
+fn rust_project_build_runnables_and_sysroot() {
+    let data: ProjectJsonData = serde_json::from_str(
+        r#"{
+            "sysroot": "toolchain",
+            "crates": [
+                {
+                    "root_module": "foo/lib.rs",
+                    "edition": "2021",
+                    "deps": [],
+                    "build": {
+                        "label": "//foo:foo",
+                        "runnables": [
+                            { "kind": "test", "program": "bazel", "args": ["test", "{label}", "--test_arg={test_id}"] },
+                            { "kind": "run", "program": "bazel", "args": ["run", "{label}"], "cwd": "foo" }
+                        ]
+                    }
+                },
+                { "root_module": "bar/lib.rs", "edition": "2021", "deps": [] }
+            ]
+        }"#,
+    )
+    .unwrap();
+    let project = rooted_project_json(data);
+    assert_eq!(
+        project.sysroot_src,
+        Some(project.path().join("toolchain/lib/rustlib/src/rust/library"))
+    );
+
+    let crates = project.crates().map(|(_, krate)| krate).collect::<Vec<_>>();
+    assert!(crates[1].build().is_none());
+    let build = crates[0].build().unwrap();
+    assert_eq!(build.label, "//foo:foo");
+    assert!(build.runnable(RunnableKind::Bench).is_none());
+    assert_eq!(build.runnable(RunnableKind::Test).unwrap().cwd, project.path().to_path_buf());
+    assert_eq!(build.runnable(RunnableKind::Run).unwrap().cwd, project.path().join("foo"));
+}
+
+#[test]
+fn sysroot_proc_macro_srv() {
+    let sysroot_dir =
+        std::env::temp_dir().join(format!("ra-sysroot-proc-macro-srv-{}", std::process::id()));
+    let src_dir = sysroot_dir.join("lib/rustlib/src/rust/library");
+    std::fs::create_dir_all(src_dir.join("core/src")).unwrap();
+    std::fs::write(src_dir.join("core/src/lib.rs"), "").unwrap();
+    let sysroot = Sysroot::load(AbsPathBuf::assert(src_dir)).unwrap();
+    assert_eq!(sysroot.discover_proc_macro_srv(), None);
+
+    let libexec = sysroot_dir.join("libexec");
+    std::fs::create_dir_all(&libexec).unwrap();
+    let srv = libexec.join(format!("rust-analyzer-proc-macro-srv{}", std::env::consts::EXE_SUFFIX));
+    std::fs::write(&srv, "").unwrap();
+    assert_eq!(sysroot.discover_proc_macro_srv(), Some(AbsPathBuf::assert(srv)));
+
+    std::fs::remove_dir_all(&sysroot_dir).unwrap();
+}
+
+#[test]
+fn merged_rust_projects_share_crates() {
+    let project = |crates: &str| {
+        let data = format!(r#"{{ "sysroot_src": null, "crates": {} }}"#, crates);
+        rooted_project_json(serde_json::from_str(&data).unwrap())
+    };
+    let mut merged = project(
+        r#"[
+            { "root_module": "common/lib.rs", "edition": "2018", "deps": [] },
+            {
+                "root_module": "a/lib.rs",
+                "edition": "2018",
+                "deps": [{ "crate": 0, "name": "common" }]
+            }
+        ]"#,
+    );
+    merged.merge(project(
+        r#"[
+            {
+                "root_module": "b/lib.rs",
+                "edition": "2018",
+                "deps": [{ "crate": 1, "name": "common" }]
+            },
+            { "root_module": "common/lib.rs", "edition": "2018", "deps": [] }
+        ]"#,
+    ));
+
+    let crates = merged.crates().map(|(_, krate)| krate).collect::<Vec<_>>();
+    assert_eq!(crates.len(), 3);
+    assert!(crates[2].root_module().as_ref().ends_with("b/lib.rs"));
+    assert_eq!(
+        crates[2].deps.iter().map(|dep| dep.crate_id).collect::<Vec<_>>(),
+        [base_db::CrateId(0)]
+    );
+}
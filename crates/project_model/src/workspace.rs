@@ -15,7 +15,7 @@ use stdx::always;
 
 use crate::{
     build_scripts::BuildScriptOutput,
-    cargo_workspace::{DepKind, PackageData, RustcSource},
+    cargo_workspace::{DepKind, Package, PackageData, RustcSource},
     cfg_flag::CfgFlag,
     rustc_cfg,
     sysroot::SysrootCrate,
@@ -75,6 +75,10 @@ pub enum ProjectWorkspace {
         /// FIXME: make this a per-crate map, as, eg, build.rs might have a
         /// different target.
         rustc_cfg: Vec<CfgFlag>,
+        /// Cfg flags for each of the additional targets. The crate graph
+        /// holds a separate instance of the workspace members for each of
+        /// these, see `add_extra_target_crates`.
+        extra_rustc_cfgs: Vec<Vec<CfgFlag>>,
         cfg_overrides: CfgOverrides,
     },
     /// Project workspace was manually specified using a `rust-project.json` file.
@@ -103,6 +107,7 @@ impl fmt::Debug for ProjectWorkspace {
                 sysroot,
                 rustc,
                 rustc_cfg,
+                extra_rustc_cfgs,
                 cfg_overrides,
             } => f
                 .debug_struct("Cargo")
@@ -114,6 +119,7 @@ impl fmt::Debug for ProjectWorkspace {
                     &rustc.as_ref().map_or(0, |rc| rc.packages().len()),
                 )
                 .field("n_rustc_cfg", &rustc_cfg.len())
+                .field("n_extra_targets", &extra_rustc_cfgs.len())
                 .field("n_cfg_overrides", &cfg_overrides.len())
                 .finish(),
             ProjectWorkspace::Json { project, sysroot, rustc_cfg } => {
@@ -209,6 +215,12 @@ impl ProjectWorkspace {
                 };
 
                 let rustc_cfg = rustc_cfg::get(Some(&cargo_toml), config.target.as_deref());
+                let extra_rustc_cfgs = config
+                    .extra_targets
+                    .iter()
+                    .filter(|target| Some(*target) != config.target.as_ref())
+                    .map(|target| rustc_cfg::get(Some(&cargo_toml), Some(target)))
+                    .collect();
 
                 let cfg_overrides = config.cfg_overrides();
                 ProjectWorkspace::Cargo {
@@ -217,6 +229,7 @@ impl ProjectWorkspace {
                     sysroot,
                     rustc,
                     rustc_cfg,
+                    extra_rustc_cfgs,
                     cfg_overrides,
                 }
             }
@@ -299,6 +312,7 @@ impl ProjectWorkspace {
                 sysroot,
                 rustc,
                 rustc_cfg: _,
+                extra_rustc_cfgs: _,
                 cfg_overrides: _,
                 build_scripts,
             } => {
@@ -404,10 +418,12 @@ impl ProjectWorkspace {
                 sysroot,
                 rustc,
                 rustc_cfg,
+                extra_rustc_cfgs,
                 cfg_overrides,
                 build_scripts,
             } => cargo_to_crate_graph(
                 rustc_cfg.clone(),
+                extra_rustc_cfgs,
                 cfg_overrides,
                 load_proc_macro,
                 load,
@@ -505,6 +521,7 @@ fn project_json_to_crate_graph(
 
 fn cargo_to_crate_graph(
     rustc_cfg: Vec<CfgFlag>,
+    extra_rustc_cfgs: &[Vec<CfgFlag>],
     override_cfg: &CfgOverrides,
     load_proc_macro: &mut dyn FnMut(&AbsPath) -> Vec<ProcMacro>,
     load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
@@ -642,9 +659,93 @@ fn cargo_to_crate_graph(
             );
         }
     }
+
+    for rustc_cfg in extra_rustc_cfgs {
+        add_extra_target_crates(&mut crate_graph, rustc_cfg, override_cfg, cargo, &pkg_crates);
+    }
     crate_graph
 }
 
+/// Adds another instance of the workspace members' crates, for an additional
+/// target with the given cfgs. The new instances come after the ones of the
+/// primary target, so those stay the default for a file.
+///
+/// Dependencies, including the sysroot, are shared with the primary target
+/// and so are analyzed for it only. Packages with a build script are skipped,
+/// as its outputs are only known for the primary target.
+fn add_extra_target_crates(
+    crate_graph: &mut CrateGraph,
+    rustc_cfg: &[CfgFlag],
+    override_cfg: &CfgOverrides,
+    cargo: &CargoWorkspace,
+    pkg_crates: &FxHashMap<Package, Vec<(CrateId, TargetKind)>>,
+) {
+    let mut instances = FxHashMap::default();
+    for pkg in cargo.packages() {
+        let pkg_data = &cargo[pkg];
+        let has_build_script =
+            pkg_data.targets.iter().any(|&tgt| cargo[tgt].kind == TargetKind::BuildScript);
+        if !pkg_data.is_member || has_build_script {
+            continue;
+        }
+
+        let mut cfg_options = CfgOptions::default();
+        cfg_options.extend(rustc_cfg.iter().cloned());
+        cfg_options.insert_atom("test".into());
+        cfg_options.insert_atom("debug_assertions".into());
+        let overrides = match override_cfg {
+            CfgOverrides::Wildcard(cfg_diff) => Some(cfg_diff),
+            CfgOverrides::Selective(cfg_overrides) => cfg_overrides.get(&pkg_data.name),
+        };
+        if let Some(overrides) = overrides {
+            cfg_options.apply_diff(overrides.clone());
+        }
+        for feature in pkg_data.active_features.iter() {
+            cfg_options.insert_key_value("feature".into(), feature.into());
+        }
+        let mut potential_cfg_options = cfg_options.clone();
+        potential_cfg_options.extend(
+            pkg_data
+                .features
+                .iter()
+                .map(|feat| CfgFlag::KeyValue { key: "feature".into(), value: feat.0.into() }),
+        );
+
+        for &(crate_id, _) in pkg_crates.get(&pkg).into_iter().flatten() {
+            let data = &crate_graph[crate_id];
+            let (file_id, edition, display_name, env, proc_macro) = (
+                data.root_file_id,
+                data.edition,
+                data.display_name.clone(),
+                data.env.clone(),
+                data.proc_macro.clone(),
+            );
+            let instance = crate_graph.add_crate_root(
+                file_id,
+                edition,
+                display_name,
+                cfg_options.clone(),
+                potential_cfg_options.clone(),
+                env,
+                proc_macro,
+            );
+            instances.insert(crate_id, instance);
+        }
+    }
+
+    // Dependencies between workspace members stay within the target.
+    for (&crate_id, &instance) in instances.iter() {
+        for dep in crate_graph[crate_id].dependencies.clone() {
+            let to = instances.get(&dep.crate_id).copied().unwrap_or(dep.crate_id);
+            add_dep_inner(
+                crate_graph,
+                instance,
+                Dependency::with_prelude(dep.name.clone(), to, dep.is_prelude()),
+            );
+        }
+    }
+}
+
 fn detached_files_to_crate_graph(
     rustc_cfg: Vec<CfgFlag>,
     load: &mut dyn FnMut(&AbsPath) -> Option<FileId>,
@@ -7,7 +7,9 @@ mod symbols;
 mod highlight;
 mod analysis_stats;
 mod diagnostics;
+mod unused_items;
 mod ssr;
+mod refactor;
 mod lsif;
 
 mod progress_report;
@@ -1,31 +1,58 @@
 //! Analyze all modules in a project for diagnostics. Exits with a non-zero
 //! status code if any errors are found.
 
+use std::fs;
+
+use crossbeam_channel::unbounded;
+use flycheck::{FlycheckConfig, FlycheckHandle};
+use lsp_types::{DiagnosticSeverity, NumberOrString};
 use rustc_hash::FxHashSet;
+use serde_json::json;
+use vfs::{AbsPath, AbsPathBuf};
 
 use hir::{db::HirDatabase, Crate, Module};
-use ide::{AssistResolveStrategy, DiagnosticsConfig, Severity};
-use ide_db::base_db::SourceDatabaseExt;
+use ide::{AssistResolveStrategy, DiagnosticsConfig};
+use ide_db::{base_db::SourceDatabaseExt, LineIndexDatabase};
 
-use crate::cli::{
-    flags,
-    load_cargo::{load_workspace_at, LoadCargoConfig},
+use crate::{
+    cli::{
+        flags::{self, DiagnosticsFormat, DiagnosticsSeverity},
+        load_cargo::{load_workspace_at, LoadCargoConfig},
+    },
+    diagnostics::{is_duplicate_of_check_diagnostic, to_proto::map_rust_diagnostic_to_lsp},
+    from_proto,
+    line_index::{LineEndings, LineIndex, OffsetEncoding},
+    to_proto,
 };
 
+/// A diagnostic, in the form we'd send it to the client, and the file it's
+/// reported for.
+struct FileDiagnostic {
+    path: AbsPathBuf,
+    diagnostic: lsp_types::Diagnostic,
+}
+
 impl flags::Diagnostics {
     pub fn run(self) -> anyhow::Result<()> {
+        let mut root = fs::canonicalize(&self.path)?;
+        if root.is_file() {
+            root.pop();
+        }
+        let root = AbsPathBuf::assert(root);
         let cargo_config = Default::default();
         let load_cargo_config = LoadCargoConfig {
             load_out_dirs_from_check: !self.disable_build_scripts,
             with_proc_macro: !self.disable_proc_macros,
             prefill_caches: false,
         };
-        let (host, _vfs, _proc_macro) =
+        let (host, vfs, _proc_macro) =
             load_workspace_at(&self.path, &cargo_config, &load_cargo_config, &|_| {})?;
         let db = host.raw_database();
         let analysis = host.analysis();
 
-        let mut found_error = false;
+        let check = if self.flycheck { run_flycheck(&root)? } else { Vec::new() };
+
+        let mut diagnostics = Vec::new();
         let mut visited_files = FxHashSet::default();
 
         let work = all_modules(db).into_iter().filter(|module| {
@@ -37,42 +64,249 @@ impl flags::Diagnostics {
 
         for module in work {
             let file_id = module.definition_source(db).file_id.original_file(db);
-            if !visited_files.contains(&file_id) {
-                let crate_name =
-                    module.krate().display_name(db).as_deref().unwrap_or("unknown").to_string();
-                println!("processing crate: {}, module: {}", crate_name, _vfs.file_path(file_id));
-                for diagnostic in analysis
-                    .diagnostics(
-                        &DiagnosticsConfig::default(),
-                        AssistResolveStrategy::None,
-                        file_id,
-                    )
-                    .unwrap()
-                {
-                    if matches!(diagnostic.severity, Severity::Error) {
-                        found_error = true;
-                    }
+            if !visited_files.insert(file_id) {
+                continue;
+            }
+            let path = match vfs.file_path(file_id).as_path() {
+                Some(path) => path.to_path_buf(),
+                None => continue,
+            };
+            let line_index = LineIndex {
+                index: db.line_index(file_id),
+                endings: LineEndings::Unix,
+                encoding: OffsetEncoding::Utf16,
+            };
+            let config = DiagnosticsConfig::default();
+            for d in analysis.diagnostics(&config, AssistResolveStrategy::None, file_id).unwrap() {
+                let diagnostic = lsp_types::Diagnostic {
+                    range: to_proto::range(&line_index, d.range),
+                    severity: Some(to_proto::diagnostic_severity(d.severity)),
+                    code: Some(NumberOrString::String(d.code.as_str().to_string())),
+                    source: Some("rust-analyzer".to_string()),
+                    message: d.message,
+                    ..lsp_types::Diagnostic::default()
+                };
+                // As in the editor, `cargo check`'s version of a diagnostic wins.
+                if check.iter().any(|check| {
+                    check.path == path
+                        && is_duplicate_of_check_diagnostic(&diagnostic, &check.diagnostic)
+                }) {
+                    continue;
+                }
+                diagnostics.push(FileDiagnostic { path: path.clone(), diagnostic });
+            }
+        }
+        diagnostics.extend(check);
+        diagnostics.retain(|it| self.is_reported(&it.diagnostic));
+        diagnostics.sort_by_key(|it| {
+            (it.path.clone(), it.diagnostic.range.start.line, it.diagnostic.range.start.character)
+        });
 
-                    println!("{:?}", diagnostic);
+        match self.format.unwrap_or(DiagnosticsFormat::Text) {
+            DiagnosticsFormat::Text => {
+                for it in &diagnostics {
+                    let d = &it.diagnostic;
+                    let code = code(d).map(|code| format!("[{}]", code)).unwrap_or_default();
+                    println!(
+                        "{}:{}:{}: {}{}: {}",
+                        relative_path(&root, &it.path),
+                        d.range.start.line + 1,
+                        d.range.start.character + 1,
+                        severity_name(severity(d)),
+                        code,
+                        d.message
+                    );
                 }
+            }
+            DiagnosticsFormat::Json => {
+                let json: Vec<_> = diagnostics.iter().map(|it| to_json(&root, it)).collect();
+                println!("{:#}", serde_json::Value::Array(json));
+            }
+            DiagnosticsFormat::Sarif => println!("{:#}", to_sarif(&root, &diagnostics)),
+        }
 
-                visited_files.insert(file_id);
+        let errors =
+            diagnostics.iter().filter(|it| severity(&it.diagnostic) == DiagnosticsSeverity::Error);
+        match errors.count() {
+            0 => Ok(()),
+            1 => anyhow::bail!("found 1 error"),
+            n => anyhow::bail!("found {} errors", n),
+        }
+    }
+
+    fn is_reported(&self, diagnostic: &lsp_types::Diagnostic) -> bool {
+        if let Some(min_severity) = self.severity {
+            if severity(diagnostic) > min_severity {
+                return false;
             }
         }
+        let code = code(diagnostic);
+        let code = code.as_deref();
+        (self.include.is_empty() || self.include.iter().any(|it| Some(it.as_str()) == code))
+            && !self.exclude.iter().any(|it| Some(it.as_str()) == code)
+    }
+}
 
-        println!();
-        println!("diagnostic scan complete");
+/// Runs `cargo check` on the workspace at `root` and returns its diagnostics.
+fn run_flycheck(root: &AbsPath) -> anyhow::Result<Vec<FileDiagnostic>> {
+    let (sender, receiver) = unbounded();
+    let config = FlycheckConfig::CargoCommand {
+        command: "check".to_string(),
+        target_triple: None,
+        all_targets: true,
+        no_default_features: false,
+        all_features: false,
+        features: Vec::new(),
+        extra_args: Vec::new(),
+    };
+    let handle = FlycheckHandle::spawn(
+        0,
+        Box::new(move |msg| {
+            let _ = sender.send(msg);
+        }),
+        config,
+        root.to_path_buf(),
+    );
+    handle.update();
 
-        if found_error {
-            println!();
-            anyhow::bail!("diagnostic error detected")
+    let map_config = Default::default();
+    let mut res = Vec::new();
+    for msg in receiver {
+        match msg {
+            flycheck::Message::AddDiagnostic { workspace_root, diagnostic, .. } => {
+                for mapped in map_rust_diagnostic_to_lsp(&map_config, &diagnostic, &workspace_root)
+                {
+                    if let Ok(path) = from_proto::abs_path(&mapped.url) {
+                        res.push(FileDiagnostic { path, diagnostic: mapped.diagnostic });
+                    }
+                }
+            }
+            flycheck::Message::Progress { progress, .. } => match progress {
+                flycheck::Progress::DidFinish(result) => {
+                    result?;
+                    break;
+                }
+                flycheck::Progress::DidCancel => anyhow::bail!("`cargo check` was cancelled"),
+                flycheck::Progress::DidStart(_) | flycheck::Progress::DidCheckCrate(_) => (),
+            },
         }
+    }
+    Ok(res)
+}
 
-        Ok(())
+/// Returns the code of the diagnostic, which for tool lints (like clippy's)
+/// includes the tool, as in `clippy::needless_return`.
+fn code(diagnostic: &lsp_types::Diagnostic) -> Option<String> {
+    let code = match diagnostic.code.as_ref()? {
+        NumberOrString::String(code) => code.clone(),
+        NumberOrString::Number(code) => code.to_string(),
+    };
+    match diagnostic.source.as_deref() {
+        Some(source) if source != "rustc" && source != "rust-analyzer" => {
+            Some(format!("{}::{}", source, code))
+        }
+        _ => Some(code),
     }
 }
 
-fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
+fn severity(diagnostic: &lsp_types::Diagnostic) -> DiagnosticsSeverity {
+    match diagnostic.severity {
+        Some(DiagnosticSeverity::WARNING) => DiagnosticsSeverity::Warning,
+        Some(DiagnosticSeverity::INFORMATION) => DiagnosticsSeverity::Info,
+        Some(DiagnosticSeverity::HINT) => DiagnosticsSeverity::Hint,
+        _ => DiagnosticsSeverity::Error,
+    }
+}
+
+fn severity_name(severity: DiagnosticsSeverity) -> &'static str {
+    match severity {
+        DiagnosticsSeverity::Error => "error",
+        DiagnosticsSeverity::Warning => "warning",
+        DiagnosticsSeverity::Info => "info",
+        DiagnosticsSeverity::Hint => "hint",
+    }
+}
+
+/// Returns `path` relative to `root` if it's within it, with `/` as the
+/// separator.
+pub(super) fn relative_path(root: &AbsPath, path: &AbsPath) -> String {
+    match path.strip_prefix(root) {
+        Some(path) => path.as_ref().to_string_lossy().replace('\\', "/"),
+        None => path.as_ref().display().to_string(),
+    }
+}
+
+/// Lines and columns are 1-based, columns count UTF-16 code units.
+fn to_json(root: &AbsPath, it: &FileDiagnostic) -> serde_json::Value {
+    let d = &it.diagnostic;
+    let position = |position: lsp_types::Position| json!({ "line": position.line + 1, "column": position.character + 1 });
+    json!({
+        "file": relative_path(root, &it.path),
+        "range": { "start": position(d.range.start), "end": position(d.range.end) },
+        "severity": severity_name(severity(d)),
+        "code": code(d),
+        "source": d.source,
+        "message": d.message,
+    })
+}
+
+/// Renders the diagnostics as a [SARIF](https://sarifweb.azurewebsites.net/)
+/// log, as understood by code scanning tools.
+fn to_sarif(root: &AbsPath, diagnostics: &[FileDiagnostic]) -> serde_json::Value {
+    let mut rules: Vec<String> = diagnostics.iter().filter_map(|it| code(&it.diagnostic)).collect();
+    rules.sort();
+    rules.dedup();
+    let results: Vec<_> = diagnostics
+        .iter()
+        .map(|it| {
+            let d = &it.diagnostic;
+            let level = match severity(d) {
+                DiagnosticsSeverity::Error => "error",
+                DiagnosticsSeverity::Warning => "warning",
+                DiagnosticsSeverity::Info | DiagnosticsSeverity::Hint => "note",
+            };
+            json!({
+                "ruleId": code(d),
+                "level": level,
+                "message": { "text": d.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": {
+                            "uri": relative_path(root, &it.path),
+                            "uriBaseId": "%SRCROOT%",
+                        },
+                        "region": {
+                            "startLine": d.range.start.line + 1,
+                            "startColumn": d.range.start.character + 1,
+                            "endLine": d.range.end.line + 1,
+                            "endColumn": d.range.end.character + 1,
+                        },
+                    },
+                }],
+            })
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "rust-analyzer",
+                    "informationUri": "https://rust-analyzer.github.io/",
+                    "rules": rules.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                },
+            },
+            "originalUriBaseIds": {
+                "%SRCROOT%": { "uri": format!("{}/", to_proto::url_from_abs_path(root)) },
+            },
+            "columnKind": "utf16CodeUnits",
+            "results": results,
+        }],
+    })
+}
+
+pub(super) fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
     let mut worklist: Vec<_> =
         Crate::all(db).into_iter().map(|krate| krate.root_module(db)).collect();
     let mut modules = Vec::new();
@@ -84,3 +318,51 @@ fn all_modules(db: &dyn HirDatabase) -> Vec<Module> {
 
     modules
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(source: &str, code: &str, severity: DiagnosticSeverity) -> lsp_types::Diagnostic {
+        lsp_types::Diagnostic {
+            severity: Some(severity),
+            code: Some(NumberOrString::String(code.to_string())),
+            source: Some(source.to_string()),
+            ..lsp_types::Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn filters_by_severity_and_code() {
+        let flags = flags::Diagnostics {
+            path: ".".into(),
+            disable_build_scripts: false,
+            disable_proc_macros: false,
+            format: None,
+            severity: Some(DiagnosticsSeverity::Warning),
+            include: Vec::new(),
+            exclude: vec!["clippy::needless_return".to_string()],
+            flycheck: false,
+        };
+        let reported =
+            |source, code, severity| flags.is_reported(&diagnostic(source, code, severity));
+        assert!(reported("rust-analyzer", "unresolved-import", DiagnosticSeverity::ERROR));
+        assert!(reported("rustc", "unused_variables", DiagnosticSeverity::WARNING));
+        assert!(!reported("rust-analyzer", "incorrect-ident-case", DiagnosticSeverity::HINT));
+        assert!(!reported("clippy", "needless_return", DiagnosticSeverity::WARNING));
+        assert!(reported("clippy", "needless_range_loop", DiagnosticSeverity::WARNING));
+
+        let flags = flags::Diagnostics {
+            severity: None,
+            include: vec!["missing-match-arm".to_string()],
+            exclude: Vec::new(),
+            ..flags
+        };
+        assert!(flags.is_reported(&diagnostic(
+            "rust-analyzer",
+            "missing-match-arm",
+            DiagnosticSeverity::ERROR
+        )));
+        assert!(!flags.is_reported(&diagnostic("rustc", "E0004", DiagnosticSeverity::ERROR)));
+    }
+}
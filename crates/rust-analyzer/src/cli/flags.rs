@@ -1,6 +1,6 @@
 //! Grammar for the command-line arguments.
 #![allow(unreachable_pub)]
-use std::path::PathBuf;
+use std::{path::PathBuf, str::FromStr};
 
 use ide_ssr::{SsrPattern, SsrRule};
 
@@ -81,6 +81,28 @@ xflags::xflags! {
         cmd diagnostics
             /// Directory with Cargo.toml.
             required path: PathBuf
+        {
+            /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
+            optional --disable-build-scripts
+            /// Don't use expand proc macros.
+            optional --disable-proc-macros
+
+            /// Output format: `text` (the default), `json` or `sarif`.
+            optional --format format: DiagnosticsFormat
+            /// Only report diagnostics at least this severe: `error`, `warning`, `info` or `hint`.
+            optional --severity severity: DiagnosticsSeverity
+            /// Only report diagnostics with this code, can be repeated.
+            repeated --include code: String
+            /// Don't report diagnostics with this code, can be repeated.
+            repeated --exclude code: String
+            /// Also report the diagnostics of `cargo check`.
+            optional --flycheck
+        }
+
+        /// List the public items that nothing in the workspace uses.
+        cmd unused-items
+            /// Directory with Cargo.toml.
+            required path: PathBuf
         {
             /// Don't run build scripts or load `OUT_DIR` values by running `cargo check` before analysis.
             optional --disable-build-scripts
@@ -91,7 +113,14 @@ xflags::xflags! {
         cmd ssr
             /// A structured search replace rule (`$a.foo($b) ==> bar($a, $b)`)
             repeated rule: SsrRule
-        {}
+        {
+            /// Also apply the named rules from a `.ssr` rule file.
+            optional --rules path: PathBuf
+            /// Print the location of every match and fail if there are any, instead of applying the rules.
+            optional --check
+            /// Apply the rules, which is the default.
+            optional --fix
+        }
 
         cmd search
             /// A structured search replace pattern (`$a.foo($b)`)
@@ -101,6 +130,32 @@ xflags::xflags! {
             optional --debug snippet: String
         }
 
+        /// Rename the item at a position, and all references to it.
+        cmd rename
+            /// Position of the item, as `path/to/file.rs:line:column`.
+            required position: FileLocation
+            /// The new name.
+            required new_name: String
+        {
+            /// Print the changes as a unified diff instead of writing them to disk.
+            optional --diff
+        }
+
+        /// Apply an assist at a position.
+        cmd assist
+            /// Id of the assist, e.g. `auto_import`.
+            required id: String
+            /// Position or range to apply the assist at, as `path/to/file.rs:line:column` or
+            /// `path/to/file.rs:line:column-line:column`.
+            required position: FileLocation
+        {
+            /// Label of the variant of the assist to apply, if it has several (e.g. one per import
+            /// candidate).
+            optional --label label: String
+            /// Print the changes as a unified diff instead of writing them to disk.
+            optional --diff
+        }
+
         cmd proc-macro {}
 
         cmd lsif
@@ -130,8 +185,11 @@ pub enum RustAnalyzerCmd {
     Highlight(Highlight),
     AnalysisStats(AnalysisStats),
     Diagnostics(Diagnostics),
+    UnusedItems(UnusedItems),
     Ssr(Ssr),
     Search(Search),
+    Rename(Rename),
+    Assist(Assist),
     ProcMacro(ProcMacro),
     Lsif(Lsif),
 }
@@ -176,6 +234,19 @@ pub struct AnalysisStats {
 pub struct Diagnostics {
     pub path: PathBuf,
 
+    pub disable_build_scripts: bool,
+    pub disable_proc_macros: bool,
+    pub format: Option<DiagnosticsFormat>,
+    pub severity: Option<DiagnosticsSeverity>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub flycheck: bool,
+}
+
+#[derive(Debug)]
+pub struct UnusedItems {
+    pub path: PathBuf,
+
     pub disable_build_scripts: bool,
     pub disable_proc_macros: bool,
 }
@@ -183,6 +254,10 @@ pub struct Diagnostics {
 #[derive(Debug)]
 pub struct Ssr {
     pub rule: Vec<SsrRule>,
+
+    pub rules: Option<PathBuf>,
+    pub check: bool,
+    pub fix: bool,
 }
 
 #[derive(Debug)]
@@ -192,6 +267,23 @@ pub struct Search {
     pub debug: Option<String>,
 }
 
+#[derive(Debug)]
+pub struct Rename {
+    pub position: FileLocation,
+    pub new_name: String,
+
+    pub diff: bool,
+}
+
+#[derive(Debug)]
+pub struct Assist {
+    pub id: String,
+    pub position: FileLocation,
+
+    pub label: Option<String>,
+    pub diff: bool,
+}
+
 #[derive(Debug)]
 pub struct ProcMacro;
 
@@ -227,3 +319,88 @@ impl RustAnalyzer {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagnosticsFormat {
+    Text,
+    Json,
+    Sarif,
+}
+
+impl FromStr for DiagnosticsFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(DiagnosticsFormat::Text),
+            "json" => Ok(DiagnosticsFormat::Json),
+            "sarif" => Ok(DiagnosticsFormat::Sarif),
+            _ => Err(format!("unknown format `{}`, expected `text`, `json` or `sarif`", s)),
+        }
+    }
+}
+
+/// Severities in decreasing order, as in LSP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiagnosticsSeverity {
+    Error,
+    Warning,
+    Info,
+    Hint,
+}
+
+impl FromStr for DiagnosticsSeverity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(DiagnosticsSeverity::Error),
+            "warning" => Ok(DiagnosticsSeverity::Warning),
+            "info" => Ok(DiagnosticsSeverity::Info),
+            "hint" => Ok(DiagnosticsSeverity::Hint),
+            _ => Err(format!(
+                "unknown severity `{}`, expected `error`, `warning`, `info` or `hint`",
+                s
+            )),
+        }
+    }
+}
+
+/// A position or range in a file, with 1-based lines and columns, where
+/// columns count characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileLocation {
+    pub path: PathBuf,
+    pub start: (u32, u32),
+    pub end: Option<(u32, u32)>,
+}
+
+impl FromStr for FileLocation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        fn line_col(line: &str, col: &str) -> Option<(u32, u32)> {
+            let (line, col) = (line.parse().ok()?, col.parse().ok()?);
+            if line == 0 || col == 0 {
+                return None;
+            }
+            Some((line, col))
+        }
+        let (rest, end) = match s.rsplit_once('-') {
+            Some((rest, end)) => match end.split_once(':').and_then(|(l, c)| line_col(l, c)) {
+                Some(end) => (rest, Some(end)),
+                None => (s, None),
+            },
+            None => (s, None),
+        };
+        let err =
+            || format!("expected `path:line:column` or `path:line:column-line:column`: {}", s);
+        let mut parts = rest.rsplitn(3, ':');
+        let (col, line, path) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(col), Some(line), Some(path)) if !path.is_empty() => (col, line, path),
+            _ => return Err(err()),
+        };
+        let start = line_col(line, col).ok_or_else(err)?;
+        Ok(FileLocation { path: path.into(), start, end })
+    }
+}
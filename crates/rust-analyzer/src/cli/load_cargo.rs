@@ -7,7 +7,7 @@ use crossbeam_channel::{unbounded, Receiver};
 use hir::db::DefDatabase;
 use ide::{AnalysisHost, Change};
 use ide_db::base_db::CrateGraph;
-use proc_macro_api::ProcMacroServer;
+use proc_macro_api::{ProcMacroServer, ProcMacroServerConfig};
 use project_model::{CargoConfig, ProjectManifest, ProjectWorkspace};
 use vfs::{loader::Handle, AbsPath, AbsPathBuf};
 
@@ -60,7 +60,10 @@ pub fn load_workspace(
 
     let proc_macro_client = if load_config.with_proc_macro {
         let path = AbsPathBuf::assert(std::env::current_exe()?);
-        Some(ProcMacroServer::spawn(path, &["proc-macro"]).unwrap())
+        Some(
+            ProcMacroServer::spawn(path, &["proc-macro"], ProcMacroServerConfig::default())
+                .unwrap(),
+        )
     } else {
         None
     };
@@ -0,0 +1,234 @@
+//! Applies a rename or an assist from the command line, so that large
+//! refactorings can be scripted.
+
+use std::{env, fs};
+
+use anyhow::{bail, format_err};
+use ide::{
+    Analysis, AssistResolveStrategy, DiagnosticsConfig, FileId, FilePosition, FileRange,
+    FileSystemEdit, LineCol, SourceChange, TextRange, TextSize,
+};
+use ide_db::{
+    base_db::{AnchoredPathBuf, SourceDatabaseExt},
+    LineIndexDatabase, RootDatabase,
+};
+use lsp_types::ClientCapabilities;
+use project_model::CargoConfig;
+use rustc_hash::FxHashMap;
+use vfs::{AbsPathBuf, Vfs, VfsPath};
+
+use crate::{
+    cli::{
+        flags::{self, FileLocation},
+        load_cargo::{load_workspace_at, LoadCargoConfig},
+        Result,
+    },
+    config::Config,
+    diff::unified_diff,
+};
+
+impl flags::Rename {
+    pub fn run(self) -> Result<()> {
+        let (host, vfs, range) = load_workspace_for(&self.position)?;
+        let position = FilePosition { file_id: range.file_id, offset: range.range.start() };
+        let change = host
+            .analysis()
+            .rename(position, &self.new_name)?
+            .map_err(|err| format_err!("{}", err))?;
+        write_source_change(host.raw_database(), &vfs, change, self.diff)
+    }
+}
+
+impl flags::Assist {
+    pub fn run(self) -> Result<()> {
+        let (host, vfs, range) = load_workspace_for(&self.position)?;
+        let config = Config::new(current_dir()?, ClientCapabilities::default());
+        let analysis: Analysis = host.analysis();
+        let assists = analysis.assists_with_fixes(
+            &config.assist(),
+            &DiagnosticsConfig::default(),
+            AssistResolveStrategy::All,
+            range,
+        )?;
+        let available: Vec<String> = assists.iter().map(|it| it.id.0.to_string()).collect();
+        let mut candidates: Vec<_> = assists.into_iter().filter(|it| it.id.0 == self.id).collect();
+        let assist = match &self.label {
+            Some(label) => candidates
+                .into_iter()
+                .find(|it| it.label.to_string() == *label)
+                .ok_or_else(|| format_err!("assist `{}` has no variant `{}`", self.id, label))?,
+            None if candidates.len() == 1 => candidates.pop().unwrap(),
+            None if candidates.is_empty() => bail!(
+                "assist `{}` isn't available, available assists are: {}",
+                self.id,
+                available.join(", ")
+            ),
+            None => {
+                let labels: Vec<String> =
+                    candidates.iter().map(|it| format!("    {}", it.label)).collect();
+                bail!(
+                    "assist `{}` has several variants, pick one with `--label`:\n{}",
+                    self.id,
+                    labels.join("\n")
+                )
+            }
+        };
+        let change = assist.source_change.ok_or_else(|| format_err!("assist has no changes"))?;
+        write_source_change(host.raw_database(), &vfs, change, self.diff)
+    }
+}
+
+fn current_dir() -> Result<AbsPathBuf> {
+    Ok(AbsPathBuf::assert(env::current_dir()?))
+}
+
+/// Loads the workspace containing the file of `location`, and returns the
+/// range of `location` in it.
+fn load_workspace_for(location: &FileLocation) -> Result<(ide::AnalysisHost, Vfs, FileRange)> {
+    let path = current_dir()?.join(&location.path).normalize();
+    let cargo_config = CargoConfig::default();
+    let load_cargo_config = LoadCargoConfig {
+        load_out_dirs_from_check: true,
+        with_proc_macro: true,
+        prefill_caches: false,
+    };
+    let (host, vfs, _proc_macro) = load_workspace_at(
+        path.parent().unwrap_or(&path).as_ref(),
+        &cargo_config,
+        &load_cargo_config,
+        &|_| {},
+    )?;
+    let file_id = vfs
+        .file_id(&VfsPath::from(path.clone()))
+        .ok_or_else(|| format_err!("{} isn't part of the workspace", path.display()))?;
+    let db = host.raw_database();
+    let start = offset(db, file_id, location.start)?;
+    let end = match location.end {
+        Some(end) => offset(db, file_id, end)?,
+        None => start,
+    };
+    if end < start {
+        bail!("the range ends before it starts");
+    }
+    let range = FileRange { file_id, range: TextRange::new(start, end) };
+    Ok((host, vfs, range))
+}
+
+/// Converts a 1-based line and column, counting characters, to an offset.
+fn offset(db: &RootDatabase, file_id: FileId, (line, col): (u32, u32)) -> Result<TextSize> {
+    let text = db.file_text(file_id);
+    if line as usize > text.lines().count().max(1) {
+        bail!("line {} is past the end of the file", line);
+    }
+    let line_start = db.line_index(file_id).offset(LineCol { line: line - 1, col: 0 });
+    let line_text = text[usize::from(line_start)..].lines().next().unwrap_or_default();
+    let mut chars = line_text.chars();
+    let col: TextSize = chars.by_ref().take(col as usize - 1).map(TextSize::of).sum();
+    Ok(line_start + col)
+}
+
+/// The contents of a file before and after a change, together with where it
+/// lives before and after the change. A new file has no old path.
+struct FileChange {
+    old_path: Option<AbsPathBuf>,
+    new_path: AbsPathBuf,
+    old_text: String,
+    new_text: String,
+}
+
+/// Writes `change` to disk, or prints it as a diff.
+fn write_source_change(
+    db: &RootDatabase,
+    vfs: &Vfs,
+    change: SourceChange,
+    diff: bool,
+) -> Result<()> {
+    let moved_files = change.file_system_edits.iter().filter_map(|edit| match edit {
+        FileSystemEdit::MoveFile { src, .. } => Some(*src),
+        FileSystemEdit::CreateFile { .. } => None,
+    });
+    let mut files: FxHashMap<FileId, FileChange> = FxHashMap::default();
+    for file_id in change.source_file_edits.keys().copied().chain(moved_files) {
+        let path = vfs
+            .file_path(file_id)
+            .as_path()
+            .map(|it| it.to_path_buf())
+            .ok_or_else(|| format_err!("{} isn't a file on disk", vfs.file_path(file_id)))?;
+        let text = db.file_text(file_id).to_string();
+        files.entry(file_id).or_insert(FileChange {
+            old_path: Some(path.clone()),
+            new_path: path,
+            old_text: text.clone(),
+            new_text: text,
+        });
+    }
+    for (file_id, edit) in change.source_file_edits {
+        edit.apply(&mut files.get_mut(&file_id).unwrap().new_text);
+    }
+    let resolve = |dst: &AnchoredPathBuf| -> Result<AbsPathBuf> {
+        let mut base = vfs.file_path(dst.anchor);
+        base.pop();
+        base.join(&dst.path)
+            .and_then(|it| it.as_path().map(|it| it.to_path_buf()))
+            .ok_or_else(|| format_err!("{} isn't a file on disk", dst.path))
+    };
+    let mut new_files = Vec::new();
+    for edit in change.file_system_edits {
+        match edit {
+            FileSystemEdit::CreateFile { dst, initial_contents } => new_files.push(FileChange {
+                old_path: None,
+                new_path: resolve(&dst)?,
+                old_text: String::new(),
+                new_text: initial_contents,
+            }),
+            FileSystemEdit::MoveFile { src, dst } => {
+                files.get_mut(&src).unwrap().new_path = resolve(&dst)?
+            }
+        }
+    }
+    let mut changes: Vec<FileChange> = files.into_iter().map(|(_, it)| it).collect();
+    changes.extend(new_files);
+    changes.sort_by(|a, b| a.new_path.cmp(&b.new_path));
+
+    let cwd = current_dir()?;
+    let display = |path: &AbsPathBuf| match path.strip_prefix(&cwd) {
+        Some(path) => path.as_ref().to_string_lossy().replace('\\', "/"),
+        None => path.display().to_string(),
+    };
+    for change in changes {
+        if diff {
+            // Git's flavour of unified diff, which can describe new and moved
+            // files, and can be applied with `git apply`.
+            let new_path = display(&change.new_path);
+            let old_path = change.old_path.as_ref().map(display);
+            println!("diff --git a/{} b/{}", old_path.as_deref().unwrap_or(&new_path), new_path);
+            match &old_path {
+                None => println!("new file mode 100644"),
+                Some(old_path) if *old_path != new_path => {
+                    println!("rename from {}\nrename to {}", old_path, new_path)
+                }
+                Some(_) => (),
+            }
+            let hunks = unified_diff(&change.old_text, &change.new_text);
+            if !hunks.is_empty() {
+                match &old_path {
+                    Some(old_path) => println!("--- a/{}", old_path),
+                    None => println!("--- /dev/null"),
+                }
+                println!("+++ b/{}", new_path);
+                print!("{}", hunks);
+            }
+            continue;
+        }
+        if let Some(parent) = change.new_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&change.new_path, &change.new_text)?;
+        if let Some(old_path) = &change.old_path {
+            if *old_path != change.new_path {
+                fs::remove_file(old_path)?;
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 //! Applies structured search replace rules from the command line.
 
-use ide_ssr::MatchFinder;
+use ide_ssr::{parse_rule_file, MatchFinder};
 use project_model::CargoConfig;
 
 use crate::cli::{
@@ -11,7 +11,14 @@ use crate::cli::{
 
 impl flags::Ssr {
     pub fn run(self) -> Result<()> {
-        use ide_db::base_db::SourceDatabaseExt;
+        use ide_db::{base_db::SourceDatabaseExt, LineIndexDatabase};
+        if self.check && self.fix {
+            anyhow::bail!("`--check` and `--fix` can't be used together");
+        }
+        let named_rules = match &self.rules {
+            Some(path) => parse_rule_file(&std::fs::read_to_string(path)?)?,
+            None => Vec::new(),
+        };
         let cargo_config = CargoConfig::default();
         let load_cargo_config = LoadCargoConfig {
             load_out_dirs_from_check: true,
@@ -25,10 +32,48 @@ impl flags::Ssr {
             &|_| {},
         )?;
         let db = host.raw_database();
+
+        if self.check {
+            let rules = self
+                .rule
+                .into_iter()
+                .enumerate()
+                .map(|(idx, rule)| (format!("command line rule #{}", idx + 1), String::new(), rule))
+                .chain(
+                    named_rules.iter().map(|it| {
+                        (format!("rule `{}`", it.name), it.description.clone(), it.rule())
+                    }),
+                );
+            let mut n_matches = 0;
+            for (name, description, rule) in rules {
+                // Use a match finder per rule, so that we know which rule matched.
+                let mut match_finder = MatchFinder::at_first_file(db)?;
+                match_finder.add_rule(rule)?;
+                for m in match_finder.matches().matches {
+                    n_matches += 1;
+                    let range = m.range();
+                    let line_col = db.line_index(range.file_id).line_col(range.range.start());
+                    let path = vfs.file_path(range.file_id);
+                    print!("{}:{}:{}: {}", path, line_col.line + 1, line_col.col + 1, name);
+                    if !description.is_empty() {
+                        print!(": {}", description);
+                    }
+                    println!();
+                }
+            }
+            if n_matches > 0 {
+                anyhow::bail!("found {} matches of SSR rules", n_matches);
+            }
+            return Ok(());
+        }
+
         let mut match_finder = MatchFinder::at_first_file(db)?;
         for rule in self.rule {
             match_finder.add_rule(rule)?;
         }
+        for named_rule in &named_rules {
+            match_finder.add_rule(named_rule.rule())?;
+        }
         let edits = match_finder.edits();
         for (file_id, edit) in edits {
             if let Some(path) = vfs.file_path(file_id).as_path() {
@@ -0,0 +1,73 @@
+//! Lists the public items of the workspace that nothing in the workspace uses.
+//! Exits with a non-zero status code if there are any.
+
+use std::fs;
+
+use rustc_hash::FxHashSet;
+use vfs::AbsPathBuf;
+
+use ide_db::{base_db::SourceDatabaseExt, LineIndexDatabase};
+
+use crate::cli::{
+    diagnostics::{all_modules, relative_path},
+    flags,
+    load_cargo::{load_workspace_at, LoadCargoConfig},
+};
+
+impl flags::UnusedItems {
+    pub fn run(self) -> anyhow::Result<()> {
+        let mut root = fs::canonicalize(&self.path)?;
+        if root.is_file() {
+            root.pop();
+        }
+        let root = AbsPathBuf::assert(root);
+        let cargo_config = Default::default();
+        let load_cargo_config = LoadCargoConfig {
+            load_out_dirs_from_check: !self.disable_build_scripts,
+            with_proc_macro: !self.disable_proc_macros,
+            prefill_caches: false,
+        };
+        let (host, vfs, _proc_macro) =
+            load_workspace_at(&self.path, &cargo_config, &load_cargo_config, &|_| {})?;
+        let db = host.raw_database();
+        let analysis = host.analysis();
+
+        let mut files = Vec::new();
+        let mut visited_files = FxHashSet::default();
+        for module in all_modules(db) {
+            let file_id = module.definition_source(db).file_id.original_file(db);
+            let source_root = db.source_root(db.file_source_root(file_id));
+            if source_root.is_library || !visited_files.insert(file_id) {
+                continue;
+            }
+            if let Some(path) = vfs.file_path(file_id).as_path() {
+                files.push((path.to_path_buf(), file_id));
+            }
+        }
+        files.sort();
+
+        let mut count = 0;
+        for (path, file_id) in files {
+            let line_index = db.line_index(file_id);
+            for item in analysis.unused_public_items(file_id)? {
+                let pos = line_index.line_col(item.range.range.start());
+                let name = item.def.name(db).map(|it| it.to_string()).unwrap_or_default();
+                println!(
+                    "{}:{}:{}: {} `{}` is never used",
+                    relative_path(&root, &path),
+                    pos.line + 1,
+                    pos.col + 1,
+                    item.kind,
+                    name
+                );
+                count += 1;
+            }
+        }
+
+        match count {
+            0 => Ok(()),
+            1 => anyhow::bail!("found 1 unused public item"),
+            n => anyhow::bail!("found {} unused public items", n),
+        }
+    }
+}
@@ -7,7 +7,7 @@
 //! configure the server itself, feature flags are passed into analysis, and
 //! tweak things like automatic insertion of `()` in completions.
 
-use std::{ffi::OsString, iter, path::PathBuf};
+use std::{ffi::OsString, iter, path::PathBuf, time::Duration};
 
 use flycheck::FlycheckConfig;
 use ide::{
@@ -18,7 +18,9 @@ use ide_db::helpers::{
     insert_use::{ImportGranularity, InsertUseConfig, PrefixKind},
     SnippetCap,
 };
+use ide_ssr::{parse_rule_file, NamedSsrRule};
 use lsp_types::{ClientCapabilities, MarkupKind};
+use proc_macro_api::ProcMacroServerConfig;
 use project_model::{
     CargoConfig, ProjectJson, ProjectJsonData, ProjectManifest, RustcSource, UnsetTestCrates,
 };
@@ -76,10 +78,23 @@ config_data! {
         /// Use `RUSTC_WRAPPER=rust-analyzer` when running build scripts to
         /// avoid compiling unnecessary things.
         cargo_useRustcWrapperForBuildScripts: bool = "true",
+        /// Load build script outputs from this JSON file instead of running
+        /// `cargo check`, unless it is missing or older than the lockfile, a
+        /// manifest or a build script. The file maps package ids (or names)
+        /// to objects with `cfgs`, `envs`, `out_dir` and
+        /// `proc_macro_dylib_path` fields.
+        cargo_buildScriptOutputs: Option<PathBuf> = "null",
         /// Do not activate the `default` feature.
         cargo_noDefaultFeatures: bool    = "false",
         /// Compilation target (target triple).
         cargo_target: Option<String>     = "null",
+        /// Additional compilation targets to analyze workspace crates for.
+        /// Each target gets its own instance of the workspace members, so
+        /// code behind `#[cfg]`s for any of them is analyzed, and diagnostics
+        /// are merged across targets. Dependencies and the sysroot are only
+        /// analyzed for the primary target, and members with a build script
+        /// are skipped, as its outputs differ between targets.
+        cargo_extraTargets: Vec<String>  = "[]",
         /// Internal config for debugging, disables loading of sysroot crates.
         cargo_noSysroot: bool            = "false",
 
@@ -106,6 +121,13 @@ config_data! {
         /// checking. The command should include `--message-format=json` or
         /// similar option.
         checkOnSave_overrideCommand: Option<Vec<String>> = "null",
+        /// Only check the package containing the saved file and the workspace
+        /// members depending on it (`-p`), instead of the whole workspace.
+        checkOnSave_perPackage: bool                     = "false",
+        /// Also check the contents of unsaved editor buffers, by running the
+        /// check in a temporary copy of the workspace. The check is then
+        /// re-run as files are edited, not just on save.
+        checkOnSave_unsavedBuffers: bool                 = "false",
 
         /// Whether to add argument snippets when completing functions.
         /// Only applies when `#rust-analyzer.completion.addCallParenthesis#` is set.
@@ -122,6 +144,10 @@ config_data! {
         /// Toggles the additional completions that automatically show method calls and field accesses
         /// with `self` prefixed to them when inside a method.
         completion_autoself_enable: bool       = "true",
+        /// Whether to complete whole expressions of the expected type, built
+        /// from locals, fields, method calls and constructors. The search can
+        /// be slow in scopes with many locals and methods.
+        completion_termSearch_enable: bool     = "false",
 
         /// Whether to show native rust-analyzer diagnostics.
         diagnostics_enable: bool                = "true",
@@ -143,6 +169,10 @@ config_data! {
         /// The warnings will be indicated by a blue squiggly underline in code
         /// and a blue icon in the `Problems Panel`.
         diagnostics_warningsAsInfo: Vec<String> = "[]",
+        /// Whether to report `pub` items that nothing in the workspace uses.
+        /// This searches the whole workspace for usages of every public item
+        /// in a file, so it can be slow in large workspaces.
+        diagnostics_unusedPublicItems_enable: bool = "false",
 
         /// Expand attribute macros.
         experimental_procAttrMacros: bool = "true",
@@ -255,6 +285,13 @@ config_data! {
         /// Internal config, path to proc-macro server executable (typically,
         /// this is rust-analyzer itself, but we override this in tests).
         procMacro_server: Option<PathBuf>          = "null",
+        /// Number of seconds a single proc-macro expansion may take before the
+        /// proc-macro server is considered stuck and restarted. `0` disables
+        /// the timeout.
+        procMacro_timeout: usize                   = "30",
+        /// Run the proc macros of every crate in a separate proc-macro server
+        /// process, so that a crashing macro only affects its own crate.
+        procMacro_processPerCrate: bool            = "false",
 
         /// Command to be executed instead of 'cargo' for runnables.
         runnables_overrideCargo: Option<String> = "null",
@@ -282,6 +319,22 @@ config_data! {
         /// available on a nightly build.
         rustfmt_enableRangeFormatting: bool = "false",
 
+        /// Paths to `.ssr` files with named structural search replace rules,
+        /// relative to the workspace root. Matches of the rules are reported
+        /// as diagnostics, with the replacement as a quick fix.
+        ssr_ruleFiles: Vec<PathBuf> = "[]",
+
+        /// Command to run when a Rust file that doesn't belong to any loaded
+        /// workspace is opened, with the path of the file as last argument.
+        /// It should print the `rust-project.json` of the build target
+        /// owning the file, which is then added to the workspace. This is
+        /// meant for build systems like Bazel or Buck.
+        workspace_discoverCommand: Option<Vec<String>> = "null",
+        /// Directory to persist the workspace symbol indices of library
+        /// crates to, so that they don't have to be rebuilt after a restart.
+        /// Relative paths are resolved against the workspace root. Entries
+        /// of other rust-analyzer versions are removed. Disabled if unset.
+        workspace_symbol_cache_directory: Option<PathBuf> = "null",
         /// Workspace symbol search scope.
         workspace_symbol_search_scope: WorkspaceSymbolSearchScopeDef = "\"workspace\"",
         /// Workspace symbol search kind.
@@ -303,6 +356,7 @@ pub struct Config {
     pub discovered_projects: Option<Vec<ProjectManifest>>,
     pub root_path: AbsPathBuf,
     snippets: Vec<Snippet>,
+    ssr_rules: Vec<NamedSsrRule>,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -440,6 +494,7 @@ impl Config {
             discovered_projects: None,
             root_path,
             snippets: Default::default(),
+            ssr_rules: Vec::new(),
         }
     }
     pub fn update(&mut self, mut json: serde_json::Value) {
@@ -474,6 +529,21 @@ impl Config {
                 None => tracing::info!("Invalid snippet {}", name),
             }
         }
+        self.reload_ssr_rules();
+    }
+
+    /// Reads the SSR rule files again, after they changed on disk.
+    pub fn reload_ssr_rules(&mut self) {
+        self.ssr_rules.clear();
+        for path in self.ssr_rule_files() {
+            let rules = std::fs::read_to_string(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| parse_rule_file(&text).map_err(|err| err.to_string()));
+            match rules {
+                Ok(rules) => self.ssr_rules.extend(rules),
+                Err(err) => tracing::error!("Invalid SSR rule file {}: {}", path.display(), err),
+            }
+        }
     }
 
     pub fn json_schema() -> serde_json::Value {
@@ -527,6 +597,10 @@ impl Config {
         }
     }
 
+    pub fn discover_command(&self) -> Option<&[String]> {
+        self.data.workspace_discoverCommand.as_deref().filter(|it| !it.is_empty())
+    }
+
     pub fn detached_files(&self) -> &[AbsPathBuf] {
         &self.detached_files
     }
@@ -644,8 +718,13 @@ impl Config {
         DiagnosticsConfig {
             disable_experimental: !self.data.diagnostics_enableExperimental,
             disabled: self.data.diagnostics_disabled.clone(),
+            ssr_rules: self.ssr_rules.clone(),
+            unused_public_items: self.data.diagnostics_unusedPublicItems_enable,
         }
     }
+    pub fn ssr_rule_files(&self) -> Vec<AbsPathBuf> {
+        self.data.ssr_ruleFiles.iter().map(|it| self.root_path.join(it)).collect()
+    }
     pub fn diagnostics_map(&self) -> DiagnosticsMapConfig {
         DiagnosticsMapConfig {
             remap_prefix: self.data.diagnostics_remapPrefix.clone(),
@@ -656,6 +735,10 @@ impl Config {
     pub fn lru_capacity(&self) -> Option<usize> {
         self.data.lruCapacity
     }
+    pub fn library_symbols_cache_dir(&self) -> Option<AbsPathBuf> {
+        let dir = self.data.workspace_symbol_cache_directory.as_ref()?;
+        Some(self.root_path.join(dir))
+    }
     pub fn proc_macro_srv(&self) -> Option<(AbsPathBuf, Vec<OsString>)> {
         if !self.data.procMacro_enable {
             return None;
@@ -666,6 +749,20 @@ impl Config {
         };
         Some((path, vec!["proc-macro".into()]))
     }
+    /// Whether to look for a proc-macro server shipped with the workspace's
+    /// toolchain before falling back to [`Config::proc_macro_srv`].
+    pub fn proc_macro_srv_from_sysroot(&self) -> bool {
+        self.data.procMacro_enable && self.data.procMacro_server.is_none()
+    }
+    pub fn proc_macro_srv_config(&self) -> ProcMacroServerConfig {
+        ProcMacroServerConfig {
+            timeout: match self.data.procMacro_timeout {
+                0 => None,
+                secs => Some(Duration::from_secs(secs as u64)),
+            },
+            process_per_dylib: self.data.procMacro_processPerCrate,
+        }
+    }
     pub fn expand_proc_attr_macros(&self) -> bool {
         self.data.experimental_procAttrMacros
     }
@@ -701,10 +798,16 @@ impl Config {
             all_features: self.data.cargo_allFeatures,
             features: self.data.cargo_features.clone(),
             target: self.data.cargo_target.clone(),
+            extra_targets: self.data.cargo_extraTargets.clone(),
             no_sysroot: self.data.cargo_noSysroot,
             rustc_source,
             unset_test_crates: UnsetTestCrates::Only(self.data.cargo_unsetTest.clone()),
             wrap_rustc_in_build_scripts: self.data.cargo_useRustcWrapperForBuildScripts,
+            build_script_outputs: self
+                .data
+                .cargo_buildScriptOutputs
+                .as_ref()
+                .map(|it| self.root_path.join(it)),
         }
     }
 
@@ -721,6 +824,12 @@ impl Config {
             },
         }
     }
+    pub fn flycheck_per_package(&self) -> bool {
+        self.data.checkOnSave_perPackage
+    }
+    pub fn flycheck_unsaved_buffers(&self) -> bool {
+        self.data.checkOnSave_unsavedBuffers
+    }
     pub fn flycheck(&self) -> Option<FlycheckConfig> {
         if !self.data.checkOnSave_enable {
             return None;
@@ -795,6 +904,7 @@ impl Config {
             enable_imports_on_the_fly: self.data.completion_autoimport_enable
                 && completion_item_edit_resolve(&self.caps),
             enable_self_on_the_fly: self.data.completion_autoself_enable,
+            enable_term_search: self.data.completion_termSearch_enable,
             add_call_parenthesis: self.data.completion_addCallParenthesis,
             add_call_argument_snippets: self.data.completion_addCallArgumentSnippets,
             insert_use: self.insert_use_config(),
@@ -896,6 +1006,30 @@ impl Config {
     pub fn semantic_tokens_refresh(&self) -> bool {
         try_or!(self.caps.workspace.as_ref()?.semantic_tokens.as_ref()?.refresh_support?, false)
     }
+    pub fn pull_diagnostics(&self) -> bool {
+        try_or!(self.caps.text_document.as_ref()?.diagnostic.is_some(), false)
+    }
+    pub fn workspace_diagnostics_refresh(&self) -> bool {
+        try_or!(self.caps.workspace.as_ref()?.diagnostic.as_ref()?.refresh_support?, false)
+    }
+    pub fn inlay_hints_refresh(&self) -> bool {
+        try_or!(self.caps.workspace.as_ref()?.inlay_hint.as_ref()?.refresh_support?, false)
+    }
+    pub fn inlay_hints_resolve_tooltip(&self) -> bool {
+        try_or!(
+            self.caps
+                .text_document
+                .as_ref()?
+                .inlay_hint
+                .as_ref()?
+                .resolve_support
+                .as_ref()?
+                .properties
+                .iter()
+                .any(|it| it == "tooltip"),
+            false
+        )
+    }
     pub fn code_lens_refresh(&self) -> bool {
         try_or!(self.caps.workspace.as_ref()?.code_lens.as_ref()?.refresh_support?, false)
     }
@@ -1176,6 +1310,10 @@ fn field_props(field: &str, ty: &str, doc: &[&str], default: &str) -> serde_json
         "FxHashMap<String, String>" => set! {
             "type": "object",
         },
+        "usize" => set! {
+            "type": "integer",
+            "minimum": 0,
+        },
         "Option<usize>" => set! {
             "type": ["null", "integer"],
             "minimum": 0,
@@ -4,11 +4,17 @@ pub(crate) mod to_proto;
 use std::{mem, sync::Arc};
 
 use ide::FileId;
+use ide_db::base_db::CrateId;
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::lsp_ext;
 
-pub(crate) type CheckFixes = Arc<FxHashMap<FileId, Vec<Fix>>>;
+/// Fixes from `cargo check`, keyed by the package they were reported for.
+pub(crate) type CheckFixes = Arc<FxHashMap<Option<String>, FxHashMap<FileId, Vec<Fix>>>>;
+
+/// Diagnostics from `cargo check`, keyed by the package they were reported for.
+pub(crate) type CheckDiagnostics =
+    Arc<FxHashMap<Option<String>, FxHashMap<FileId, Vec<lsp_types::Diagnostic>>>>;
 
 #[derive(Debug, Default, Clone)]
 pub struct DiagnosticsMapConfig {
@@ -22,11 +28,31 @@ pub(crate) struct DiagnosticCollection {
     // FIXME: should be FxHashMap<FileId, Vec<ra_id::Diagnostic>>
     pub(crate) native: FxHashMap<FileId, Vec<lsp_types::Diagnostic>>,
     // FIXME: should be Vec<flycheck::Diagnostic>
-    pub(crate) check: FxHashMap<FileId, Vec<lsp_types::Diagnostic>>,
+    /// Diagnostics from `cargo check`, keyed by the id of the package they
+    /// were reported for, so that a check of some packages only replaces
+    /// their diagnostics.
+    pub(crate) check: CheckDiagnostics,
     pub(crate) check_fixes: CheckFixes,
+    /// Tells whether the native diagnostics of a file can have changed since
+    /// they were last pulled.
+    pub(crate) native_revisions: Arc<NativeRevisions>,
+    /// Whether clients pulling diagnostics have to be asked to pull again,
+    /// because diagnostics changed without them knowing.
+    refresh_pulled: bool,
     changes: FxHashSet<FileId>,
 }
 
+/// Revisions of the inputs of native diagnostics. A file's diagnostics only
+/// depend on its crates and their dependencies, so pulled diagnostics of files
+/// elsewhere don't have to be computed again after an edit.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct NativeRevisions {
+    /// Bumped on changes that can affect any file, like a new crate graph.
+    pub(crate) global: u64,
+    /// Bumped when a file of the crate or of one of its dependencies changes.
+    pub(crate) crates: FxHashMap<CrateId, u64>,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) struct Fix {
     pub(crate) range: lsp_types::Range,
@@ -36,28 +62,46 @@ pub(crate) struct Fix {
 impl DiagnosticCollection {
     pub(crate) fn clear_check(&mut self) {
         Arc::make_mut(&mut self.check_fixes).clear();
-        self.changes.extend(self.check.drain().map(|(key, _value)| key))
+        self.changes
+            .extend(Arc::make_mut(&mut self.check).drain().flat_map(|(_, files)| files.into_keys()))
+    }
+
+    pub(crate) fn clear_check_for_packages(&mut self, package_ids: &[String]) {
+        let check_fixes = Arc::make_mut(&mut self.check_fixes);
+        for package_id in package_ids {
+            let package_id = Some(package_id.clone());
+            check_fixes.remove(&package_id);
+            if let Some(files) = Arc::make_mut(&mut self.check).remove(&package_id) {
+                self.changes.extend(files.into_keys());
+            }
+        }
     }
 
     pub(crate) fn add_check_diagnostic(
         &mut self,
+        package_id: Option<String>,
         file_id: FileId,
         diagnostic: lsp_types::Diagnostic,
         fixes: Vec<lsp_ext::CodeAction>,
     ) {
-        let diagnostics = self.check.entry(file_id).or_default();
-        for existing_diagnostic in diagnostics.iter() {
-            if are_diagnostics_equal(existing_diagnostic, &diagnostic) {
-                return;
-            }
+        let mut existing = self.check.values().filter_map(|files| files.get(&file_id)).flatten();
+        if existing.any(|it| are_diagnostics_equal(it, &diagnostic)) {
+            return;
         }
 
         let check_fixes = Arc::make_mut(&mut self.check_fixes);
         check_fixes
+            .entry(package_id.clone())
+            .or_default()
             .entry(file_id)
             .or_default()
             .extend(fixes.into_iter().map(|action| Fix { range: diagnostic.range, action }));
-        diagnostics.push(diagnostic);
+        Arc::make_mut(&mut self.check)
+            .entry(package_id)
+            .or_default()
+            .entry(file_id)
+            .or_default()
+            .push(diagnostic);
         self.changes.insert(file_id);
     }
 
@@ -85,9 +129,8 @@ impl DiagnosticCollection {
         &self,
         file_id: FileId,
     ) -> impl Iterator<Item = &lsp_types::Diagnostic> {
-        let native = self.native.get(&file_id).into_iter().flatten();
-        let check = self.check.get(&file_id).into_iter().flatten();
-        native.chain(check)
+        let native = self.native.get(&file_id).map_or(&[][..], Vec::as_slice);
+        merge_with_check(native, &self.check, file_id)
     }
 
     pub(crate) fn take_changes(&mut self) -> Option<FxHashSet<FileId>> {
@@ -96,6 +139,80 @@ impl DiagnosticCollection {
         }
         Some(mem::take(&mut self.changes))
     }
+
+    /// Notes that the native diagnostics of any file may have changed.
+    pub(crate) fn invalidate_all_native(&mut self) {
+        Arc::make_mut(&mut self.native_revisions).global += 1;
+        self.refresh_pulled = true;
+    }
+
+    /// Notes that the native diagnostics of the files of `crates` may have
+    /// changed.
+    pub(crate) fn invalidate_native(&mut self, crates: impl IntoIterator<Item = CrateId>) {
+        let revisions = &mut Arc::make_mut(&mut self.native_revisions).crates;
+        for krate in crates {
+            *revisions.entry(krate).or_default() += 1;
+        }
+    }
+
+    pub(crate) fn take_refresh_pulled(&mut self) -> bool {
+        mem::take(&mut self.refresh_pulled)
+    }
+}
+
+/// Adds the `cargo check` diagnostics of `file_id` to its native ones.
+pub(crate) fn merge_with_check<'a>(
+    native: &'a [lsp_types::Diagnostic],
+    check: &'a CheckDiagnostics,
+    file_id: FileId,
+) -> impl Iterator<Item = &'a lsp_types::Diagnostic> {
+    let check =
+        check.values().filter_map(|files| files.get(&file_id)).flatten().collect::<Vec<_>>();
+    // Native diagnostics which `cargo check` reported as well are dropped
+    // in favor of the compiler's version.
+    let native = native.iter().filter({
+        let check = check.clone();
+        move |native| !check.iter().any(|check| is_duplicate_of_check_diagnostic(native, check))
+    });
+    native.chain(check)
+}
+
+/// Native diagnostic codes, together with the equivalent rustc (or clippy)
+/// codes.
+const RUSTC_EQUIVALENT_CODES: &[(&str, &[&str])] = &[
+    ("add-reference-here", &["E0308"]),
+    ("break-outside-of-loop", &["E0268"]),
+    ("incorrect-ident-case", &["non_snake_case", "non_camel_case_types", "non_upper_case_globals"]),
+    ("mismatched-arg-count", &["E0057", "E0061"]),
+    ("missing-fields", &["E0063"]),
+    ("missing-match-arm", &["E0004"]),
+    ("missing-ok-or-some-in-tail-expr", &["E0308"]),
+    ("missing-unsafe", &["E0133"]),
+    ("no-such-field", &["E0559", "E0560"]),
+    ("remove-this-semicolon", &["E0308"]),
+    ("replace-filter-map-next-with-find-map", &["filter_map_next"]),
+    ("unresolved-extern-crate", &["E0463"]),
+    ("unresolved-import", &["E0432"]),
+    ("unresolved-module", &["E0583"]),
+];
+
+pub(crate) fn is_duplicate_of_check_diagnostic(
+    native: &lsp_types::Diagnostic,
+    check: &lsp_types::Diagnostic,
+) -> bool {
+    let code = |d: &lsp_types::Diagnostic| match &d.code {
+        Some(lsp_types::NumberOrString::String(code)) => Some(code.clone()),
+        Some(lsp_types::NumberOrString::Number(code)) => Some(code.to_string()),
+        None => None,
+    };
+    let (native_code, check_code) = match (code(native), code(check)) {
+        (Some(native_code), Some(check_code)) => (native_code, check_code),
+        _ => return false,
+    };
+    native.range == check.range
+        && RUSTC_EQUIVALENT_CODES
+            .iter()
+            .any(|(native, rustc)| *native == native_code && rustc.contains(&check_code.as_str()))
 }
 
 fn are_diagnostics_equal(left: &lsp_types::Diagnostic, right: &lsp_types::Diagnostic) -> bool {
@@ -104,3 +221,41 @@ fn are_diagnostics_equal(left: &lsp_types::Diagnostic, right: &lsp_types::Diagno
         && left.range == right.range
         && left.message == right.message
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnostic(code: &str, line: u32) -> lsp_types::Diagnostic {
+        lsp_types::Diagnostic {
+            range: lsp_types::Range::new(
+                lsp_types::Position::new(line, 4),
+                lsp_types::Position::new(line, 8),
+            ),
+            code: Some(lsp_types::NumberOrString::String(code.to_string())),
+            message: code.to_string(),
+            ..lsp_types::Diagnostic::default()
+        }
+    }
+
+    #[test]
+    fn native_duplicates_of_check_diagnostics_are_dropped() {
+        let file_id = FileId(0);
+        let mut diagnostics = DiagnosticCollection::default();
+        diagnostics.set_native_diagnostics(
+            file_id,
+            vec![
+                diagnostic("missing-fields", 1),
+                diagnostic("unresolved-import", 2),
+                diagnostic("missing-unsafe", 3),
+            ],
+        );
+        diagnostics.add_check_diagnostic(None, file_id, diagnostic("E0063", 1), Vec::new());
+        diagnostics.add_check_diagnostic(None, file_id, diagnostic("E0432", 5), Vec::new());
+        diagnostics.add_check_diagnostic(None, file_id, diagnostic("E0308", 3), Vec::new());
+
+        let codes =
+            diagnostics.diagnostics_for(file_id).map(|it| it.message.as_str()).collect::<Vec<_>>();
+        assert_eq!(codes, vec!["unresolved-import", "missing-unsafe", "E0063", "E0432", "E0308"]);
+    }
+}
@@ -62,19 +62,21 @@ fn location(
 
 /// Extracts a suitable "primary" location from a rustc diagnostic.
 ///
-/// This takes locations pointing into the standard library, or generally outside the current
-/// workspace into account and tries to avoid those, in case macros are involved.
+/// For errors inside macro expansions, this follows the expansion chain back to the outermost
+/// macro call within the current workspace, as that's the code the user wrote. Locations
+/// pointing into the standard library, or generally outside the current workspace, are avoided.
 fn primary_location(
     config: &DiagnosticsMapConfig,
     workspace_root: &AbsPath,
     span: &DiagnosticSpan,
 ) -> lsp_types::Location {
     let span_stack = std::iter::successors(Some(span), |span| Some(&span.expansion.as_ref()?.span));
-    for span in span_stack.clone() {
+    let in_workspace = span_stack.clone().filter(|span| {
         let abs_path = resolve_path(config, workspace_root, &span.file_name);
-        if !is_dummy_macro_file(&span.file_name) && abs_path.starts_with(workspace_root) {
-            return location(config, workspace_root, span);
-        }
+        !is_dummy_macro_file(&span.file_name) && abs_path.starts_with(workspace_root)
+    });
+    if let Some(span) = in_workspace.last() {
+        return location(config, workspace_root, span);
     }
 
     // Fall back to the outermost macro invocation if no suitable span comes up.
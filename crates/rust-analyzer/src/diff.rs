@@ -1,4 +1,4 @@
-//! Generate minimal `TextEdit`s from different text versions
+//! Generate minimal `TextEdit`s and unified diffs from different text versions
 use dissimilar::Chunk;
 use ide::{TextEdit, TextRange, TextSize};
 
@@ -38,10 +38,159 @@ fn textedit_from_chunks(chunks: Vec<dissimilar::Chunk>) -> TextEdit {
     builder.finish()
 }
 
+/// Renders the changes from `left` to `right` as the hunks of a unified diff,
+/// with three lines of context.
+pub(crate) fn unified_diff(left: &str, right: &str) -> String {
+    const CONTEXT: usize = 3;
+
+    let left: Vec<&str> = left.split_inclusive('\n').collect();
+    let right: Vec<&str> = right.split_inclusive('\n').collect();
+    let ops = diff_lines(&left, &right);
+
+    let mut res = String::new();
+    let (mut idx, mut left_line, mut right_line) = (0, 1, 1);
+    while let Some(first_change) = ops[idx..].iter().position(|(op, _)| *op != ' ') {
+        let first_change = idx + first_change;
+        let start = first_change.saturating_sub(CONTEXT).max(idx);
+        // Changes separated by at most twice the context go into one hunk.
+        let mut end = first_change;
+        loop {
+            while end < ops.len() && ops[end].0 != ' ' {
+                end += 1;
+            }
+            match ops[end..].iter().position(|(op, _)| *op != ' ') {
+                Some(equal) if equal <= 2 * CONTEXT => end += equal,
+                _ => break,
+            }
+        }
+        let end = (end + CONTEXT).min(ops.len());
+
+        for (op, _) in &ops[idx..start] {
+            left_line += (*op != '+') as usize;
+            right_line += (*op != '-') as usize;
+        }
+        let hunk = &ops[start..end];
+        let left_len = hunk.iter().filter(|(op, _)| *op != '+').count();
+        let right_len = hunk.iter().filter(|(op, _)| *op != '-').count();
+        // An empty range starts at the line before it.
+        let start_line = |line: usize, len: usize| if len == 0 { line - 1 } else { line };
+        res.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            start_line(left_line, left_len),
+            left_len,
+            start_line(right_line, right_len),
+            right_len
+        ));
+        for (op, line) in hunk {
+            res.push(*op);
+            res.push_str(line);
+            if !line.ends_with('\n') {
+                res.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+        left_line += left_len;
+        right_line += right_len;
+        idx = end;
+    }
+    res
+}
+
+/// Myers' diff of two sequences of lines, as a list of `' '`, `'-'` and `'+'`
+/// operations with the line they apply to.
+fn diff_lines<'a>(left: &[&'a str], right: &[&'a str]) -> Vec<(char, &'a str)> {
+    let (n, m) = (left.len() as isize, right.len() as isize);
+    let offset = n + m + 1;
+    let mut v = vec![0isize; 2 * offset as usize + 1];
+    // The furthest reaching paths after each number of edits, to backtrack
+    // through once we reach the end.
+    let mut trace = Vec::new();
+    'search: for d in 0..=n + m {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let idx = (k + offset) as usize;
+            let mut x = if k == -d || (k != d && v[idx - 1] < v[idx + 1]) {
+                v[idx + 1]
+            } else {
+                v[idx - 1] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && left[x as usize] == right[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[idx] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let prev_k =
+            if k == -d || (k != d && v[(k - 1 + offset) as usize] < v[(k + 1 + offset) as usize]) {
+                k + 1
+            } else {
+                k - 1
+            };
+        let prev_x = if d == 0 { 0 } else { v[(prev_k + offset) as usize] };
+        let prev_y = if d == 0 { 0 } else { prev_x - prev_k };
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push((' ', left[x as usize]));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(('+', right[prev_y as usize]));
+            } else {
+                ops.push(('-', left[prev_x as usize]));
+            }
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    ops.reverse();
+    ops
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn unified_diff_hunks() {
+        let left = (1..=20).map(|i| format!("line {}\n", i)).collect::<String>();
+        let right =
+            left.replace("line 2\n", "two\n").replace("line 15\n", "").replace("line 20\n", "end");
+        expect_test::expect![[r#"
+            @@ -1,5 +1,5 @@
+             line 1
+            -line 2
+            +two
+             line 3
+             line 4
+             line 5
+            @@ -12,9 +12,8 @@
+             line 12
+             line 13
+             line 14
+            -line 15
+             line 16
+             line 17
+             line 18
+             line 19
+            -line 20
+            +end
+            \ No newline at end of file
+        "#]]
+        .assert_eq(&unified_diff(&left, &right));
+        assert_eq!(unified_diff(&left, &left), "");
+        assert_eq!(unified_diff("", "a\n"), "@@ -0,0 +1,1 @@\n+a\n");
+    }
+
     #[test]
     fn diff_applies() {
         let mut original = String::from("fn foo(a:u32){\n}");
@@ -1,6 +1,7 @@
 //! See [RequestDispatcher].
 use std::{fmt, panic, thread};
 
+use crossbeam_channel::Sender;
 use serde::{de::DeserializeOwned, Serialize};
 
 use crate::{
@@ -111,6 +112,38 @@ impl<'a> RequestDispatcher<'a> {
         self
     }
 
+    /// Dispatches the request onto thread pool, like [`RequestDispatcher::on`],
+    /// but lets the handler send tasks before its response, e.g. to stream
+    /// partial results.
+    pub(crate) fn on_with_sender<R>(
+        &mut self,
+        f: fn(GlobalStateSnapshot, R::Params, &Sender<Task>) -> Result<R::Result>,
+    ) -> &mut Self
+    where
+        R: lsp_types::request::Request + 'static,
+        R::Params: DeserializeOwned + panic::UnwindSafe + Send + fmt::Debug + 'static,
+        R::Result: Serialize + 'static,
+    {
+        let (id, params, panic_context) = match self.parse::<R>() {
+            Some(it) => it,
+            None => return self,
+        };
+
+        self.global_state.task_pool.handle.spawn_with_sender({
+            let world = self.global_state.snapshot();
+            move |sender| {
+                let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                    let _pctx = stdx::panic_context::enter(panic_context);
+                    f(world, params, &sender)
+                }));
+                let response = thread_result_to_response::<R>(id, result);
+                sender.send(Task::Response(response)).unwrap();
+            }
+        });
+
+        self
+    }
+
     pub(crate) fn finish(&mut self) {
         if let Some(req) = self.req.take() {
             tracing::error!("unknown request: {:?}", req);
@@ -8,17 +8,19 @@ use std::{sync::Arc, time::Instant};
 use crossbeam_channel::{unbounded, Receiver, Sender};
 use flycheck::FlycheckHandle;
 use ide::{Analysis, AnalysisHost, Cancellable, Change, FileId};
-use ide_db::base_db::CrateId;
+use ide_db::base_db::{CrateId, FileLoader, SourceDatabase};
 use lsp_types::{SemanticTokens, Url};
 use parking_lot::{Mutex, RwLock};
 use proc_macro_api::ProcMacroServer;
-use project_model::{CargoWorkspace, ProjectWorkspace, Target, WorkspaceBuildScripts};
-use rustc_hash::FxHashMap;
-use vfs::AnchoredPathBuf;
+use project_model::{
+    Build, CargoWorkspace, ProjectJson, ProjectWorkspace, Target, WorkspaceBuildScripts,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use vfs::{AbsPathBuf, AnchoredPathBuf};
 
 use crate::{
     config::Config,
-    diagnostics::{CheckFixes, DiagnosticCollection},
+    diagnostics::{CheckDiagnostics, CheckFixes, DiagnosticCollection, NativeRevisions},
     from_proto,
     line_index::{LineEndings, LineIndex},
     lsp_ext,
@@ -103,6 +105,15 @@ pub(crate) struct GlobalState {
         OpQueue<(Arc<Vec<ProjectWorkspace>>, Vec<anyhow::Result<WorkspaceBuildScripts>>)>,
 
     pub(crate) prime_caches_queue: OpQueue<()>,
+
+    /// Projects reported by the workspace discovery command, which are loaded
+    /// in addition to the linked projects.
+    pub(crate) discovered_projects: Vec<ProjectJson>,
+    /// Files we already ran the workspace discovery command for.
+    pub(crate) discovery_requested: FxHashSet<AbsPathBuf>,
+    /// Files opened while workspaces were being fetched, to run the workspace
+    /// discovery command for once they are loaded.
+    pub(crate) deferred_discovery: Vec<AbsPathBuf>,
 }
 
 /// An immutable snapshot of the world's state at a point in time.
@@ -110,6 +121,8 @@ pub(crate) struct GlobalStateSnapshot {
     pub(crate) config: Arc<Config>,
     pub(crate) analysis: Analysis,
     pub(crate) check_fixes: CheckFixes,
+    pub(crate) check_diagnostics: CheckDiagnostics,
+    pub(crate) native_diagnostics_revisions: Arc<NativeRevisions>,
     mem_docs: MemDocs,
     pub(crate) semantic_tokens_cache: Arc<Mutex<FxHashMap<Url, SemanticTokens>>>,
     vfs: Arc<RwLock<(vfs::Vfs, FxHashMap<FileId, LineEndings>)>>,
@@ -166,6 +179,10 @@ impl GlobalState {
             prime_caches_queue: OpQueue::default(),
 
             fetch_build_data_queue: OpQueue::default(),
+
+            discovered_projects: Vec::new(),
+            discovery_requested: FxHashSet::default(),
+            deferred_discovery: Vec::new(),
         };
         // Apply any required database inputs from the config.
         this.update_configuration(config);
@@ -177,6 +194,9 @@ impl GlobalState {
         let mut fs_changes = Vec::new();
         // A file was added or deleted
         let mut has_structure_changes = false;
+        let mut ssr_rules_changed = false;
+        let mut changed_file_ids = Vec::new();
+        let ssr_rule_files = self.config.ssr_rule_files();
 
         let change = {
             let mut change = Change::new();
@@ -192,6 +212,9 @@ impl GlobalState {
                     if reload::should_refresh_for_change(&path, file.change_kind) {
                         self.fetch_workspaces_queue.request_op();
                     }
+                    if ssr_rule_files.contains(&path) {
+                        ssr_rules_changed = true;
+                    }
                     fs_changes.push((path, file.change_kind));
                     if file.is_created_or_deleted() {
                         has_structure_changes = true;
@@ -212,6 +235,7 @@ impl GlobalState {
                     None
                 };
                 change.change_file(file.file_id, text);
+                changed_file_ids.push(file.file_id);
             }
             if has_structure_changes {
                 let roots = self.source_root_config.partition(vfs);
@@ -221,6 +245,26 @@ impl GlobalState {
         };
 
         self.analysis_host.apply_change(change);
+        if has_structure_changes {
+            self.diagnostics.invalidate_all_native();
+        } else {
+            let db = self.analysis_host.raw_database();
+            let crate_graph = db.crate_graph();
+            let mut affected_crates = FxHashSet::default();
+            for file_id in changed_file_ids {
+                for &krate in db.relevant_crates(file_id).iter() {
+                    if !affected_crates.contains(&krate) {
+                        affected_crates.extend(crate_graph.transitive_rev_deps(krate));
+                    }
+                }
+            }
+            self.diagnostics.invalidate_native(affected_crates);
+        }
+        if ssr_rules_changed {
+            let mut config = Config::clone(&*self.config);
+            config.reload_ssr_rules();
+            self.update_configuration(config);
+        }
         true
     }
 
@@ -231,6 +275,8 @@ impl GlobalState {
             analysis: self.analysis_host.analysis(),
             vfs: Arc::clone(&self.vfs),
             check_fixes: Arc::clone(&self.diagnostics.check_fixes),
+            check_diagnostics: Arc::clone(&self.diagnostics.check),
+            native_diagnostics_revisions: Arc::clone(&self.diagnostics.native_revisions),
             mem_docs: self.mem_docs.clone(),
             semantic_tokens_cache: Arc::clone(&self.semantic_tokens_cache),
         }
@@ -285,7 +331,7 @@ impl GlobalState {
         }
     }
 
-    fn send(&mut self, message: lsp_server::Message) {
+    pub(crate) fn send(&mut self, message: lsp_server::Message) {
         self.sender.send(message).unwrap()
     }
 }
@@ -312,6 +358,29 @@ impl GlobalStateSnapshot {
         Ok(res)
     }
 
+    /// The Rust files of the workspace, excluding dependencies and the sysroot.
+    pub(crate) fn workspace_files(&self) -> Cancellable<Vec<FileId>> {
+        let files: Vec<_> = self
+            .vfs
+            .read()
+            .0
+            .iter()
+            .filter(|(_, path)| {
+                path.name_and_extension().map_or(false, |(_, ext)| ext == Some("rs"))
+            })
+            .map(|(file_id, _)| file_id)
+            .collect();
+        let mut res = Vec::new();
+        for file_id in files {
+            if !self.analysis.is_library_file(file_id)?
+                && !self.analysis.crate_for(file_id)?.is_empty()
+            {
+                res.push(file_id);
+            }
+        }
+        Ok(res)
+    }
+
     pub(crate) fn url_file_version(&self, url: &Url) -> Option<i32> {
         let path = from_proto::vfs_path(url).ok()?;
         Some(self.mem_docs.get(&path)?.version)
@@ -340,6 +409,19 @@ impl GlobalStateSnapshot {
             ProjectWorkspace::DetachedFiles { .. } => None,
         })
     }
+
+    pub(crate) fn project_json_build_for_crate_root(&self, crate_id: CrateId) -> Option<&Build> {
+        let file_id = self.analysis.crate_root(crate_id).ok()?;
+        let path = self.vfs.read().0.file_path(file_id);
+        let path = path.as_path()?;
+        self.workspaces.iter().find_map(|ws| match ws {
+            ProjectWorkspace::Json { project, .. } => project
+                .crates()
+                .find(|(_, krate)| krate.root_module() == path)
+                .and_then(|(_, krate)| krate.build()),
+            ProjectWorkspace::Cargo { .. } | ProjectWorkspace::DetachedFiles { .. } => None,
+        })
+    }
 }
 
 pub(crate) fn file_id_to_url(vfs: &vfs::Vfs, id: FileId) -> Url {
@@ -3,11 +3,14 @@
 //! `ide` crate.
 
 use std::{
+    hash::{Hash, Hasher},
     io::Write as _,
+    mem,
     process::{self, Stdio},
 };
 
 use anyhow::Context;
+use crossbeam_channel::Sender;
 use ide::{
     AnnotationConfig, AssistKind, AssistResolveStrategy, FileId, FilePosition, FileRange,
     HoverAction, HoverGotoTypeData, Query, RangeInfo, Runnable, RunnableKind, SingleResolve,
@@ -27,6 +30,7 @@ use lsp_types::{
     TextDocumentIdentifier, Url, WorkspaceEdit,
 };
 use project_model::{ManifestPath, ProjectWorkspace, TargetKind};
+use rustc_hash::{FxHashMap, FxHasher};
 use serde_json::json;
 use stdx::{format_to, never};
 use syntax::{algo, ast, AstNode, TextRange, TextSize, T};
@@ -36,7 +40,7 @@ use crate::{
     cargo_target_spec::CargoTargetSpec,
     config::RustfmtConfig,
     diff::diff,
-    from_proto,
+    from_json, from_proto,
     global_state::{GlobalState, GlobalStateSnapshot},
     line_index::LineEndings,
     lsp_ext::{
@@ -44,6 +48,7 @@ use crate::{
         WorkspaceSymbolParams,
     },
     lsp_utils::{all_edits_are_disjoint, invalid_params_error},
+    main_loop::Task,
     to_proto, LspError, Result,
 };
 
@@ -97,6 +102,36 @@ pub(crate) fn handle_memory_usage(state: &mut GlobalState, _: ()) -> Result<Stri
     Ok(out)
 }
 
+pub(crate) fn handle_execute_command(
+    state: &mut GlobalState,
+    params: lsp_types::ExecuteCommandParams,
+) -> Result<Option<serde_json::Value>> {
+    let _p = profile::span("handle_execute_command");
+    let command = lsp_ext::ServerCommand::from_name(&params.command).ok_or_else(|| {
+        LspError::new(
+            ErrorCode::InvalidParams as i32,
+            format!("unknown command: {}", params.command),
+        )
+    })?;
+    match command {
+        lsp_ext::ServerCommand::ReloadWorkspace => state.fetch_workspaces_queue.request_op(),
+        lsp_ext::ServerCommand::RunFlycheck => state.restart_flycheck(None),
+        lsp_ext::ServerCommand::ClearFlycheck => {
+            for flycheck in &state.flycheck {
+                flycheck.cancel();
+            }
+            state.diagnostics.clear_check();
+        }
+        lsp_ext::ServerCommand::RebuildProcMacros => {
+            // As when the proc-macro server config changes, rebuilding the
+            // crate graph respawns the server and reloads the proc-macros.
+            state.proc_macro_client = None;
+            state.fetch_build_data_queue.request_op();
+        }
+    }
+    Ok(None)
+}
+
 pub(crate) fn handle_syntax_tree(
     snap: GlobalStateSnapshot,
     params: lsp_ext::SyntaxTreeParams,
@@ -695,9 +730,10 @@ pub(crate) fn handle_runnables(
             continue;
         }
         let mut runnable = to_proto::runnable(&snap, runnable)?;
-        if expect_test {
+        // Only cargo runnables get `UPDATE_EXPECT` set, shell runnables stay as they are.
+        if let (true, lsp_ext::RunnableArgs::Cargo(args)) = (expect_test, &mut runnable.args) {
             runnable.label = format!("{} + expect", runnable.label);
-            runnable.args.expect_test = Some(true);
+            args.expect_test = Some(true);
         }
         res.push(runnable);
     }
@@ -711,7 +747,7 @@ pub(crate) fn handle_runnables(
                     label: format!("cargo {} -p {} --all-targets", cmd, spec.package),
                     location: None,
                     kind: lsp_ext::RunnableKind::Cargo,
-                    args: lsp_ext::CargoRunnable {
+                    args: lsp_ext::RunnableArgs::Cargo(lsp_ext::CargoRunnable {
                         workspace_root: Some(spec.workspace_root.clone().into()),
                         override_cargo: config.override_cargo.clone(),
                         cargo_args: vec![
@@ -723,7 +759,7 @@ pub(crate) fn handle_runnables(
                         cargo_extra_args: config.cargo_extra_args.clone(),
                         executable_args: Vec::new(),
                         expect_test: None,
-                    },
+                    }),
                 })
             }
         }
@@ -740,14 +776,14 @@ pub(crate) fn handle_runnables(
                     label: "cargo check --workspace".to_string(),
                     location: None,
                     kind: lsp_ext::RunnableKind::Cargo,
-                    args: lsp_ext::CargoRunnable {
+                    args: lsp_ext::RunnableArgs::Cargo(lsp_ext::CargoRunnable {
                         workspace_root: None,
                         override_cargo: config.override_cargo,
                         cargo_args: vec!["check".to_string(), "--workspace".to_string()],
                         cargo_extra_args: config.cargo_extra_args,
                         executable_args: Vec::new(),
                         expect_test: None,
-                    },
+                    }),
                 });
             }
         }
@@ -1073,7 +1109,8 @@ pub(crate) fn handle_code_action(
     }
 
     // Fixes from `cargo check`.
-    for fix in snap.check_fixes.get(&frange.file_id).into_iter().flatten() {
+    let fixes = snap.check_fixes.values().filter_map(|files| files.get(&frange.file_id));
+    for fix in fixes.flatten() {
         // FIXME: this mapping is awkward and shouldn't exist. Refactor
         // `snap.check_fixes` to not convert to LSP prematurely.
         let fix_range = from_proto::text_range(&line_index, fix.range);
@@ -1300,6 +1337,163 @@ pub(crate) fn publish_diagnostics(
     Ok(diagnostics)
 }
 
+pub(crate) fn handle_document_diagnostics(
+    snap: GlobalStateSnapshot,
+    params: lsp_types::DocumentDiagnosticParams,
+) -> Result<lsp_types::DocumentDiagnosticReportResult> {
+    let _p = profile::span("handle_document_diagnostics");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let report = match pull_diagnostics(&snap, file_id, params.previous_result_id.as_deref())? {
+        lsp_types::DocumentDiagnosticReportKind::Full(report) => {
+            lsp_types::DocumentDiagnosticReport::Full(
+                lsp_types::RelatedFullDocumentDiagnosticReport {
+                    related_documents: None,
+                    full_document_diagnostic_report: report,
+                },
+            )
+        }
+        lsp_types::DocumentDiagnosticReportKind::Unchanged(report) => {
+            lsp_types::DocumentDiagnosticReport::Unchanged(
+                lsp_types::RelatedUnchangedDocumentDiagnosticReport {
+                    related_documents: None,
+                    unchanged_document_diagnostic_report: report,
+                },
+            )
+        }
+    };
+    Ok(report.into())
+}
+
+/// Computes the diagnostics of every workspace file. Clients asking for partial
+/// results get them in chunks as they are computed, and an empty response.
+pub(crate) fn handle_workspace_diagnostics(
+    snap: GlobalStateSnapshot,
+    params: lsp_types::WorkspaceDiagnosticParams,
+    sender: &Sender<Task>,
+) -> Result<lsp_types::WorkspaceDiagnosticReportResult> {
+    let _p = profile::span("handle_workspace_diagnostics");
+    const PARTIAL_RESULT_CHUNK_SIZE: usize = 32;
+
+    let previous_result_ids: FxHashMap<_, _> =
+        params.previous_result_ids.into_iter().map(|it| (it.uri, it.value)).collect();
+    let partial_result_token = params.partial_result_params.partial_result_token;
+    let send_partial_result = |token: &lsp_types::ProgressToken, items| {
+        let value = lsp_types::WorkspaceDiagnosticReportPartialResult { items };
+        let not = lsp_server::Notification::new(
+            "$/progress".to_string(),
+            json!({ "token": token, "value": value }),
+        );
+        sender.send(Task::Notification(not)).unwrap();
+    };
+
+    let mut items = Vec::new();
+    // Every file queries the database, so an edit cancels the request between
+    // files at the latest.
+    for file_id in snap.workspace_files()? {
+        let uri = to_proto::url(&snap, file_id);
+        let version = snap.url_file_version(&uri).map(i64::from);
+        let previous_result_id = previous_result_ids.get(&uri).map(String::as_str);
+        let item = match pull_diagnostics(&snap, file_id, previous_result_id)? {
+            lsp_types::DocumentDiagnosticReportKind::Full(report) => {
+                lsp_types::WorkspaceDocumentDiagnosticReport::Full(
+                    lsp_types::WorkspaceFullDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        full_document_diagnostic_report: report,
+                    },
+                )
+            }
+            lsp_types::DocumentDiagnosticReportKind::Unchanged(report) => {
+                lsp_types::WorkspaceDocumentDiagnosticReport::Unchanged(
+                    lsp_types::WorkspaceUnchangedDocumentDiagnosticReport {
+                        uri,
+                        version,
+                        unchanged_document_diagnostic_report: report,
+                    },
+                )
+            }
+        };
+        items.push(item);
+        if let Some(token) = &partial_result_token {
+            if items.len() == PARTIAL_RESULT_CHUNK_SIZE {
+                send_partial_result(token, mem::take(&mut items));
+            }
+        }
+    }
+    if let Some(token) = &partial_result_token {
+        if !items.is_empty() {
+            send_partial_result(token, mem::take(&mut items));
+        }
+    }
+    Ok(lsp_types::WorkspaceDiagnosticReport { items }.into())
+}
+
+/// Computes the native and `cargo check` diagnostics of a file for a pull
+/// request.
+///
+/// Result ids have the form `inputs:hash`. If the inputs of the diagnostics
+/// are the same as for the previous result, they aren't computed again.
+/// Otherwise the hash of the diagnostics tells whether they changed since the
+/// previous result.
+fn pull_diagnostics(
+    snap: &GlobalStateSnapshot,
+    file_id: FileId,
+    previous_result_id: Option<&str>,
+) -> Result<lsp_types::DocumentDiagnosticReportKind> {
+    let inputs = diagnostics_inputs(snap, file_id)?;
+    let previous = previous_result_id.and_then(|it| it.split_once(':'));
+    if let (Some(inputs), Some((previous_inputs, _))) = (&inputs, previous) {
+        if inputs == previous_inputs {
+            let result_id = previous_result_id.unwrap_or_default().to_string();
+            return Ok(lsp_types::UnchangedDocumentDiagnosticReport { result_id }.into());
+        }
+    }
+
+    // Like when pushing diagnostics, library files don't get any.
+    let items: Vec<Diagnostic> = if snap.analysis.is_library_file(file_id)? {
+        Vec::new()
+    } else {
+        let native = if snap.config.publish_diagnostics() {
+            publish_diagnostics(snap, file_id)?
+        } else {
+            Vec::new()
+        };
+        crate::diagnostics::merge_with_check(&native, &snap.check_diagnostics, file_id)
+            .cloned()
+            .collect()
+    };
+
+    let mut hasher = FxHasher::default();
+    serde_json::to_string(&items)?.hash(&mut hasher);
+    let hash = format!("{:x}", hasher.finish());
+    let result_id = format!("{}:{}", inputs.unwrap_or_default(), hash);
+    if previous.map_or(false, |(_, previous_hash)| previous_hash == hash) {
+        return Ok(lsp_types::UnchangedDocumentDiagnosticReport { result_id }.into());
+    }
+    Ok(lsp_types::FullDocumentDiagnosticReport { result_id: Some(result_id), items }.into())
+}
+
+/// Identifies the revisions of everything the diagnostics of `file_id` depend
+/// on: the crates containing it and their dependencies, and its `cargo check`
+/// diagnostics. `None` for files outside of any crate, whose diagnostics are
+/// always computed.
+fn diagnostics_inputs(snap: &GlobalStateSnapshot, file_id: FileId) -> Result<Option<String>> {
+    let crates = snap.analysis.crate_for(file_id)?;
+    if crates.is_empty() {
+        return Ok(None);
+    }
+    let revisions = &snap.native_diagnostics_revisions;
+    let mut hasher = FxHasher::default();
+    for krate in crates {
+        (krate, revisions.crates.get(&krate).copied().unwrap_or_default()).hash(&mut hasher);
+    }
+    let check = snap.check_diagnostics.values().filter_map(|files| files.get(&file_id));
+    for diagnostic in check.flatten() {
+        serde_json::to_string(diagnostic)?.hash(&mut hasher);
+    }
+    Ok(Some(format!("{}.{:x}", revisions.global, hasher.finish())))
+}
+
 pub(crate) fn handle_inlay_hints(
     snap: GlobalStateSnapshot,
     params: InlayHintsParams,
@@ -1315,6 +1509,44 @@ pub(crate) fn handle_inlay_hints(
         .collect())
 }
 
+pub(crate) fn handle_lsp_inlay_hints(
+    snap: GlobalStateSnapshot,
+    params: lsp_types::InlayHintParams,
+) -> Result<Option<Vec<lsp_types::InlayHint>>> {
+    let _p = profile::span("handle_lsp_inlay_hints");
+    let file_id = from_proto::file_id(&snap, &params.text_document.uri)?;
+    let line_index = snap.file_line_index(file_id)?;
+    let range = from_proto::text_range(&line_index, params.range);
+    let resolve_tooltip = snap.config.inlay_hints_resolve_tooltip();
+    let hints = snap
+        .analysis
+        .inlay_hints(&snap.config.inlay_hints(), file_id)?
+        .into_iter()
+        .filter(|it| range.intersect(it.range).is_some())
+        .map(|it| to_proto::lsp_inlay_hint(&snap, &line_index, resolve_tooltip, it))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(hints))
+}
+
+pub(crate) fn handle_inlay_hint_resolve(
+    snap: GlobalStateSnapshot,
+    mut hint: lsp_types::InlayHint,
+) -> Result<lsp_types::InlayHint> {
+    let _p = profile::span("handle_inlay_hint_resolve");
+    let data = match hint.data.take() {
+        Some(it) => it,
+        None => return Ok(hint),
+    };
+    let resolve_data: lsp_ext::InlayHintResolveData = from_json("InlayHintResolveData", data)?;
+    let file_range = from_proto::file_range(&snap, resolve_data.text_document, resolve_data.range)?;
+    if let Some(info) = snap.analysis.hover(&snap.config.hover(), file_range)? {
+        hint.tooltip = Some(lsp_types::InlayHintTooltip::MarkupContent(to_proto::markup_content(
+            info.info.markup,
+        )));
+    }
+    Ok(hint)
+}
+
 pub(crate) fn handle_call_hierarchy_prepare(
     snap: GlobalStateSnapshot,
     params: CallHierarchyPrepareParams,
@@ -181,13 +181,21 @@ pub struct Runnable {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub location: Option<lsp_types::LocationLink>,
     pub kind: RunnableKind,
-    pub args: CargoRunnable,
+    pub args: RunnableArgs,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum RunnableKind {
     Cargo,
+    Shell,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(untagged)]
+pub enum RunnableArgs {
+    Cargo(CargoRunnable),
+    Shell(ShellRunnable),
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -207,6 +215,14 @@ pub struct CargoRunnable {
     pub expect_test: Option<bool>,
 }
 
+#[derive(Deserialize, Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ShellRunnable {
+    pub program: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+}
+
 pub enum RelatedTests {}
 
 impl Request for RelatedTests {
@@ -452,6 +468,14 @@ pub(crate) enum CodeLensResolveData {
     References(lsp_types::TextDocumentPositionParams),
 }
 
+/// The hover shown as the tooltip of an inlay hint, computed on `inlayHint/resolve`.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InlayHintResolveData {
+    pub text_document: TextDocumentIdentifier,
+    pub range: Range,
+}
+
 pub fn supports_utf8(caps: &lsp_types::ClientCapabilities) -> bool {
     caps.offset_encoding.as_deref().unwrap_or_default().iter().any(|it| it == "utf-8")
 }
@@ -533,3 +557,35 @@ pub struct CompletionImport {
 pub struct ClientCommandOptions {
     pub commands: Vec<String>,
 }
+
+/// The commands the server runs for `workspace/executeCommand`, so that
+/// clients which only speak standard LSP can use them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerCommand {
+    ReloadWorkspace,
+    RunFlycheck,
+    ClearFlycheck,
+    RebuildProcMacros,
+}
+
+impl ServerCommand {
+    pub const ALL: [ServerCommand; 4] = [
+        ServerCommand::ReloadWorkspace,
+        ServerCommand::RunFlycheck,
+        ServerCommand::ClearFlycheck,
+        ServerCommand::RebuildProcMacros,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ServerCommand::ReloadWorkspace => "rust-analyzer.server.reloadWorkspace",
+            ServerCommand::RunFlycheck => "rust-analyzer.server.runFlycheck",
+            ServerCommand::ClearFlycheck => "rust-analyzer.server.clearFlycheck",
+            ServerCommand::RebuildProcMacros => "rust-analyzer.server.rebuildProcMacros",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ServerCommand> {
+        ServerCommand::ALL.iter().copied().find(|it| it.name() == name)
+    }
+}
@@ -1,17 +1,22 @@
 //! The main loop of `rust-analyzer` responsible for dispatching LSP
 //! requests/replies and notifications back to the client.
 use std::{
-    fmt,
+    fmt, mem,
     sync::Arc,
     time::{Duration, Instant},
 };
 
 use always_assert::always;
 use crossbeam_channel::{select, Receiver};
-use ide_db::base_db::{SourceDatabaseExt, VfsPath};
+use flycheck::CheckScope;
+use ide_db::{
+    base_db::{salsa::Durability, SourceDatabaseExt, VfsPath},
+    symbol_index::SymbolsDatabase,
+};
 use lsp_server::{Connection, Notification, Request};
 use lsp_types::notification::Notification as _;
-use vfs::{ChangeKind, FileId};
+use project_model::{ProjectJson, ProjectWorkspace};
+use vfs::{AbsPath, AbsPathBuf, ChangeKind, FileId};
 
 use crate::{
     config::Config,
@@ -60,10 +65,14 @@ enum Event {
 #[derive(Debug)]
 pub(crate) enum Task {
     Response(lsp_server::Response),
+    /// A notification on behalf of a request, like its partial results.
+    Notification(lsp_server::Notification),
     Diagnostics(Vec<(FileId, Vec<lsp_types::Diagnostic>)>),
     PrimeCaches(PrimeCachesProgress),
     FetchWorkspace(ProjectWorkspaceProgress),
     FetchBuildData(BuildDataProgress),
+    DiscoverProject(AbsPathBuf, anyhow::Result<ProjectJson>),
+    LoadLibrarySymbols(Option<ide::CachedLibrarySymbols>),
 }
 
 #[derive(Debug)]
@@ -211,6 +220,7 @@ impl GlobalState {
                 loop {
                     match task {
                         Task::Response(response) => self.respond(response),
+                        Task::Notification(not) => self.send(not.into()),
                         Task::Diagnostics(diagnostics_per_file) => {
                             for (file_id, diagnostics) in diagnostics_per_file {
                                 self.diagnostics.set_native_diagnostics(file_id, diagnostics)
@@ -245,6 +255,9 @@ impl GlobalState {
                                     if self.config.run_build_scripts() && workspaces_updated {
                                         self.fetch_build_data_queue.request_op()
                                     }
+                                    for path in mem::take(&mut self.deferred_discovery) {
+                                        self.discover_project(path);
+                                    }
 
                                     (Progress::End, None)
                                 }
@@ -271,6 +284,28 @@ impl GlobalState {
                                 self.report_progress("Loading", state, msg, None);
                             }
                         }
+                        Task::DiscoverProject(path, res) => match res {
+                            Ok(project) => self.add_discovered_project(project),
+                            Err(err) => self.show_message(
+                                lsp_types::MessageType::ERROR,
+                                format!(
+                                    "rust-analyzer failed to discover workspace for {}: {:#}",
+                                    path.display(),
+                                    err
+                                ),
+                            ),
+                        },
+                        Task::LoadLibrarySymbols(symbols) => {
+                            if let Some(symbols) = symbols {
+                                self.analysis_host
+                                    .raw_database_mut()
+                                    .set_cached_library_symbols_with_durability(
+                                        Arc::new(symbols),
+                                        Durability::HIGH,
+                                    );
+                            }
+                            self.prime_caches_queue.request_op();
+                        }
                     }
 
                     // Coalesce multiple task events into one loop turn
@@ -304,6 +339,8 @@ impl GlobalState {
                             self.prime_caches_queue.op_completed(());
                             if cancelled {
                                 self.prime_caches_queue.request_op();
+                            } else {
+                                self.store_library_symbols();
                             }
                         }
                     };
@@ -358,7 +395,11 @@ impl GlobalState {
                 let _p = profile::span("GlobalState::handle_event/flycheck");
                 loop {
                     match task {
-                        flycheck::Message::AddDiagnostic { workspace_root, diagnostic } => {
+                        flycheck::Message::AddDiagnostic {
+                            workspace_root,
+                            package_id,
+                            diagnostic,
+                        } => {
                             let diagnostics =
                                 crate::diagnostics::to_proto::map_rust_diagnostic_to_lsp(
                                     &self.config.diagnostics_map(),
@@ -368,6 +409,7 @@ impl GlobalState {
                             for diag in diagnostics {
                                 match url_to_file_id(&self.vfs.read().0, &diag.url) {
                                     Ok(file_id) => self.diagnostics.add_check_diagnostic(
+                                        package_id.clone(),
                                         file_id,
                                         diag.diagnostic,
                                         diag.fixes,
@@ -384,10 +426,24 @@ impl GlobalState {
 
                         flycheck::Message::Progress { id, progress } => {
                             let (state, message) = match progress {
-                                flycheck::Progress::DidStart => {
+                                flycheck::Progress::DidStart(CheckScope::Workspace) => {
                                     self.diagnostics.clear_check();
                                     (Progress::Begin, None)
                                 }
+                                flycheck::Progress::DidStart(CheckScope::Packages(packages)) => {
+                                    let package_ids = match self.workspaces.get(id) {
+                                        Some(ProjectWorkspace::Cargo { cargo, .. }) => cargo
+                                            .packages()
+                                            .filter(|&pkg| {
+                                                packages.contains(&cargo.package_flag(&cargo[pkg]))
+                                            })
+                                            .map(|pkg| cargo[pkg].id.clone())
+                                            .collect(),
+                                        _ => Vec::new(),
+                                    };
+                                    self.diagnostics.clear_check_for_packages(&package_ids);
+                                    (Progress::Begin, None)
+                                }
                                 flycheck::Progress::DidCheckCrate(target) => {
                                     (Progress::Report, Some(target))
                                 }
@@ -430,14 +486,19 @@ impl GlobalState {
                 for flycheck in &self.flycheck {
                     flycheck.update();
                 }
-                self.prime_caches_queue.request_op();
+                self.load_library_symbols();
             }
 
             if !was_quiescent || state_changed {
                 // Refresh semantic tokens if the client supports it.
                 if self.config.semantic_tokens_refresh() {
                     self.semantic_tokens_cache.lock().clear();
-                    self.send_request::<lsp_types::request::SemanticTokensRefesh>((), |_, _| ());
+                    self.send_request::<lsp_types::request::SemanticTokensRefresh>((), |_, _| ());
+                }
+
+                // Refresh inlay hints if the client supports it.
+                if self.config.inlay_hints_refresh() {
+                    self.send_request::<lsp_types::request::InlayHintRefreshRequest>((), |_, _| ());
                 }
 
                 // Refresh code lens if the client supports it.
@@ -446,35 +507,54 @@ impl GlobalState {
                 }
             }
 
+            // Refresh pulled diagnostics if the client supports it, but not
+            // after edits: clients pull the diagnostics of edited files again by
+            // themselves, and unaffected files get an `unchanged` report anyway.
+            if self.diagnostics.take_refresh_pulled() && self.config.workspace_diagnostics_refresh()
+            {
+                self.send_request::<lsp_types::request::WorkspaceDiagnosticRefresh>((), |_, _| ());
+            }
+
+            // Clients pulling diagnostics compute them on demand instead.
             if !was_quiescent || state_changed || memdocs_added_or_removed {
-                if self.config.publish_diagnostics() {
+                if self.config.publish_diagnostics() && !self.config.pull_diagnostics() {
                     self.update_diagnostics()
                 }
             }
         }
 
         if let Some(diagnostic_changes) = self.diagnostics.take_changes() {
-            for file_id in diagnostic_changes {
-                let db = self.analysis_host.raw_database();
-                let source_root = db.file_source_root(file_id);
-                if db.source_root(source_root).is_library {
-                    // Only publish diagnostics for files in the workspace, not from crates.io deps
-                    // or the sysroot.
-                    // While theoretically these should never have errors, we have quite a few false
-                    // positives particularly in the stdlib, and those diagnostics would stay around
-                    // forever if we emitted them here.
-                    continue;
+            if self.config.pull_diagnostics() {
+                // Only `cargo check` diagnostics get here, as native ones aren't pushed.
+                if self.config.workspace_diagnostics_refresh() {
+                    self.send_request::<lsp_types::request::WorkspaceDiagnosticRefresh>(
+                        (),
+                        |_, _| (),
+                    );
                 }
+            } else {
+                for file_id in diagnostic_changes {
+                    let db = self.analysis_host.raw_database();
+                    let source_root = db.file_source_root(file_id);
+                    if db.source_root(source_root).is_library {
+                        // Only publish diagnostics for files in the workspace, not from crates.io deps
+                        // or the sysroot.
+                        // While theoretically these should never have errors, we have quite a few false
+                        // positives particularly in the stdlib, and those diagnostics would stay around
+                        // forever if we emitted them here.
+                        continue;
+                    }
 
-                let url = file_id_to_url(&self.vfs.read().0, file_id);
-                let diagnostics = self.diagnostics.diagnostics_for(file_id).cloned().collect();
-                let version = from_proto::vfs_path(&url)
-                    .map(|path| self.mem_docs.get(&path).map(|it| it.version))
-                    .unwrap_or_default();
+                    let url = file_id_to_url(&self.vfs.read().0, file_id);
+                    let diagnostics = self.diagnostics.diagnostics_for(file_id).cloned().collect();
+                    let version = from_proto::vfs_path(&url)
+                        .map(|path| self.mem_docs.get(&path).map(|it| it.version))
+                        .unwrap_or_default();
 
-                self.send_notification::<lsp_types::notification::PublishDiagnostics>(
-                    lsp_types::PublishDiagnosticsParams { uri: url, diagnostics, version },
-                );
+                    self.send_notification::<lsp_types::notification::PublishDiagnostics>(
+                        lsp_types::PublishDiagnosticsParams { uri: url, diagnostics, version },
+                    );
+                }
             }
         }
 
@@ -562,6 +642,7 @@ impl GlobalState {
                 Ok(())
             })?
             .on_sync_mut::<lsp_ext::MemoryUsage>(|s, p| handlers::handle_memory_usage(s, p))?
+            .on_sync_mut::<lsp_types::request::ExecuteCommand>(handlers::handle_execute_command)?
             .on_sync::<lsp_ext::JoinLines>(handlers::handle_join_lines)?
             .on_sync::<lsp_ext::OnEnter>(handlers::handle_on_enter)?
             .on_sync::<lsp_types::request::SelectionRangeRequest>(handlers::handle_selection_range)?
@@ -576,6 +657,14 @@ impl GlobalState {
             .on::<lsp_ext::Runnables>(handlers::handle_runnables)
             .on::<lsp_ext::RelatedTests>(handlers::handle_related_tests)
             .on::<lsp_ext::InlayHints>(handlers::handle_inlay_hints)
+            .on::<lsp_types::request::InlayHintRequest>(handlers::handle_lsp_inlay_hints)
+            .on::<lsp_types::request::InlayHintResolveRequest>(handlers::handle_inlay_hint_resolve)
+            .on::<lsp_types::request::DocumentDiagnosticRequest>(
+                handlers::handle_document_diagnostics,
+            )
+            .on_with_sender::<lsp_types::request::WorkspaceDiagnosticRequest>(
+                handlers::handle_workspace_diagnostics,
+            )
             .on::<lsp_ext::CodeActionRequest>(handlers::handle_code_action)
             .on::<lsp_ext::CodeActionResolveRequest>(handlers::handle_code_action_resolve)
             .on::<lsp_ext::HoverRequest>(handlers::handle_hover)
@@ -647,6 +736,9 @@ impl GlobalState {
                     {
                         tracing::error!("duplicate DidOpenTextDocument: {}", path)
                     }
+                    if let Some(path) = path.as_path() {
+                        this.discover_project(path.to_path_buf());
+                    }
                     this.vfs
                         .write()
                         .0
@@ -668,12 +760,23 @@ impl GlobalState {
                         }
                     };
 
-                    let vfs = &mut this.vfs.write().0;
-                    let file_id = vfs.file_id(&path).unwrap();
-                    let mut text = String::from_utf8(vfs.file_contents(file_id).to_vec()).unwrap();
-                    apply_document_changes(&mut text, params.content_changes);
-
-                    vfs.set_file_contents(path, Some(text.into_bytes()));
+                    {
+                        let vfs = &mut this.vfs.write().0;
+                        let file_id = vfs.file_id(&path).unwrap();
+                        let mut text =
+                            String::from_utf8(vfs.file_contents(file_id).to_vec()).unwrap();
+                        apply_document_changes(&mut text, params.content_changes);
+
+                        if this.config.flycheck_unsaved_buffers() {
+                            if let Some(path) = path.as_path() {
+                                this.update_flycheck_unsaved_file(path, Some(text.clone().into_bytes()));
+                            }
+                        }
+                        vfs.set_file_contents(path.clone(), Some(text.into_bytes()));
+                    }
+                    if this.config.flycheck_unsaved_buffers() {
+                        this.restart_flycheck(path.as_path());
+                    }
                 }
                 Ok(())
             })?
@@ -687,18 +790,24 @@ impl GlobalState {
 
                     if let Some(path) = path.as_path() {
                         this.loader.handle.invalidate(path.to_path_buf());
+                        if this.config.flycheck_unsaved_buffers() {
+                            // The check should see the file on disk again.
+                            this.update_flycheck_unsaved_file(path, None);
+                            this.restart_flycheck(Some(path));
+                        }
                     }
                 }
                 Ok(())
             })?
             .on::<lsp_types::notification::DidSaveTextDocument>(|this, params| {
-                for flycheck in &this.flycheck {
-                    flycheck.update();
-                }
-                if let Ok(abs_path) = from_proto::abs_path(&params.text_document.uri) {
-                    if reload::should_refresh_for_change(&abs_path, ChangeKind::Modify) {
-                        this.fetch_workspaces_queue.request_op();
+                match from_proto::abs_path(&params.text_document.uri) {
+                    Ok(abs_path) => {
+                        this.restart_flycheck(Some(&abs_path));
+                        if reload::should_refresh_for_change(&abs_path, ChangeKind::Modify) {
+                            this.fetch_workspaces_queue.request_op();
+                        }
                     }
+                    Err(_) => this.restart_flycheck(None),
                 }
                 Ok(())
             })?
@@ -750,6 +859,46 @@ impl GlobalState {
         Ok(())
     }
 
+    /// Restarts the checks after `path` changed. When configured, only the
+    /// packages affected by the change are checked.
+    pub(crate) fn restart_flycheck(&self, path: Option<&AbsPath>) {
+        for flycheck in &self.flycheck {
+            let scope = match (self.workspaces.get(flycheck.id()), path) {
+                (Some(ProjectWorkspace::Cargo { cargo, .. }), Some(path))
+                    if self.config.flycheck_per_package() =>
+                {
+                    let packages = cargo.affected_members(path);
+                    if packages.is_empty() {
+                        if !path.starts_with(cargo.workspace_root()) {
+                            continue;
+                        }
+                        CheckScope::Workspace
+                    } else {
+                        CheckScope::Packages(
+                            packages
+                                .into_iter()
+                                .map(|pkg| cargo.package_flag(&cargo[pkg]))
+                                .collect(),
+                        )
+                    }
+                }
+                _ => CheckScope::Workspace,
+            };
+            flycheck.restart(scope);
+        }
+    }
+
+    /// Passes the contents of an unsaved buffer, or `None` once it is closed,
+    /// on to the checks.
+    pub(crate) fn update_flycheck_unsaved_file(&self, path: &AbsPath, contents: Option<Vec<u8>>) {
+        for flycheck in &self.flycheck {
+            if !self.workspaces.get(flycheck.id()).map_or(false, supports_unsaved_buffers) {
+                continue;
+            }
+            flycheck.update_unsaved_files(vec![(path.to_path_buf(), contents.clone())]);
+        }
+    }
+
     fn update_diagnostics(&mut self) {
         let subscriptions = self
             .mem_docs
@@ -777,4 +926,42 @@ impl GlobalState {
             Task::Diagnostics(diagnostics)
         })
     }
+    /// Reads the cached symbol indices of libraries before priming, so that writing the cache
+    /// afterwards doesn't have to rebuild them.
+    fn load_library_symbols(&mut self) {
+        let dir = match self.config.library_symbols_cache_dir() {
+            Some(it) => it,
+            None => return self.prime_caches_queue.request_op(),
+        };
+        let analysis = self.snapshot().analysis;
+        self.task_pool.handle.spawn(move || {
+            let symbols = analysis.load_library_symbols(dir.as_ref(), env!("REV"));
+            Task::LoadLibrarySymbols(symbols.ok().flatten())
+        });
+    }
+
+    fn store_library_symbols(&mut self) {
+        let dir = match self.config.library_symbols_cache_dir() {
+            Some(it) => it,
+            None => return,
+        };
+        let analysis = self.snapshot().analysis;
+        self.task_pool.handle.spawn_with_sender(move |_| {
+            // If this gets cancelled, the next priming writes the cache.
+            let _ = analysis.store_library_symbols(dir.as_ref(), env!("REV"));
+        });
+    }
+}
+
+/// Unsaved buffers are checked in a copy of the workspace root. Path
+/// dependencies outside of the root would be resolved relative to the copy
+/// and not be found, so such workspaces are checked on disk only.
+fn supports_unsaved_buffers(ws: &ProjectWorkspace) -> bool {
+    match ws {
+        ProjectWorkspace::Cargo { cargo, .. } => cargo
+            .packages()
+            .filter(|&pkg| cargo[pkg].is_local)
+            .all(|pkg| cargo[pkg].manifest.starts_with(cargo.workspace_root())),
+        ProjectWorkspace::Json { .. } | ProjectWorkspace::DetachedFiles { .. } => true,
+    }
 }
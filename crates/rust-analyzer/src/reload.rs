@@ -1,5 +1,5 @@
 //! Project loading & configuration updates
-use std::{mem, sync::Arc};
+use std::{iter, mem, sync::Arc};
 
 use flycheck::{FlycheckConfig, FlycheckHandle};
 use hir::db::DefDatabase;
@@ -9,7 +9,8 @@ use ide_db::base_db::{
     SourceRoot, VfsPath,
 };
 use proc_macro_api::{MacroDylib, ProcMacroServer};
-use project_model::{ProjectWorkspace, WorkspaceBuildScripts};
+use project_model::{ProjectJson, ProjectWorkspace, WorkspaceBuildScripts};
+use rustc_hash::FxHashSet;
 use vfs::{file_set::FileSetConfig, AbsPath, AbsPathBuf, ChangeKind};
 
 use crate::{
@@ -44,19 +45,42 @@ impl GlobalState {
     pub(crate) fn update_configuration(&mut self, config: Config) {
         let _p = profile::span("GlobalState::update_configuration");
         let old_config = mem::replace(&mut self.config, Arc::new(config));
+        if self.config.publish_diagnostics() != old_config.publish_diagnostics()
+            || self.config.diagnostics() != old_config.diagnostics()
+        {
+            self.diagnostics.invalidate_all_native();
+        }
         if self.config.lru_capacity() != old_config.lru_capacity() {
             self.analysis_host.update_lru_capacity(self.config.lru_capacity());
         }
         if self.config.linked_projects() != old_config.linked_projects() {
             self.fetch_workspaces_queue.request_op()
-        } else if self.config.flycheck() != old_config.flycheck() {
+        } else if self.config.flycheck() != old_config.flycheck()
+            || self.config.flycheck_unsaved_buffers() != old_config.flycheck_unsaved_buffers()
+        {
             self.reload_flycheck();
         }
+        if self.config.ssr_rule_files() != old_config.ssr_rule_files() {
+            // The VFS only watches the rule files known when the workspace
+            // was loaded.
+            self.fetch_workspaces_queue.request_op()
+        }
+        if self.config.proc_macro_srv_config() != old_config.proc_macro_srv_config() {
+            // Loaded proc macros hold on to the old server, so we have to
+            // rebuild the crate graph, which respawns it.
+            self.proc_macro_client = None;
+            self.fetch_build_data_queue.request_op();
+        }
 
         // Apply experimental feature flags.
         self.analysis_host
             .raw_database_mut()
             .set_enable_proc_attr_macros(self.config.expand_proc_attr_macros());
+
+        if self.config.library_symbols_cache_dir() != old_config.library_symbols_cache_dir() {
+            // Writing the cache entries happens after priming.
+            self.prime_caches_queue.request_op();
+        }
     }
 
     pub(crate) fn current_status(&self) -> lsp_ext::ServerStatusParams {
@@ -89,7 +113,14 @@ impl GlobalState {
         tracing::info!("will fetch workspaces");
 
         self.task_pool.handle.spawn_with_sender({
-            let linked_projects = self.config.linked_projects();
+            let mut linked_projects = self.config.linked_projects();
+            // Discovered projects go into one workspace, so that the sysroot
+            // and shared dependencies are loaded once.
+            let mut discovered = self.discovered_projects.iter().cloned();
+            if let Some(mut project) = discovered.next() {
+                discovered.for_each(|it| project.merge(it));
+                linked_projects.push(project.into());
+            }
             let detached_files = self.config.detached_files().to_vec();
             let cargo_config = self.config.cargo();
 
@@ -137,6 +168,60 @@ impl GlobalState {
         });
     }
 
+    /// Runs the workspace discovery command for `path`, unless it belongs to
+    /// a loaded workspace already.
+    pub(crate) fn discover_project(&mut self, path: AbsPathBuf) {
+        let command = match self.config.discover_command() {
+            Some(it) => it.to_vec(),
+            None => return,
+        };
+        if path.extension().unwrap_or_default() != "rs" {
+            return;
+        }
+        // The file likely belongs to one of the workspaces being loaded, check
+        // again once they are.
+        if self.fetch_workspaces_queue.op_requested()
+            || self.fetch_workspaces_queue.op_in_progress()
+        {
+            self.deferred_discovery.push(path);
+            return;
+        }
+        let is_loaded = self
+            .workspaces
+            .iter()
+            .flat_map(|ws| ws.to_roots())
+            .filter(|root| root.is_local)
+            .any(|root| root.include.iter().any(|it| path.starts_with(it)));
+        if is_loaded || !self.discovery_requested.insert(path.clone()) {
+            return;
+        }
+
+        let root = self.config.root_path.clone();
+        self.task_pool.handle.spawn(move || {
+            let res = ProjectJson::discover(&command, &root, &path);
+            Task::DiscoverProject(path, res)
+        });
+    }
+
+    pub(crate) fn add_discovered_project(&mut self, project: ProjectJson) {
+        if self.discovered_projects.contains(&project) {
+            return;
+        }
+        // A project for a build target supersedes the ones for the targets it
+        // includes, in particular earlier versions of itself.
+        let members = |project: &ProjectJson| {
+            project
+                .crates()
+                .filter(|(_, krate)| krate.is_workspace_member())
+                .map(|(_, krate)| krate.root_module().to_path_buf())
+                .collect::<FxHashSet<_>>()
+        };
+        let new_members = members(&project);
+        self.discovered_projects.retain(|it| !members(it).is_subset(&new_members));
+        self.discovered_projects.push(project);
+        self.fetch_workspaces_queue.request_op();
+    }
+
     pub(crate) fn fetch_build_data(&mut self) {
         let workspaces = Arc::clone(&self.workspaces);
         let config = self.config.cargo();
@@ -191,10 +276,11 @@ impl GlobalState {
                     sysroot,
                     rustc,
                     rustc_cfg,
+                    extra_rustc_cfgs,
                     cfg_overrides,
 
                     build_scripts: _,
-                } => Some((cargo, sysroot, rustc, rustc_cfg, cfg_overrides)),
+                } => Some((cargo, sysroot, rustc, rustc_cfg, extra_rustc_cfgs, cfg_overrides)),
                 _ => None,
             };
             match (key(left), key(right)) {
@@ -252,8 +338,14 @@ impl GlobalState {
                                 ]
                             })
                         })
+                        .chain(
+                            self.config
+                                .ssr_rule_files()
+                                .into_iter()
+                                .map(|it| it.display().to_string()),
+                        )
                         .map(|glob_pattern| lsp_types::FileSystemWatcher {
-                            glob_pattern,
+                            glob_pattern: lsp_types::GlobPattern::String(glob_pattern),
                             kind: None,
                         })
                         .collect(),
@@ -273,23 +365,17 @@ impl GlobalState {
         let mut change = Change::new();
 
         let files_config = self.config.files();
-        let project_folders = ProjectFolders::new(&self.workspaces, &files_config.exclude);
+        let mut project_folders = ProjectFolders::new(&self.workspaces, &files_config.exclude);
+        // SSR rule files aren't part of any workspace, but their rules have
+        // to be reloaded when they change.
+        let ssr_rule_files = self.config.ssr_rule_files();
+        if !ssr_rule_files.is_empty() {
+            project_folders.watch.push(project_folders.load.len());
+            project_folders.load.push(vfs::loader::Entry::Files(ssr_rule_files));
+        }
 
         if self.proc_macro_client.is_none() {
-            self.proc_macro_client = match self.config.proc_macro_srv() {
-                None => None,
-                Some((path, args)) => match ProcMacroServer::spawn(path.clone(), args) {
-                    Ok(it) => Some(it),
-                    Err(err) => {
-                        tracing::error!(
-                            "Failed to run proc_macro_srv from path {}, error: {:?}",
-                            path.display(),
-                            err
-                        );
-                        None
-                    }
-                },
-            };
+            self.proc_macro_client = self.spawn_proc_macro_server();
         }
 
         let watch = match files_config.watcher {
@@ -335,6 +421,7 @@ impl GlobalState {
         change.set_crate_graph(crate_graph);
 
         self.source_root_config = project_folders.source_root_config;
+        self.diagnostics.invalidate_all_native();
 
         self.analysis_host.apply_change(change);
         self.process_changes();
@@ -384,6 +471,38 @@ impl GlobalState {
         }
     }
 
+    fn spawn_proc_macro_server(&self) -> Option<ProcMacroServer> {
+        let (path, args) = self.config.proc_macro_srv()?;
+        let config = self.config.proc_macro_srv_config();
+
+        // A server shipped with the toolchain understands the ABI of the
+        // proc-macros that toolchain builds, so prefer it over our own.
+        let sysroot_srv = if self.config.proc_macro_srv_from_sysroot() {
+            self.workspaces.iter().find_map(|ws| {
+                let sysroot = match ws {
+                    ProjectWorkspace::Cargo { sysroot, .. } => sysroot.as_ref()?,
+                    ProjectWorkspace::Json { sysroot, .. } => sysroot.as_ref()?,
+                    ProjectWorkspace::DetachedFiles { sysroot, .. } => sysroot,
+                };
+                sysroot.discover_proc_macro_srv()
+            })
+        } else {
+            None
+        };
+
+        match ProcMacroServer::spawn_preferring(sysroot_srv, path.clone(), args, config) {
+            Ok(it) => Some(it),
+            Err(err) => {
+                tracing::error!(
+                    "Failed to run proc_macro_srv from path {}, error: {:?}",
+                    path.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
     fn reload_flycheck(&mut self) {
         let _p = profile::span("GlobalState::reload_flycheck");
         let config = match self.config.flycheck() {
@@ -421,6 +540,17 @@ impl GlobalState {
                 )
             })
             .collect();
+
+        // The new checks don't know about the open editor buffers yet.
+        if self.config.flycheck_unsaved_buffers() {
+            let vfs = &self.vfs.read().0;
+            for path in self.mem_docs.iter() {
+                if let (Some(file_id), Some(path)) = (vfs.file_id(path), path.as_path()) {
+                    let contents = vfs.file_contents(file_id).to_vec();
+                    self.update_flycheck_unsaved_file(path, Some(contents));
+                }
+            }
+        }
     }
 }
 
@@ -9,8 +9,8 @@ use ide::{
     Annotation, AnnotationKind, Assist, AssistKind, CallInfo, Cancellable, CompletionItem,
     CompletionItemKind, CompletionRelevance, Documentation, FileId, FileRange, FileSystemEdit,
     Fold, FoldKind, Highlight, HlMod, HlOperator, HlPunct, HlRange, HlTag, Indel, InlayHint,
-    InlayKind, Markup, NavigationTarget, ReferenceCategory, RenameError, Runnable, Severity,
-    SourceChange, StructureNodeKind, SymbolKind, TextEdit, TextRange, TextSize,
+    InlayKind, InlayTooltip, Markup, NavigationTarget, ReferenceCategory, RenameError, Runnable,
+    Severity, SourceChange, StructureNodeKind, SymbolKind, TextEdit, TextRange, TextSize,
 };
 use itertools::Itertools;
 use serde_json::to_value;
@@ -416,6 +416,77 @@ pub(crate) fn inlay_hint(line_index: &LineIndex, inlay_hint: InlayHint) -> lsp_e
     }
 }
 
+pub(crate) fn lsp_inlay_hint(
+    snap: &GlobalStateSnapshot,
+    line_index: &LineIndex,
+    resolve_tooltip: bool,
+    inlay_hint: InlayHint,
+) -> Result<lsp_types::InlayHint> {
+    let (position, prefix, suffix, kind) = match inlay_hint.kind {
+        InlayKind::ParameterHint => {
+            (inlay_hint.range.start(), "", ":", lsp_types::InlayHintKind::PARAMETER)
+        }
+        InlayKind::TypeHint => (inlay_hint.range.end(), ": ", "", lsp_types::InlayHintKind::TYPE),
+        InlayKind::ChainingHint => (inlay_hint.range.end(), "", "", lsp_types::InlayHintKind::TYPE),
+    };
+
+    let label = if inlay_hint.label.parts.iter().all(|part| part.linked_location.is_none()) {
+        lsp_types::InlayHintLabel::String(format!("{}{}{}", prefix, inlay_hint.label, suffix))
+    } else {
+        let unlinked = |value: &str| lsp_types::InlayHintLabelPart {
+            value: value.to_string(),
+            tooltip: None,
+            location: None,
+            command: None,
+        };
+        let mut parts = Vec::new();
+        if !prefix.is_empty() {
+            parts.push(unlinked(prefix));
+        }
+        for part in inlay_hint.label.parts {
+            parts.push(lsp_types::InlayHintLabelPart {
+                value: part.text,
+                tooltip: None,
+                location: part.linked_location.map(|it| location(snap, it)).transpose()?,
+                command: None,
+            });
+        }
+        if !suffix.is_empty() {
+            parts.push(unlinked(suffix));
+        }
+        lsp_types::InlayHintLabel::LabelParts(parts)
+    };
+
+    let data = match inlay_hint.tooltip.filter(|_| resolve_tooltip) {
+        Some(tooltip) => {
+            let frange = match tooltip {
+                InlayTooltip::HoverRanged(file_id, range) => FileRange { file_id, range },
+                InlayTooltip::HoverOffset(file_id, offset) => {
+                    FileRange { file_id, range: TextRange::empty(offset) }
+                }
+            };
+            let tooltip_line_index = snap.file_line_index(frange.file_id)?;
+            let data = lsp_ext::InlayHintResolveData {
+                text_document: lsp_types::TextDocumentIdentifier::new(url(snap, frange.file_id)),
+                range: range(&tooltip_line_index, frange.range),
+            };
+            Some(to_value(data).unwrap())
+        }
+        None => None,
+    };
+
+    Ok(lsp_types::InlayHint {
+        position: self::position(line_index, position),
+        label,
+        kind: Some(kind),
+        text_edits: None,
+        tooltip: None,
+        padding_left: Some(inlay_hint.kind == InlayKind::ChainingHint),
+        padding_right: Some(inlay_hint.kind == InlayKind::ParameterHint),
+        data,
+    })
+}
+
 static TOKEN_RESULT_COUNTER: AtomicU32 = AtomicU32::new(1);
 
 pub(crate) fn semantic_tokens(
@@ -605,6 +676,7 @@ pub(crate) fn folding_range(
             end_line,
             end_character: None,
             kind,
+            collapsed_text: None,
         }
     } else {
         lsp_types::FoldingRange {
@@ -613,6 +685,7 @@ pub(crate) fn folding_range(
             end_line: range.end.line,
             end_character: Some(range.end.character),
             kind,
+            collapsed_text: None,
         }
     }
 }
@@ -965,6 +1038,10 @@ pub(crate) fn runnable(
     snap: &GlobalStateSnapshot,
     runnable: Runnable,
 ) -> Result<lsp_ext::Runnable> {
+    if let Some(res) = project_json_runnable(snap, &runnable)? {
+        return Ok(res);
+    }
+
     let config = snap.config.runnables();
     let spec = CargoTargetSpec::for_file(snap, runnable.nav.file_id)?;
     let workspace_root = spec.as_ref().map(|it| it.workspace_root.clone());
@@ -978,17 +1055,63 @@ pub(crate) fn runnable(
         label,
         location: Some(location),
         kind: lsp_ext::RunnableKind::Cargo,
-        args: lsp_ext::CargoRunnable {
+        args: lsp_ext::RunnableArgs::Cargo(lsp_ext::CargoRunnable {
             workspace_root: workspace_root.map(|it| it.into()),
             override_cargo: config.override_cargo,
             cargo_args,
             cargo_extra_args: config.cargo_extra_args,
             executable_args,
             expect_test: None,
-        },
+        }),
     })
 }
 
+/// Fills in the runnable templates of a crate from `rust-project.json`, if
+/// it has one for this kind of runnable.
+fn project_json_runnable(
+    snap: &GlobalStateSnapshot,
+    runnable: &Runnable,
+) -> Result<Option<lsp_ext::Runnable>> {
+    let build = match snap.analysis.crate_for(runnable.nav.file_id)?.first() {
+        Some(&crate_id) => snap.project_json_build_for_crate_root(crate_id),
+        None => None,
+    };
+    let build = match build {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let (kind, test_id) = match &runnable.kind {
+        ide::RunnableKind::Test { test_id, .. } | ide::RunnableKind::DocTest { test_id } => {
+            (project_model::RunnableKind::Test, test_id.to_string())
+        }
+        ide::RunnableKind::TestMod { path } => (project_model::RunnableKind::Test, path.clone()),
+        ide::RunnableKind::Bench { test_id } => {
+            (project_model::RunnableKind::Bench, test_id.to_string())
+        }
+        ide::RunnableKind::Bin => (project_model::RunnableKind::Run, String::new()),
+    };
+    let template = match build.runnable(kind) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let args = template
+        .args
+        .iter()
+        .map(|arg| arg.replace("{label}", &build.label).replace("{test_id}", &test_id))
+        .collect();
+
+    Ok(Some(lsp_ext::Runnable {
+        label: runnable.label(Some(build.label.clone())),
+        location: Some(location_link(snap, None, runnable.nav.clone())?),
+        kind: lsp_ext::RunnableKind::Shell,
+        args: lsp_ext::RunnableArgs::Shell(lsp_ext::ShellRunnable {
+            program: template.program.clone(),
+            args,
+            cwd: template.cwd.clone().into(),
+        }),
+    }))
+}
+
 pub(crate) fn code_lens(
     acc: &mut Vec<lsp_types::CodeLens>,
     snap: &GlobalStateSnapshot,
@@ -1009,6 +1132,8 @@ pub(crate) fn code_lens(
                 | ide::RunnableKind::Bin => true,
             };
             let r = runnable(snap, run)?;
+            // Debugging needs to know how to build the executable.
+            let can_debug = can_debug && matches!(r.kind, lsp_ext::RunnableKind::Cargo);
 
             let lens_config = snap.config.lens();
             if lens_config.run && client_commands_config.run_single {
@@ -17,17 +17,20 @@ use std::{collections::HashMap, path::PathBuf, time::Instant};
 
 use expect_test::expect;
 use lsp_types::{
-    notification::DidOpenTextDocument,
+    notification::{DidChangeWatchedFiles, DidOpenTextDocument},
     request::{
-        CodeActionRequest, Completion, Formatting, GotoTypeDefinition, HoverRequest,
-        WillRenameFiles,
+        CodeActionRequest, Completion, DocumentDiagnosticRequest, ExecuteCommand, Formatting,
+        GotoTypeDefinition, HoverRequest, InlayHintRequest, InlayHintResolveRequest,
+        WillRenameFiles, WorkspaceDiagnosticRequest,
     },
-    CodeActionContext, CodeActionParams, CompletionParams, DidOpenTextDocumentParams,
-    DocumentFormattingParams, FileRename, FormattingOptions, GotoDefinitionParams, HoverParams,
-    PartialResultParams, Position, Range, RenameFilesParams, TextDocumentItem,
-    TextDocumentPositionParams, WorkDoneProgressParams,
+    CodeActionContext, CodeActionParams, CompletionParams, DidChangeWatchedFilesParams,
+    DidOpenTextDocumentParams, DocumentDiagnosticParams, DocumentFormattingParams,
+    ExecuteCommandParams, FileChangeType, FileEvent, FileRename, FormattingOptions,
+    GotoDefinitionParams, HoverParams, InlayHintParams, NumberOrString, PartialResultParams,
+    Position, PreviousResultId, Range, RenameFilesParams, TextDocumentItem,
+    TextDocumentPositionParams, WorkDoneProgressParams, WorkspaceDiagnosticParams,
 };
-use rust_analyzer::lsp_ext::{OnEnter, Runnables, RunnablesParams};
+use rust_analyzer::lsp_ext::{OnEnter, Runnables, RunnablesParams, ServerCommand};
 use serde_json::json;
 use test_utils::skip_slow_tests;
 
@@ -1021,3 +1024,381 @@ fn main() {}
         }),
     );
 }
+
+#[test]
+fn execute_server_commands() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+pub fn foo() {}
+"#,
+    )
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    for command in ServerCommand::ALL {
+        server.request::<ExecuteCommand>(
+            ExecuteCommandParams {
+                command: command.name().to_string(),
+                arguments: Vec::new(),
+                work_done_progress_params: WorkDoneProgressParams::default(),
+            },
+            json!(null),
+        );
+    }
+}
+
+#[test]
+fn inlay_hints_link_to_definitions() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+pub struct S;
+pub fn f(thing: S, n: u32) {}
+fn g() {
+    let s = S;
+    f(s, 0);
+}
+"#,
+    )
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    server.request::<InlayHintRequest>(
+        InlayHintParams {
+            text_document: server.doc_id("src/lib.rs"),
+            range: Range::new(Position::new(0, 0), Position::new(6, 0)),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+        },
+        json!([
+            {
+                "position": { "line": 3, "character": 9 },
+                "label": [
+                    { "value": ": " },
+                    {
+                        "value": "S",
+                        "location": {
+                            "uri": "file:///[..]src/lib.rs",
+                            "range": {
+                                "start": { "line": 0, "character": 11 },
+                                "end": { "line": 0, "character": 12 }
+                            }
+                        }
+                    }
+                ],
+                "kind": 1,
+                "paddingLeft": false,
+                "paddingRight": false
+            },
+            {
+                "position": { "line": 4, "character": 6 },
+                "label": [
+                    {
+                        "value": "thing",
+                        "location": {
+                            "uri": "file:///[..]src/lib.rs",
+                            "range": {
+                                "start": { "line": 1, "character": 9 },
+                                "end": { "line": 1, "character": 14 }
+                            }
+                        }
+                    },
+                    { "value": ":" }
+                ],
+                "kind": 2,
+                "paddingLeft": false,
+                "paddingRight": true
+            },
+            {
+                "position": { "line": 4, "character": 9 },
+                "label": [
+                    {
+                        "value": "n",
+                        "location": {
+                            "uri": "file:///[..]src/lib.rs",
+                            "range": {
+                                "start": { "line": 1, "character": 19 },
+                                "end": { "line": 1, "character": 20 }
+                            }
+                        }
+                    },
+                    { "value": ":" }
+                ],
+                "kind": 2,
+                "paddingLeft": false,
+                "paddingRight": true
+            }
+        ]),
+    );
+
+    let res = server.send_request::<InlayHintResolveRequest>(
+        serde_json::from_value(json!({
+            "position": { "line": 3, "character": 9 },
+            "label": ": S",
+            "data": {
+                "textDocument": server.doc_id("src/lib.rs"),
+                "range": {
+                    "start": { "line": 3, "character": 8 },
+                    "end": { "line": 3, "character": 9 }
+                }
+            }
+        }))
+        .unwrap(),
+    );
+    assert!(res["tooltip"]["value"].as_str().unwrap().contains("S"), "{}", res);
+}
+
+#[test]
+fn pull_diagnostics() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+mod unopened;
+
+//- /src/unopened.rs
+fn f() {
+    let x = S { };
+}
+struct S { field: u32 }
+"#,
+    )
+    .pull_diagnostics()
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let report = server.send_request::<DocumentDiagnosticRequest>(DocumentDiagnosticParams {
+        text_document: server.doc_id("src/unopened.rs"),
+        identifier: None,
+        previous_result_id: None,
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams::default(),
+    });
+    assert_eq!(report["kind"], "full", "{}", report);
+    assert_eq!(report["items"][0]["code"], "missing-fields", "{}", report);
+    let result_id = report["resultId"].as_str().unwrap().to_string();
+
+    server.request::<DocumentDiagnosticRequest>(
+        DocumentDiagnosticParams {
+            text_document: server.doc_id("src/unopened.rs"),
+            identifier: None,
+            previous_result_id: Some(result_id.clone()),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        },
+        json!({ "kind": "unchanged", "resultId": result_id }),
+    );
+
+    let report = server.send_request::<WorkspaceDiagnosticRequest>(WorkspaceDiagnosticParams {
+        identifier: None,
+        previous_result_ids: vec![PreviousResultId {
+            uri: server.doc_id("src/unopened.rs").uri,
+            value: result_id,
+        }],
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams::default(),
+    });
+    let items = report["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2, "{}", report);
+    for item in items {
+        let uri = item["uri"].as_str().unwrap();
+        if uri.ends_with("src/unopened.rs") {
+            assert_eq!(item["kind"], "unchanged", "{}", report);
+        } else {
+            assert!(uri.ends_with("src/lib.rs"), "{}", report);
+            assert_eq!(item["kind"], "full", "{}", report);
+            assert_eq!(item["items"], json!([]), "{}", report);
+        }
+    }
+}
+
+#[test]
+fn pull_workspace_diagnostics_with_partial_results() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /src/lib.rs
+mod unopened;
+
+//- /src/unopened.rs
+fn f() {
+    let x = S { };
+}
+struct S { field: u32 }
+"#,
+    )
+    .pull_diagnostics()
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let report = server.send_request::<WorkspaceDiagnosticRequest>(WorkspaceDiagnosticParams {
+        identifier: None,
+        previous_result_ids: Vec::new(),
+        work_done_progress_params: WorkDoneProgressParams::default(),
+        partial_result_params: PartialResultParams {
+            partial_result_token: Some(NumberOrString::String("partial".to_string())),
+        },
+    });
+    assert_eq!(report["items"], json!([]), "{}", report);
+
+    let items: Vec<_> = server
+        .received_notifications("$/progress")
+        .into_iter()
+        .filter(|it| it["token"] == "partial")
+        .flat_map(|it| it["value"]["items"].as_array().unwrap().clone())
+        .collect();
+    assert_eq!(items.len(), 2, "{:?}", items);
+    let unopened =
+        items.iter().find(|it| it["uri"].as_str().unwrap().ends_with("src/unopened.rs")).unwrap();
+    assert_eq!(unopened["items"][0]["code"], "missing-fields", "{}", unopened);
+}
+
+#[test]
+fn pull_diagnostics_only_recomputes_affected_crates() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /foo/Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+[dependencies]
+bar = { path = "../bar" }
+
+//- /foo/src/lib.rs
+pub fn foo() { bar::bar() }
+
+//- /bar/Cargo.toml
+[package]
+name = "bar"
+version = "0.0.0"
+
+//- /bar/src/lib.rs
+pub fn bar() {}
+"#,
+    )
+    .root("foo")
+    .pull_diagnostics()
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let pull = |path: &str, previous_result_id: Option<&str>| {
+        server.send_request::<DocumentDiagnosticRequest>(DocumentDiagnosticParams {
+            text_document: server.doc_id(path),
+            identifier: None,
+            previous_result_id: previous_result_id.map(ToString::to_string),
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        })["resultId"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    };
+    let foo_id = pull("foo/src/lib.rs", None);
+    let bar_id = pull("bar/src/lib.rs", None);
+
+    // An edit of `foo` can't affect its dependency `bar`.
+    server.notification::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri: server.doc_id("foo/src/lib.rs").uri,
+            language_id: "rust".to_string(),
+            version: 0,
+            text: "pub fn foo() { bar::bar(); }\n".to_string(),
+        },
+    });
+    assert_eq!(pull("bar/src/lib.rs", Some(&bar_id)), bar_id);
+    assert_ne!(pull("foo/src/lib.rs", Some(&foo_id)), foo_id);
+}
+
+#[test]
+fn reloads_changed_ssr_rule_files() {
+    if skip_slow_tests() {
+        return;
+    }
+
+    let server = Project::with_fixture(
+        r#"
+//- /Cargo.toml
+[package]
+name = "foo"
+version = "0.0.0"
+
+//- /rules.ssr
+// No rules yet.
+
+//- /src/lib.rs
+fn foo() {}
+fn bar() {}
+fn f() { foo(); }
+"#,
+    )
+    .with_config(serde_json::json!({ "ssr": { "ruleFiles": ["rules.ssr"] } }))
+    .pull_diagnostics()
+    .server()
+    .wait_until_workspace_is_loaded();
+
+    let codes = || {
+        let report = server.send_request::<DocumentDiagnosticRequest>(DocumentDiagnosticParams {
+            text_document: server.doc_id("src/lib.rs"),
+            identifier: None,
+            previous_result_id: None,
+            work_done_progress_params: WorkDoneProgressParams::default(),
+            partial_result_params: PartialResultParams::default(),
+        });
+        report["items"].as_array().unwrap().iter().map(|it| it["code"].clone()).collect::<Vec<_>>()
+    };
+    assert!(!codes().contains(&json!("ssr-rule")));
+
+    std::fs::write(server.path().join("rules.ssr"), "foo_to_bar: foo() ==>> bar()\n").unwrap();
+    server.notification::<DidChangeWatchedFiles>(DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: server.doc_id("rules.ssr").uri,
+            typ: FileChangeType::CHANGED,
+        }],
+    });
+    // The VFS picks up the change asynchronously.
+    for _ in 0..100 {
+        if codes().contains(&json!("ssr-rule")) {
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    panic!("the changed rule file was not reloaded");
+}
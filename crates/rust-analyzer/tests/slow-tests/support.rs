@@ -23,6 +23,7 @@ pub(crate) struct Project<'a> {
     tmp_dir: Option<TestDir>,
     roots: Vec<PathBuf>,
     config: serde_json::Value,
+    pull_diagnostics: bool,
 }
 
 impl<'a> Project<'a> {
@@ -39,6 +40,7 @@ impl<'a> Project<'a> {
                     "useRustcWrapperForBuildScripts": false,
                 }
             }),
+            pull_diagnostics: false,
         }
     }
 
@@ -52,6 +54,12 @@ impl<'a> Project<'a> {
         self
     }
 
+    /// Makes the client pull diagnostics instead of having them pushed.
+    pub(crate) fn pull_diagnostics(mut self) -> Project<'a> {
+        self.pull_diagnostics = true;
+        self
+    }
+
     pub(crate) fn with_config(mut self, config: serde_json::Value) -> Project<'a> {
         fn merge(dst: &mut serde_json::Value, src: serde_json::Value) {
             match (dst, src) {
@@ -116,6 +124,9 @@ impl<'a> Project<'a> {
                         content_format: Some(vec![lsp_types::MarkupKind::Markdown]),
                         ..Default::default()
                     }),
+                    diagnostic: self
+                        .pull_diagnostics
+                        .then(lsp_types::DiagnosticClientCapabilities::default),
                     ..Default::default()
                 }),
                 window: Some(lsp_types::WindowClientCapabilities {
@@ -224,6 +235,12 @@ impl Server {
                 Message::Response(res) => {
                     assert_eq!(res.id, id);
                     if let Some(err) = res.error {
+                        // Like a real client, ask again if a change to the
+                        // files cancelled the request.
+                        if err.code == lsp_server::ErrorCode::ContentModified as i32 {
+                            self.client.sender.send(r.clone().into()).unwrap();
+                            continue;
+                        }
                         panic!("error response: {:#?}", err);
                     }
                     return res.result.unwrap();
@@ -277,6 +294,18 @@ impl Server {
         self.client.sender.send(Message::Notification(not)).unwrap();
     }
 
+    /// Params of the notifications with `method` received so far.
+    pub(crate) fn received_notifications(&self, method: &str) -> Vec<Value> {
+        self.messages
+            .borrow()
+            .iter()
+            .filter_map(|msg| match msg {
+                Message::Notification(n) if n.method == method => Some(n.params.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub(crate) fn path(&self) -> &Path {
         self.dir.path()
     }
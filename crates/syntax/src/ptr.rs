@@ -32,6 +32,16 @@ impl SyntaxNodePtr {
         SyntaxNodePtr { range: node.text_range(), kind: node.kind() }
     }
 
+    /// Recreates a pointer from the kind and range of its node, for pointers that have been
+    /// persisted outside of memory.
+    pub fn from_parts(kind: SyntaxKind, range: TextRange) -> SyntaxNodePtr {
+        SyntaxNodePtr { range, kind }
+    }
+
+    pub fn kind(&self) -> SyntaxKind {
+        self.kind
+    }
+
     /// "Dereference" the pointer to get the node it points to.
     ///
     /// Panics if node is not found, so make sure that `root` syntax tree is
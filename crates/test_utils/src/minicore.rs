@@ -36,6 +36,7 @@
 //!     bool_impl: option, fn
 //!     add:
 //!     as_ref: sized
+//!     todo:
 
 pub mod marker {
     // region:sized
@@ -601,6 +602,15 @@ mod macros {
 }
 // endregion:derive
 
+// region:todo
+#[macro_export]
+macro_rules! todo {
+    ($($arg:tt)*) => {
+        loop {}
+    };
+}
+// endregion:todo
+
 // region:bool_impl
 #[lang = "bool"]
 impl bool {
@@ -1,16 +1,24 @@
 //! An implementation of `loader::Handle`, based on `walkdir` and `notify`.
 //!
-//! The file watching bits here are untested and quite probably buggy. For this
-//! reason, by default we don't watch files and rely on editor's file watching
-//! capabilities.
+//! Events are debounced and reported in batches. If the watcher signals that
+//! it lost events (for example, because the inotify queue overflowed), all
+//! watched entries are scanned again. The actor remembers which files of the
+//! watched entries exist, so that files which disappear without an event of
+//! their own, like the contents of a removed directory or files deleted while
+//! events were dropped, are still reported as removed.
 //!
-//! Hopefully, one day a reliable file watching/walking crate appears on
-//! crates.io, and we can reduce this to trivial glue code.
-use std::fs;
+//! By default we still don't watch files and rely on editor's file watching
+//! capabilities, which however don't see changes outside of the workspace
+//! folders.
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
 
 use crossbeam_channel::{never, select, unbounded, Receiver, Sender};
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notify::{event::Flag, RecommendedWatcher, RecursiveMode, Watcher};
 use paths::{AbsPath, AbsPathBuf};
+use rustc_hash::FxHashSet;
 use vfs::loader;
 use walkdir::WalkDir;
 
@@ -50,9 +58,16 @@ impl loader::Handle for NotifyHandle {
 
 type NotifyEvent = notify::Result<notify::Event>;
 
+/// How long to wait for further events before reporting a batch.
+const DEBOUNCE: Duration = Duration::from_millis(50);
+/// Upper bound on the delay of a batch, for a steady stream of events.
+const MAX_DEBOUNCE: Duration = Duration::from_millis(500);
+
 struct NotifyActor {
     sender: loader::Sender,
     watched_entries: Vec<loader::Entry>,
+    /// The files of `watched_entries` that were last reported as existing.
+    known_files: FxHashSet<AbsPathBuf>,
     // Drop order is significant.
     watcher: Option<(RecommendedWatcher, Receiver<NotifyEvent>)>,
 }
@@ -65,7 +80,12 @@ enum Event {
 
 impl NotifyActor {
     fn new(sender: loader::Sender) -> NotifyActor {
-        NotifyActor { sender, watched_entries: Vec::new(), watcher: None }
+        NotifyActor {
+            sender,
+            watched_entries: Vec::new(),
+            known_files: FxHashSet::default(),
+            watcher: None,
+        }
     }
     fn next_event(&self, receiver: &Receiver<Message>) -> Option<Event> {
         let watcher_receiver = self.watcher.as_ref().map(|(_, receiver)| receiver);
@@ -95,6 +115,7 @@ impl NotifyActor {
                         self.send(loader::Message::Progress { n_total, n_done: 0, config_version });
 
                         self.watched_entries.clear();
+                        self.known_files.clear();
 
                         for (i, entry) in config.load.into_iter().enumerate() {
                             let watch = config.watch.contains(&i);
@@ -102,6 +123,9 @@ impl NotifyActor {
                                 self.watched_entries.push(entry.clone());
                             }
                             let files = self.load_entry(entry, watch);
+                            if watch {
+                                self.record(&files);
+                            }
                             self.send(loader::Message::Loaded { files });
                             self.send(loader::Message::Progress {
                                 n_total,
@@ -117,43 +141,133 @@ impl NotifyActor {
                     }
                 },
                 Event::NotifyEvent(event) => {
-                    if let Some(event) = log_notify_error(event) {
-                        let files = event
-                            .paths
-                            .into_iter()
-                            .map(|path| AbsPathBuf::try_from(path).unwrap())
-                            .filter_map(|path| {
-                                let meta = fs::metadata(&path).ok()?;
-                                if meta.file_type().is_dir()
-                                    && self
-                                        .watched_entries
-                                        .iter()
-                                        .any(|entry| entry.contains_dir(&path))
-                                {
-                                    self.watch(path);
-                                    return None;
-                                }
-
-                                if !meta.file_type().is_file() {
-                                    return None;
-                                }
-                                if !self
-                                    .watched_entries
-                                    .iter()
-                                    .any(|entry| entry.contains_file(&path))
-                                {
-                                    return None;
-                                }
-
-                                let contents = read(&path);
-                                Some((path, contents))
-                            })
-                            .collect();
-                        self.send(loader::Message::Loaded { files });
+                    let mut events = Vec::new();
+                    events.extend(log_notify_error(event));
+                    self.debounce(&mut events);
+                    self.handle_notify_events(events);
+                }
+            }
+        }
+    }
+    /// Collects the events following `events` in quick succession, so that a
+    /// burst of changes, like the one caused by a `git checkout`, is reported
+    /// as a single batch.
+    fn debounce(&self, events: &mut Vec<notify::Event>) {
+        let receiver = match &self.watcher {
+            Some((_, receiver)) => receiver,
+            None => return,
+        };
+        let deadline = Instant::now() + MAX_DEBOUNCE;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            match receiver.recv_timeout(timeout.min(DEBOUNCE)) {
+                Ok(event) => events.extend(log_notify_error(event)),
+                Err(_) => break,
+            }
+        }
+    }
+    fn handle_notify_events(&mut self, events: Vec<notify::Event>) {
+        if events.iter().any(|event| event.flag() == Some(Flag::Rescan)) {
+            // The watcher dropped events, we can't know what changed.
+            self.rescan();
+            return;
+        }
+
+        let mut paths = events
+            .into_iter()
+            .flat_map(|event| event.paths)
+            .filter_map(|path| AbsPathBuf::try_from(path).ok())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths.dedup();
+
+        let mut files = Vec::new();
+        for path in paths {
+            // Check exclusion first, so that changes to excluded paths (think
+            // `target/`) never hit the file system.
+            let is_file = self.watched_entries.iter().any(|entry| entry.contains_file(&path));
+            let dirs = self
+                .watched_entries
+                .iter()
+                .filter_map(|entry| match entry {
+                    loader::Entry::Directories(dirs) if dirs.contains_dir(&path) => Some(dirs),
+                    _ => None,
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            if !is_file && dirs.is_empty() {
+                continue;
+            }
+
+            let meta = match fs::metadata(&path) {
+                Ok(it) => it,
+                Err(_) => {
+                    if is_file {
+                        files.push((path.clone(), None));
                     }
+                    // A removed directory takes its files with it, without
+                    // necessarily an event for each of them.
+                    files.extend(self.known_files_in(&path).into_iter().map(|it| (it, None)));
+                    continue;
+                }
+            };
+            if meta.file_type().is_dir() {
+                // Files may have been created before the watch for the new
+                // directory was set up, so walk it in full.
+                let mut loaded = Vec::new();
+                for dirs in dirs {
+                    let dirs = loader::Directories {
+                        extensions: dirs.extensions,
+                        include: vec![path.clone()],
+                        exclude: dirs.exclude,
+                    };
+                    loaded.extend(self.load_entry(loader::Entry::Directories(dirs), true));
                 }
+                let removed = self
+                    .known_files_in(&path)
+                    .into_iter()
+                    .filter(|known| loaded.iter().all(|(it, _)| it != known))
+                    .map(|it| (it, None))
+                    .collect::<Vec<_>>();
+                files.extend(loaded);
+                files.extend(removed);
+            } else if is_file && meta.file_type().is_file() {
+                let contents = read(&path);
+                files.push((path, contents));
             }
         }
+        if !files.is_empty() {
+            self.record(&files);
+            self.send(loader::Message::Loaded { files });
+        }
+    }
+    fn rescan(&mut self) {
+        tracing::warn!("file watcher dropped events, rescanning watched entries");
+        let entries = self.watched_entries.clone();
+        let mut files: Vec<_> =
+            entries.into_iter().flat_map(|entry| self.load_entry(entry, true)).collect();
+        let existing = files.iter().map(|(path, _)| path.clone()).collect::<FxHashSet<_>>();
+        let removed = self.known_files.iter().filter(|it| !existing.contains(*it)).cloned();
+        files.extend(removed.map(|it| (it, None)).collect::<Vec<_>>());
+        self.record(&files);
+        self.send(loader::Message::Loaded { files });
+    }
+    /// Updates `known_files` with files that are about to be reported.
+    fn record(&mut self, files: &[(AbsPathBuf, Option<Vec<u8>>)]) {
+        for (path, contents) in files {
+            if contents.is_some() {
+                self.known_files.insert(path.clone());
+            } else {
+                self.known_files.remove(path);
+            }
+        }
+    }
+    /// The known files below the directory `dir`.
+    fn known_files_in(&self, dir: &AbsPath) -> Vec<AbsPathBuf> {
+        self.known_files
+            .iter()
+            .filter(|it| it.starts_with(dir) && it.as_path() != dir)
+            .cloned()
+            .collect()
     }
     fn load_entry(
         &mut self,
@@ -229,3 +343,6 @@ fn read(path: &AbsPath) -> Option<Vec<u8>> {
 fn log_notify_error<T>(res: notify::Result<T>) -> Option<T> {
     res.map_err(|err| tracing::warn!("notify error: {}", err)).ok()
 }
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,251 @@
+//! These tests drive real file system changes, so they wait on the watcher
+//! with generous timeouts.
+use std::{
+    fs,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use crossbeam_channel::{unbounded, Receiver};
+use notify::{event::Flag, EventKind};
+use paths::AbsPathBuf;
+use vfs::loader::{self, Handle};
+
+use crate::{NotifyActor, NotifyHandle};
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+struct TestDir {
+    path: AbsPathBuf,
+}
+
+impl TestDir {
+    fn new() -> TestDir {
+        let temp_dir = std::env::temp_dir();
+        // The temp dir is a symlink on MacOS, while the watcher reports
+        // resolved paths.
+        #[cfg(target_os = "macos")]
+        let temp_dir = temp_dir.canonicalize().unwrap();
+
+        static CNT: AtomicUsize = AtomicUsize::new(0);
+        let cnt = CNT.fetch_add(1, Ordering::Relaxed);
+        let path = temp_dir.join(format!("vfs-notify-{}-{}", std::process::id(), cnt));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDir { path: AbsPathBuf::assert(path) }
+    }
+    fn path(&self, rel: &str) -> AbsPathBuf {
+        self.path.join(rel)
+    }
+    fn write(&self, rel: &str, text: &str) {
+        let path = self.path(rel);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    }
+    fn entry(&self) -> loader::Entry {
+        loader::Entry::local_cargo_package(self.path.clone())
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+type Files = Vec<(AbsPathBuf, Option<Vec<u8>>)>;
+
+struct Loader {
+    handle: NotifyHandle,
+    loaded: Receiver<Files>,
+}
+
+impl Loader {
+    fn spawn() -> Loader {
+        let (sender, loaded) = unbounded();
+        let handle = NotifyHandle::spawn(Box::new(move |msg| {
+            if let loader::Message::Loaded { files } = msg {
+                sender.send(files).unwrap();
+            }
+        }));
+        Loader { handle, loaded }
+    }
+    fn watch(&mut self, entry: loader::Entry) -> Files {
+        self.handle.set_config(loader::Config { version: 0, load: vec![entry], watch: vec![0] });
+        self.loaded.recv_timeout(TIMEOUT).unwrap()
+    }
+    /// Waits until `path` is reported, returning its contents and everything
+    /// else that has been reported before.
+    fn wait_for(&self, path: &AbsPathBuf) -> (Option<Vec<u8>>, Files) {
+        let deadline = Instant::now() + TIMEOUT;
+        let mut seen = Vec::new();
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let files = self
+                .loaded
+                .recv_timeout(timeout)
+                .unwrap_or_else(|_| panic!("{} was not reported, got {:?}", path.display(), seen));
+            for (file, contents) in files {
+                if &file == path {
+                    return (contents, seen);
+                }
+                seen.push((file, contents));
+            }
+        }
+    }
+}
+
+fn paths(files: &Files) -> Vec<AbsPathBuf> {
+    let mut res = files.iter().map(|(path, _)| path.clone()).collect::<Vec<_>>();
+    res.sort();
+    res
+}
+
+#[test]
+fn initial_load_respects_excludes() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    dir.write("src/README.md", "");
+    dir.write("target/debug/build/out.rs", "");
+    dir.write(".git/hooks.rs", "");
+
+    let mut loader = Loader::spawn();
+    let files = loader.watch(dir.entry());
+    assert_eq!(paths(&files), vec![dir.path("src/lib.rs")]);
+}
+
+#[test]
+fn reports_created_modified_and_removed_files() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    let mut loader = Loader::spawn();
+    loader.watch(dir.entry());
+
+    dir.write("src/new.rs", "fn new() {}");
+    let (contents, _) = loader.wait_for(&dir.path("src/new.rs"));
+    assert_eq!(contents.as_deref(), Some(&b"fn new() {}"[..]));
+
+    dir.write("src/lib.rs", "mod new;");
+    let (contents, _) = loader.wait_for(&dir.path("src/lib.rs"));
+    assert_eq!(contents.as_deref(), Some(&b"mod new;"[..]));
+
+    fs::remove_file(dir.path("src/new.rs")).unwrap();
+    let (contents, _) = loader.wait_for(&dir.path("src/new.rs"));
+    assert_eq!(contents, None);
+}
+
+#[test]
+fn new_directories_are_loaded_in_full() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    let mut loader = Loader::spawn();
+    loader.watch(dir.entry());
+
+    // Nested files are created before the watcher gets to watch `src/a`.
+    dir.write("src/a/b/c.rs", "struct C;");
+    let (contents, _) = loader.wait_for(&dir.path("src/a/b/c.rs"));
+    assert_eq!(contents.as_deref(), Some(&b"struct C;"[..]));
+
+    dir.write("src/a/b/d.rs", "struct D;");
+    let (contents, _) = loader.wait_for(&dir.path("src/a/b/d.rs"));
+    assert_eq!(contents.as_deref(), Some(&b"struct D;"[..]));
+}
+
+#[test]
+fn excluded_changes_are_ignored() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    dir.write("target/out.rs", "");
+    let mut loader = Loader::spawn();
+    loader.watch(dir.entry());
+
+    dir.write("target/out.rs", "changed");
+    dir.write("target/gen/new.rs", "");
+    dir.write("src/lib.rs", "changed");
+    let (_, seen) = loader.wait_for(&dir.path("src/lib.rs"));
+    assert!(seen.iter().all(|(path, _)| !path.starts_with(&dir.path("target"))), "{:?}", seen);
+}
+
+#[test]
+fn bursts_of_changes_are_batched() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    let mut loader = Loader::spawn();
+    loader.watch(dir.entry());
+
+    for i in 0..20 {
+        dir.write(&format!("src/m{}.rs", i), "");
+    }
+    dir.write("src/last.rs", "");
+    let mut batches = 0;
+    let mut reported = Vec::new();
+    while !reported.contains(&dir.path("src/last.rs")) {
+        let files = loader.loaded.recv_timeout(TIMEOUT).unwrap();
+        batches += 1;
+        reported.extend(files.into_iter().map(|(path, _)| path));
+    }
+    assert!(batches < 21, "{} batches", batches);
+}
+
+#[test]
+fn rescans_after_dropped_events() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    let (sender, loaded) = unbounded();
+    let mut actor = NotifyActor::new(Box::new(move |msg| {
+        if let loader::Message::Loaded { files } = msg {
+            sender.send(files).unwrap();
+        }
+    }));
+    actor.watched_entries.push(dir.entry());
+
+    dir.write("src/missed.rs", "");
+    actor.handle_notify_events(vec![notify::Event::new(EventKind::Other).set_flag(Flag::Rescan)]);
+    let files = loaded.try_recv().unwrap();
+    assert_eq!(paths(&files), vec![dir.path("src/lib.rs"), dir.path("src/missed.rs")]);
+}
+
+fn actor_with_entry(entry: loader::Entry) -> (NotifyActor, Receiver<Files>) {
+    let (sender, loaded) = unbounded();
+    let mut actor = NotifyActor::new(Box::new(move |msg| {
+        if let loader::Message::Loaded { files } = msg {
+            sender.send(files).unwrap();
+        }
+    }));
+    let files = actor.load_entry(entry.clone(), false);
+    actor.record(&files);
+    actor.watched_entries.push(entry);
+    (actor, loaded)
+}
+
+#[test]
+fn rescan_reports_removed_files() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    dir.write("src/gone.rs", "");
+    let (mut actor, loaded) = actor_with_entry(dir.entry());
+
+    fs::remove_file(dir.path("src/gone.rs")).unwrap();
+    actor.handle_notify_events(vec![notify::Event::new(EventKind::Other).set_flag(Flag::Rescan)]);
+    let files = loaded.try_recv().unwrap();
+    let removed = files.iter().find(|(path, _)| path == &dir.path("src/gone.rs"));
+    assert_eq!(removed, Some(&(dir.path("src/gone.rs"), None)));
+}
+
+#[test]
+fn removed_directories_report_their_files() {
+    let dir = TestDir::new();
+    dir.write("src/lib.rs", "");
+    dir.write("src/a/b.rs", "");
+    dir.write("src/a/c/d.rs", "");
+    let (mut actor, loaded) = actor_with_entry(dir.entry());
+
+    // Only the directory itself gets an event.
+    fs::remove_dir_all(dir.path("src/a")).unwrap();
+    actor.handle_notify_events(vec![
+        notify::Event::new(EventKind::Any).add_path(dir.path("src/a").into())
+    ]);
+    let files = loaded.try_recv().unwrap();
+    assert_eq!(paths(&files), vec![dir.path("src/a/b.rs"), dir.path("src/a/c/d.rs")]);
+    assert!(files.iter().all(|(_, contents)| contents.is_none()));
+}
@@ -753,7 +753,14 @@ export function copyRunCommandLine(ctx: Ctx) {
     return async () => {
         const item = await selectRunnable(ctx, prevRunnable);
         if (!item) return;
-        const args = createArgs(item.runnable);
+        const runnable = item.runnable;
+        if (runnable.kind === "shell") {
+            const commandLine = [runnable.args.program, ...runnable.args.args].join(" ");
+            await vscode.env.clipboard.writeText(commandLine);
+            await vscode.window.showInformationMessage("Command line copied to the clipboard.");
+            return;
+        }
+        const args = createArgs(runnable);
         const commandLine = ["cargo", ...args].join(" ");
         await vscode.env.clipboard.writeText(commandLine);
         await vscode.window.showInformationMessage("Cargo invocation copied to the clipboard.");
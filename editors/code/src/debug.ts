@@ -8,7 +8,7 @@ import { Ctx } from "./ctx";
 import { prepareEnv } from "./run";
 
 const debugOutput = vscode.window.createOutputChannel("Debug");
-type DebugConfigProvider = (config: ra.Runnable, executable: string, env: Record<string, string>, sourceFileMap?: Record<string, string>) => vscode.DebugConfiguration;
+type DebugConfigProvider = (config: ra.CargoRunnable, executable: string, env: Record<string, string>, sourceFileMap?: Record<string, string>) => vscode.DebugConfiguration;
 
 export async function makeDebugConfig(ctx: Ctx, runnable: ra.Runnable): Promise<void> {
     const scope = ctx.activeRustEditor?.document.uri;
@@ -60,6 +60,12 @@ async function getDebugConfiguration(ctx: Ctx, runnable: ra.Runnable): Promise<v
     const editor = ctx.activeRustEditor;
     if (!editor) return;
 
+    if (runnable.kind !== "cargo") {
+        await vscode.window.showErrorMessage(`Can't debug '${runnable.label}', only cargo targets can be debugged.`);
+        return;
+    }
+    const workspaceRoot = runnable.args.workspaceRoot;
+
     const knownEngines: Record<string, DebugConfigProvider> = {
         "vadimcn.vscode-lldb": getLldbDebugConfig,
         "ms-vscode.cpptools": getCppvsDebugConfig
@@ -91,9 +97,9 @@ async function getDebugConfiguration(ctx: Ctx, runnable: ra.Runnable): Promise<v
     const workspaceFolders = vscode.workspace.workspaceFolders!;
     const isMultiFolderWorkspace = workspaceFolders.length > 1;
     const firstWorkspace = workspaceFolders[0];
-    const workspace = !isMultiFolderWorkspace || !runnable.args.workspaceRoot ?
+    const workspace = !isMultiFolderWorkspace || !workspaceRoot ?
         firstWorkspace :
-        workspaceFolders.find(w => runnable.args.workspaceRoot?.includes(w.uri.fsPath)) || firstWorkspace;
+        workspaceFolders.find(w => workspaceRoot.includes(w.uri.fsPath)) || firstWorkspace;
 
     const wsFolder = path.normalize(workspace.uri.fsPath);
     const workspaceQualifier = isMultiFolderWorkspace ? `:${workspace.name}` : '';
@@ -135,7 +141,7 @@ async function getDebugConfiguration(ctx: Ctx, runnable: ra.Runnable): Promise<v
     return debugConfig;
 }
 
-async function getDebugExecutable(runnable: ra.Runnable): Promise<string> {
+async function getDebugExecutable(runnable: ra.CargoRunnable): Promise<string> {
     const cargo = new Cargo(runnable.args.workspaceRoot || '.', debugOutput);
     const executable = await cargo.executableFromArgs(runnable.args.cargoArgs);
 
@@ -143,7 +149,7 @@ async function getDebugExecutable(runnable: ra.Runnable): Promise<string> {
     return executable;
 }
 
-function getLldbDebugConfig(runnable: ra.Runnable, executable: string, env: Record<string, string>, sourceFileMap?: Record<string, string>): vscode.DebugConfiguration {
+function getLldbDebugConfig(runnable: ra.CargoRunnable, executable: string, env: Record<string, string>, sourceFileMap?: Record<string, string>): vscode.DebugConfiguration {
     return {
         type: "lldb",
         request: "launch",
@@ -157,7 +163,7 @@ function getLldbDebugConfig(runnable: ra.Runnable, executable: string, env: Reco
     };
 }
 
-function getCppvsDebugConfig(runnable: ra.Runnable, executable: string, env: Record<string, string>, sourceFileMap?: Record<string, string>): vscode.DebugConfiguration {
+function getCppvsDebugConfig(runnable: ra.CargoRunnable, executable: string, env: Record<string, string>, sourceFileMap?: Record<string, string>): vscode.DebugConfiguration {
     return {
         type: (os.platform() === "win32") ? "cppvsdbg" : "cppdbg",
         request: "launch",
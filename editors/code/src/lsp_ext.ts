@@ -77,7 +77,9 @@ export interface RunnablesParams {
     position: lc.Position | null;
 }
 
-export interface Runnable {
+export type Runnable = CargoRunnable | ShellRunnable;
+
+export interface CargoRunnable {
     label: string;
     location?: lc.LocationLink;
     kind: "cargo";
@@ -90,6 +92,17 @@ export interface Runnable {
         overrideCargo?: string;
     };
 }
+
+export interface ShellRunnable {
+    label: string;
+    location?: lc.LocationLink;
+    kind: "shell";
+    args: {
+        program: string;
+        args: string[];
+        cwd: string;
+    };
+}
 export const runnables = new lc.RequestType<RunnablesParams, Runnable[], void>("experimental/runnables");
 
 export interface TestInfo {
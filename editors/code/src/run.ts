@@ -36,7 +36,7 @@ export async function selectRunnable(ctx: Ctx, prevRunnable?: RunnableQuickPick,
             continue;
         }
 
-        if (debuggeeOnly && (r.label.startsWith('doctest') || r.label.startsWith('cargo'))) {
+        if (debuggeeOnly && (r.kind !== "cargo" || r.label.startsWith('doctest') || r.label.startsWith('cargo'))) {
             continue;
         }
         items.push(new RunnableQuickPick(r));
@@ -99,7 +99,7 @@ export class RunnableQuickPick implements vscode.QuickPickItem {
 export function prepareEnv(runnable: ra.Runnable, runnableEnvCfg: RunnableEnvCfg): Record<string, string> {
     const env: Record<string, string> = { "RUST_BACKTRACE": "short" };
 
-    if (runnable.args.expectTest) {
+    if (runnable.kind === "cargo" && runnable.args.expectTest) {
         env["UPDATE_EXPECT"] = "1";
     }
 
@@ -121,11 +121,21 @@ export function prepareEnv(runnable: ra.Runnable, runnableEnvCfg: RunnableEnvCfg
 }
 
 export async function createTask(runnable: ra.Runnable, config: Config): Promise<vscode.Task> {
-    if (runnable.kind !== "cargo") {
-        // rust-analyzer supports only one kind, "cargo"
-        // do not use tasks.TASK_TYPE here, these are completely different meanings.
+    // eslint-disable-next-line @typescript-eslint/no-unnecessary-type-assertion
+    const target = vscode.workspace.workspaceFolders![0]; // safe, see main activate()
 
-        throw `Unexpected runnable kind: ${runnable.kind}`;
+    if (runnable.kind === "shell") {
+        // Comes from the runnable templates in `rust-project.json`, run it as is.
+        const definition: tasks.CargoTaskDefinition = {
+            type: tasks.TASK_TYPE,
+            cwd: runnable.args.cwd,
+            env: prepareEnv(runnable, config.runnableEnv),
+        };
+        const exec = new vscode.ProcessExecution(runnable.args.program, runnable.args.args, definition);
+        const shellTask = new vscode.Task(definition, target, runnable.label, tasks.TASK_SOURCE, exec, ['$rustc']);
+        shellTask.presentationOptions.clear = true;
+        shellTask.presentationOptions.focus = false;
+        return shellTask;
     }
 
     const args = createArgs(runnable);
@@ -139,8 +149,6 @@ export async function createTask(runnable: ra.Runnable, config: Config): Promise
         overrideCargo: runnable.args.overrideCargo,
     };
 
-    // eslint-disable-next-line @typescript-eslint/no-unnecessary-type-assertion
-    const target = vscode.workspace.workspaceFolders![0]; // safe, see main activate()
     const cargoTask = await tasks.buildCargoTask(target, definition, runnable.label, args, config.cargoRunner, true);
 
     cargoTask.presentationOptions.clear = true;
@@ -151,7 +159,7 @@ export async function createTask(runnable: ra.Runnable, config: Config): Promise
     return cargoTask;
 }
 
-export function createArgs(runnable: ra.Runnable): string[] {
+export function createArgs(runnable: ra.CargoRunnable): string[] {
     const args = [...runnable.args.cargoArgs]; // should be a copy!
     if (runnable.args.cargoExtraArgs) {
         args.push(...runnable.args.cargoExtraArgs); // Append user-specified cargo options.